@@ -0,0 +1,23 @@
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS password_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_user_id ON password_history(user_id);
+
+CREATE TABLE IF NOT EXISTS password_policy (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    min_length INTEGER NOT NULL DEFAULT 10,
+    history_depth INTEGER NOT NULL DEFAULT 5,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO password_policy (id) VALUES (1);
+
+-- Demo accounts were seeded with a shared default password
+UPDATE users SET must_change_password = 1 WHERE id IN ('user_admin', 'user_teacher', 'user_finance');
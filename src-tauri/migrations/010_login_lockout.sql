@@ -0,0 +1,15 @@
+ALTER TABLE users ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN locked_until DATETIME;
+
+CREATE TABLE IF NOT EXISTS login_failures (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    email TEXT NOT NULL,
+    user_id TEXT,
+    attempted_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_failures_attempted_at ON login_failures(attempted_at);
+
+-- Non-login actor for audit entries not attributable to a user (e.g. unknown emails)
+INSERT OR IGNORE INTO users (id, email, password_hash, role, active)
+VALUES ('system', 'system@local', '!', 'ADMIN', 0);
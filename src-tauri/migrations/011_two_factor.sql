@@ -0,0 +1,24 @@
+ALTER TABLE users ADD COLUMN totp_secret TEXT;
+ALTER TABLE users ADD COLUMN totp_enabled BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN totp_last_step INTEGER;
+
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    code_hash TEXT NOT NULL,
+    used_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_recovery_codes_user_id ON recovery_codes(user_id);
+
+CREATE TABLE IF NOT EXISTS two_factor_policy (
+    role TEXT PRIMARY KEY,
+    required BOOLEAN NOT NULL DEFAULT 0
+);
+
+INSERT OR IGNORE INTO two_factor_policy (role, required) VALUES
+    ('ADMIN', 1),
+    ('MANAGEMENT_FINANCE', 1),
+    ('TEACHER', 0);
@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN staff_id TEXT REFERENCES staff(id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_staff_id ON users(staff_id) WHERE staff_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_staff_classes_staff_id ON staff_classes(staff_id);
+
+-- Link the demo teacher account to its staff record and homeroom class
+UPDATE users SET staff_id = 'staff_01'
+WHERE id = 'user_teacher' AND EXISTS (SELECT 1 FROM staff WHERE id = 'staff_01');
+
+UPDATE classes SET homeroom_teacher_id = 'staff_01'
+WHERE id = 'class_10a' AND homeroom_teacher_id IS NULL AND EXISTS (SELECT 1 FROM staff WHERE id = 'staff_01');
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS permissions (
+    key TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role TEXT NOT NULL,
+    permission_key TEXT NOT NULL,
+    PRIMARY KEY(role, permission_key),
+    FOREIGN KEY(permission_key) REFERENCES permissions(key)
+);
@@ -0,0 +1,62 @@
+-- Rebuilds users to drop the role CHECK. Dropping a referenced table needs
+-- foreign keys off, so init_db runs migrations with enforcement disabled.
+
+CREATE TABLE IF NOT EXISTS roles (
+    name TEXT PRIMARY KEY CHECK (name <> '' AND name NOT GLOB '*[^A-Z0-9_]*'),
+    description TEXT NOT NULL DEFAULT '',
+    built_in BOOLEAN NOT NULL DEFAULT 0,
+    -- Members only see students in the classes of their linked staff record
+    class_scoped BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO roles (name, description, built_in, class_scoped) VALUES
+    ('ADMIN', 'Full access to every area', 1, 0),
+    ('TEACHER', 'Education records for assigned classes', 1, 1),
+    ('MANAGEMENT_FINANCE', 'Fee plans, invoices and payments', 1, 0);
+
+CREATE TABLE users_new (
+    id TEXT PRIMARY KEY,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL REFERENCES roles(name),
+    active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login_at DATETIME,
+    must_change_password BOOLEAN NOT NULL DEFAULT 0,
+    failed_login_count INTEGER NOT NULL DEFAULT 0,
+    locked_until DATETIME,
+    totp_secret TEXT,
+    totp_enabled BOOLEAN NOT NULL DEFAULT 0,
+    totp_last_step INTEGER,
+    staff_id TEXT REFERENCES staff(id)
+);
+
+INSERT INTO users_new (
+    id, email, password_hash, role, active, created_at, last_login_at, must_change_password,
+    failed_login_count, locked_until, totp_secret, totp_enabled, totp_last_step, staff_id
+)
+SELECT
+    id, email, password_hash, role, active, created_at, last_login_at, must_change_password,
+    failed_login_count, locked_until, totp_secret, totp_enabled, totp_last_step, staff_id
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_staff_id ON users(staff_id) WHERE staff_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_users_role ON users(role);
+
+CREATE TABLE role_permissions_new (
+    role TEXT NOT NULL,
+    permission_key TEXT NOT NULL,
+    PRIMARY KEY(role, permission_key),
+    FOREIGN KEY(role) REFERENCES roles(name) ON DELETE CASCADE,
+    FOREIGN KEY(permission_key) REFERENCES permissions(key)
+);
+
+INSERT INTO role_permissions_new (role, permission_key)
+SELECT role, permission_key FROM role_permissions WHERE role IN (SELECT name FROM roles);
+
+DROP TABLE role_permissions;
+ALTER TABLE role_permissions_new RENAME TO role_permissions;
@@ -0,0 +1,2 @@
+-- Last time the session holder re-confirmed their password or 2FA code
+ALTER TABLE sessions ADD COLUMN reauthenticated_at DATETIME;
@@ -0,0 +1,5 @@
+-- Support filtered audit queries; id is the pagination cursor so each index ends with it
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log(actor_user_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity_type, entity_id, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action, id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
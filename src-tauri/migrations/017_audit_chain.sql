@@ -0,0 +1,28 @@
+-- Each entry's hash covers its content and the previous entry's hash.
+-- Rows written before this migration are sealed in id order at startup.
+ALTER TABLE audit_log ADD COLUMN prev_hash TEXT;
+ALTER TABLE audit_log ADD COLUMN entry_hash TEXT;
+
+-- Sealed entries are immutable through the app; direct edits break the chain
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update
+BEFORE UPDATE ON audit_log
+WHEN OLD.entry_hash IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log entries are immutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete
+BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log entries are immutable');
+END;
+
+-- Signed snapshots of the chain head; the key lives outside the database
+CREATE TABLE IF NOT EXISTS audit_checkpoints (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    last_entry_id INTEGER NOT NULL,
+    last_entry_hash TEXT NOT NULL,
+    entry_count INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    signature TEXT NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Sensitive reads that are written to the audit log. Repeat reads of the same
+-- record by the same user within dedup_minutes count as one view.
+CREATE TABLE IF NOT EXISTS read_audit_policy (
+    action TEXT PRIMARY KEY,
+    description TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    dedup_minutes INTEGER NOT NULL DEFAULT 15 CHECK (dedup_minutes BETWEEN 0 AND 1440),
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO read_audit_policy (action, description) VALUES
+    ('VIEW_STUDENT_PROFILE', 'Full student profile incl. address and guardian contacts'),
+    ('VIEW_ATTENDANCE', 'A student''s attendance records'),
+    ('VIEW_ASSESSMENTS', 'A student''s assessments and grades'),
+    ('VIEW_INVOICES', 'A student''s invoices'),
+    ('VIEW_TRAJECTORY', 'A student''s computed trajectory and risk');
+
+-- De-duplication looks up a user's recent reads of one record
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor_entity ON audit_log(actor_user_id, entity_type, entity_id, action, created_at);
@@ -0,0 +1,45 @@
+-- NULL keeps audit entries forever
+CREATE TABLE IF NOT EXISTS audit_retention_policy (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    retention_years INTEGER CHECK (retention_years IS NULL OR retention_years BETWEEN 1 AND 100),
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO audit_retention_policy (id) VALUES (1);
+
+-- One row per compressed archive file. The signature (same key as checkpoints)
+-- covers the archived id range and the hash of its last entry, which anchors
+-- the chain of the entries that remain.
+CREATE TABLE IF NOT EXISTS audit_archives (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_name TEXT NOT NULL,
+    file_sha256 TEXT NOT NULL,
+    first_entry_id INTEGER NOT NULL,
+    last_entry_id INTEGER NOT NULL,
+    last_entry_hash TEXT NOT NULL,
+    entry_count INTEGER NOT NULL,
+    cutoff TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    signature TEXT NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS audit_archives_no_update
+BEFORE UPDATE ON audit_archives
+BEGIN
+    SELECT RAISE(ABORT, 'audit_archives entries are immutable');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_archives_no_delete
+BEFORE DELETE ON audit_archives
+BEGIN
+    SELECT RAISE(ABORT, 'audit_archives entries are immutable');
+END;
+
+-- Entries may only be deleted once an archive covering them has been recorded
+DROP TRIGGER IF EXISTS audit_log_no_delete;
+CREATE TRIGGER audit_log_no_delete
+BEFORE DELETE ON audit_log
+WHEN OLD.id > (SELECT COALESCE(MAX(last_entry_id), 0) FROM audit_archives)
+BEGIN
+    SELECT RAISE(ABORT, 'audit_log entries are immutable');
+END;
@@ -0,0 +1,9 @@
+-- fail_closed: a change whose audit entry cannot be written is rolled back.
+-- Otherwise the change commits and the failure is only reported.
+CREATE TABLE IF NOT EXISTS audit_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    fail_closed BOOLEAN NOT NULL DEFAULT 1,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO audit_settings (id) VALUES (1);
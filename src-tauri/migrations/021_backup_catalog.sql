@@ -0,0 +1,17 @@
+-- Every backup the app has taken and verified. Rows are kept when the file is
+-- later removed so the history of who copied the data remains.
+CREATE TABLE IF NOT EXISTS backup_catalog (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL CHECK (kind IN ('AUTO', 'MANUAL')),
+    file_name TEXT NOT NULL,
+    file_path TEXT NOT NULL UNIQUE,
+    size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    -- Latest migration applied in the copied database
+    schema_version INTEGER,
+    created_by TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(created_by) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_backup_catalog_created_at ON backup_catalog(created_at);
@@ -0,0 +1,2 @@
+-- Encrypted archives also contain the photo directories
+ALTER TABLE backup_catalog ADD COLUMN encrypted BOOLEAN NOT NULL DEFAULT 0;
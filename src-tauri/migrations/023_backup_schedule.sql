@@ -0,0 +1,15 @@
+-- Set when a backup file is deleted by rotation or by an administrator; the row stays
+ALTER TABLE backup_catalog ADD COLUMN deleted_at DATETIME;
+
+-- Automatic backups while the app runs, thinned out grandfather-father-son style.
+-- NULL interval_hours turns the scheduler off; start-up backups still happen.
+CREATE TABLE IF NOT EXISTS backup_schedule (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    interval_hours INTEGER CHECK (interval_hours IS NULL OR interval_hours BETWEEN 1 AND 720),
+    keep_daily INTEGER NOT NULL DEFAULT 7 CHECK (keep_daily BETWEEN 0 AND 366),
+    keep_weekly INTEGER NOT NULL DEFAULT 4 CHECK (keep_weekly BETWEEN 0 AND 260),
+    keep_monthly INTEGER NOT NULL DEFAULT 12 CHECK (keep_monthly BETWEEN 0 AND 240),
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT OR IGNORE INTO backup_schedule (id, interval_hours) VALUES (1, 24);
@@ -0,0 +1,18 @@
+-- Foreign keys are enforced on every connection from this version on. Rows that
+-- already break a constraint are recorded here for review before that happens;
+-- they stay readable, but writes that touch the broken reference are rejected.
+-- Migrations run with enforcement off, so this sees the data as it was.
+CREATE TABLE IF NOT EXISTS foreign_key_violations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    table_name TEXT NOT NULL,
+    row_id INTEGER,
+    parent_table TEXT NOT NULL,
+    -- Position of the constraint in PRAGMA foreign_key_list(table_name)
+    constraint_index INTEGER NOT NULL,
+    detected_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Set by a later check that no longer finds the violation
+    resolved_at DATETIME
+);
+
+INSERT INTO foreign_key_violations (table_name, row_id, parent_table, constraint_index)
+SELECT "table", rowid, parent, fkid FROM pragma_foreign_key_check;
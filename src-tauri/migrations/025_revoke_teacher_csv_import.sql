@@ -0,0 +1,4 @@
+-- CSV import was admin-only before the permission registry, which granted it to
+-- teachers by mistake. Existing databases got that grant when the key was first
+-- registered; fresh ones are seeded with the admin-only default.
+DELETE FROM role_permissions WHERE role = 'TEACHER' AND permission_key = 'students.import';
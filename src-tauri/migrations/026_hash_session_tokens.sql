@@ -0,0 +1,5 @@
+-- Sessions are looked up by the SHA-256 of their token, so a copy of the database
+-- or of a backup no longer hands out live sessions. Plaintext tokens cannot be
+-- hashed here, so existing sessions end and everyone signs in again once.
+DELETE FROM sessions;
+ALTER TABLE sessions RENAME COLUMN token TO token_hash;
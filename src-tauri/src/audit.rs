@@ -1,10 +1,21 @@
 use crate::db::DbPool;
-use crate::models::Role;
-use crate::auth::check_auth;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::permissions::SYSTEM_AUDIT;
+use crate::audit_chain::append_entry;
+use crate::audit_event::{parse_metadata, AuditEvent, FieldChange};
 use tauri::State;
 use serde::{Serialize, Deserialize};
-use sqlx::FromRow;
+use serde_json::{Map, Value};
+use sqlx::{Acquire, Executor, FromRow, QueryBuilder, Sqlite, Transaction};
 use chrono::NaiveDateTime;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+const EXPORT_BATCH_SIZE: i64 = 1000;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct AuditLogEntry {
@@ -17,7 +28,83 @@ pub struct AuditLogEntry {
     pub created_at: NaiveDateTime,
 }
 
-// Helper to log actions from other commands
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditLogRecord {
+    pub id: i64,
+    pub actor_user_id: String,
+    pub actor_email: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub metadata_json: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// All filters are optional and combined with AND; the date range is [from, to)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuditLogFilter {
+    pub actor_user_id: Option<String>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPage {
+    pub entries: Vec<AuditLogRecord>,
+    // Rows matching the filter across all pages
+    pub total: i64,
+    // Pass back as `cursor` to fetch the next (older) page; None on the last page
+    pub next_cursor: Option<i64>,
+}
+
+// Complete row incl. chain hashes, so inspectors can re-verify what they were given.
+// `created_at` is the stored text, exactly as it was hashed.
+#[derive(Debug, Serialize, FromRow)]
+pub struct AuditExportRow {
+    pub id: i64,
+    pub created_at: String,
+    pub actor_user_id: String,
+    pub actor_email: Option<String>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub metadata_json: Option<String>,
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
+}
+
+pub const EXPORT_COLUMNS: &str = r#"
+    a.id, CAST(a.created_at AS TEXT) AS created_at, a.actor_user_id, u.email AS actor_email,
+    a.action, a.entity_type, a.entity_id, a.metadata_json, a.prev_hash, a.entry_hash
+"#;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditExportFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntityHistoryEntry {
+    pub id: i64,
+    pub actor_user_id: String,
+    pub actor_email: Option<String>,
+    pub action: String,
+    pub created_at: NaiveDateTime,
+    pub changes: Vec<FieldChange>,
+    pub context: Map<String, Value>,
+}
+
+// Inactive pseudo-user (seeded by migration) for entries with no human actor
+pub const SYSTEM_USER_ID: &str = "system";
+
+// Logs an event that has no transaction of its own, e.g. a failed login or an
+// export. Follows the same fail-closed rule as log_audit_tx, so callers must
+// propagate the error rather than ignore it.
 pub async fn log_audit(
     pool: &DbPool,
     actor_user_id: &str,
@@ -25,18 +112,71 @@ pub async fn log_audit(
     entity_type: &str,
     entity_id: &str,
     metadata: Option<&str>
-) -> Result<(), String> {
-    sqlx::query(
-        "INSERT INTO audit_log (actor_user_id, action, entity_type, entity_id, metadata_json) VALUES (?, ?, ?, ?, ?)"
-    )
-    .bind(actor_user_id)
-    .bind(action)
-    .bind(entity_type)
-    .bind(entity_id)
-    .bind(metadata)
-    .execute(pool)
-    .await
-    .map_err(|e| e.to_string())?;
+) -> AppResult<()> {
+    // Chained entries must be appended one at a time, so each write is its own transaction
+    let mut tx = pool.begin().await?;
+    log_audit_tx(&mut tx, actor_user_id, action, entity_type, entity_id, metadata).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Staging file an export is written to before it is audited
+pub fn partial_export_path(export_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.partial", export_path))
+}
+
+// Moves a finished export into place only once it has been audited, so an export
+// that cannot be audited leaves nothing behind
+pub async fn publish_export(
+    pool: &DbPool,
+    actor_user_id: &str,
+    event: AuditEvent,
+    partial: &Path,
+    export_path: &str
+) -> AppResult<()> {
+    if let Err(e) = event.log(pool, actor_user_id).await {
+        let _ = fs::remove_file(partial);
+        return Err(e);
+    }
+    fs::rename(partial, export_path)?;
+    Ok(())
+}
+
+pub async fn audit_fail_closed<'e, E: Executor<'e, Database = Sqlite>>(executor: E) -> AppResult<bool> {
+    // A missing row means the secure default
+    let fail_closed: Option<bool> = sqlx::query_scalar("SELECT fail_closed FROM audit_settings WHERE id = 1")
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(fail_closed.unwrap_or(true))
+}
+
+// Writes the entry inside the caller's transaction, so it commits or rolls back
+// together with the change it describes. In fail-closed mode an error here must
+// abort the caller; otherwise the write is isolated in a savepoint and a failure
+// is only reported.
+pub async fn log_audit_tx(
+    tx: &mut Transaction<'_, Sqlite>,
+    actor_user_id: &str,
+    action: &str,
+    entity_type: &str,
+    entity_id: &str,
+    metadata: Option<&str>
+) -> AppResult<()> {
+    if audit_fail_closed(&mut **tx).await? {
+        append_entry(tx, actor_user_id, action, entity_type, entity_id, metadata).await?;
+        return Ok(());
+    }
+
+    let mut savepoint = tx.begin().await?;
+    match append_entry(&mut savepoint, actor_user_id, action, entity_type, entity_id, metadata).await {
+        Ok(_) => savepoint.commit().await?,
+        Err(e) => {
+            savepoint.rollback().await?;
+            eprintln!("Audit entry {} for {} {} was not written: {}", action, entity_type, entity_id, e);
+        }
+    }
 
     Ok(())
 }
@@ -44,11 +184,11 @@ pub async fn log_audit(
 #[tauri::command]
 pub async fn get_audit_logs(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     limit: Option<i64>
-) -> Result<Vec<AuditLogEntry>, String> {
+) -> AppResult<Vec<AuditLogEntry>> {
     // Only Admin can view audit logs
-    check_auth(&pool, &user_id, &[Role::Admin]).await?;
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
 
     let limit_val = limit.unwrap_or(100);
 
@@ -57,8 +197,245 @@ pub async fn get_audit_logs(
     )
     .bind(limit_val)
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(logs)
 }
+
+fn push_filters<'a>(query: &mut QueryBuilder<'a, Sqlite>, filter: &'a AuditLogFilter) {
+    if let Some(actor) = &filter.actor_user_id {
+        query.push(" AND a.actor_user_id = ").push_bind(actor);
+    }
+    if let Some(action) = &filter.action {
+        query.push(" AND a.action = ").push_bind(action);
+    }
+    if let Some(entity_type) = &filter.entity_type {
+        query.push(" AND a.entity_type = ").push_bind(entity_type);
+    }
+    if let Some(entity_id) = &filter.entity_id {
+        query.push(" AND a.entity_id = ").push_bind(entity_id);
+    }
+    if let Some(from) = &filter.from {
+        query.push(" AND a.created_at >= ").push_bind(from);
+    }
+    if let Some(to) = &filter.to {
+        query.push(" AND a.created_at < ").push_bind(to);
+    }
+}
+
+fn validate_filter(filter: &AuditLogFilter) -> AppResult<()> {
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from >= to {
+            return Err(AppError::invalid_field("to", "End of the date range must be after its start"));
+        }
+    }
+    Ok(())
+}
+
+// Writes one JSON object per line
+pub fn write_jsonl<W: Write>(writer: &mut W, row: &AuditExportRow) -> AppResult<()> {
+    serde_json::to_writer(&mut *writer, row)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+// Newest-first audit entries matching `filter`, paginated by id
+#[tauri::command]
+pub async fn query_audit_logs(
+    pool: State<'_, DbPool>,
+    token: String,
+    filter: Option<AuditLogFilter>,
+    cursor: Option<i64>,
+    limit: Option<i64>
+) -> AppResult<AuditLogPage> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+
+    let filter = filter.unwrap_or_default();
+    validate_filter(&filter)?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let mut count_query = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM audit_log a WHERE 1 = 1");
+    push_filters(&mut count_query, &filter);
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(&*pool)
+        .await?;
+
+    let mut page_query = QueryBuilder::<Sqlite>::new(
+        r#"
+        SELECT a.id, a.actor_user_id, u.email AS actor_email, a.action, a.entity_type,
+               a.entity_id, a.metadata_json, a.created_at
+        FROM audit_log a
+        LEFT JOIN users u ON u.id = a.actor_user_id
+        WHERE 1 = 1
+        "#
+    );
+    push_filters(&mut page_query, &filter);
+    if let Some(cursor) = cursor {
+        page_query.push(" AND a.id < ").push_bind(cursor);
+    }
+    // One extra row tells us whether another page follows
+    page_query.push(" ORDER BY a.id DESC LIMIT ").push_bind(limit + 1);
+
+    let mut entries: Vec<AuditLogRecord> = page_query
+        .build_query_as()
+        .fetch_all(&*pool)
+        .await?;
+
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().map(|e| e.id)
+    } else {
+        None
+    };
+
+    Ok(AuditLogPage { entries, total, next_cursor })
+}
+
+// Every audited event for one record, oldest first, with its field-level changes
+#[tauri::command]
+pub async fn get_entity_history(
+    pool: State<'_, DbPool>,
+    token: String,
+    entity_type: String,
+    entity_id: String
+) -> AppResult<Vec<EntityHistoryEntry>> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+
+    let records = sqlx::query_as::<_, AuditLogRecord>(
+        r#"
+        SELECT a.id, a.actor_user_id, u.email AS actor_email, a.action, a.entity_type,
+               a.entity_id, a.metadata_json, a.created_at
+        FROM audit_log a
+        LEFT JOIN users u ON u.id = a.actor_user_id
+        WHERE a.entity_type = ? AND a.entity_id = ?
+        ORDER BY a.id
+        "#
+    )
+    .bind(entity_type.to_uppercase())
+    .bind(&entity_id)
+    .fetch_all(&*pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| {
+            let metadata = parse_metadata(r.metadata_json.as_deref());
+            EntityHistoryEntry {
+                id: r.id,
+                actor_user_id: r.actor_user_id,
+                actor_email: r.actor_email,
+                action: r.action,
+                created_at: r.created_at,
+                changes: metadata.changes,
+                context: metadata.context,
+            }
+        })
+        .collect())
+}
+
+// Writes every entry matching `filter` to `export_path`, oldest first
+#[tauri::command]
+pub async fn export_audit_logs(
+    pool: State<'_, DbPool>,
+    token: String,
+    filter: Option<AuditLogFilter>,
+    format: AuditExportFormat,
+    export_path: String
+) -> AppResult<i64> {
+    let user = check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+
+    let filter = filter.unwrap_or_default();
+    validate_filter(&filter)?;
+
+    let partial = partial_export_path(&export_path);
+    let file = BufWriter::new(File::create(&partial)?);
+    let (mut csv_writer, mut jsonl_writer) = match format {
+        AuditExportFormat::Csv => (Some(csv::Writer::from_writer(file)), None),
+        AuditExportFormat::Jsonl => (None, Some(file)),
+    };
+
+    let mut exported = 0;
+    let mut last_id = 0;
+    loop {
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM audit_log a LEFT JOIN users u ON u.id = a.actor_user_id WHERE a.id > ",
+            EXPORT_COLUMNS
+        ));
+        query.push_bind(last_id);
+        push_filters(&mut query, &filter);
+        query.push(" ORDER BY a.id LIMIT ").push_bind(EXPORT_BATCH_SIZE);
+
+        let rows: Vec<AuditExportRow> = query
+            .build_query_as()
+            .fetch_all(&*pool)
+            .await?;
+
+        let Some(last) = rows.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        for row in &rows {
+            if let Some(writer) = csv_writer.as_mut() {
+                writer.serialize(row)?;
+            }
+            if let Some(writer) = jsonl_writer.as_mut() {
+                write_jsonl(writer, row)?;
+            }
+        }
+        exported += rows.len() as i64;
+    }
+
+    if let Some(mut writer) = csv_writer {
+        writer.flush()?;
+    }
+    if let Some(mut writer) = jsonl_writer {
+        writer.flush()?;
+    }
+
+    let event = AuditEvent::new("EXPORT_AUDIT_LOG", "BATCH", "AUDIT_LOG")
+        .context("format", &format)
+        .context("rows_exported", &exported)
+        .context("file_path", &export_path)
+        .context("filter", &filter);
+    publish_export(&pool, &user.id, event, &partial, &export_path).await?;
+
+    Ok(exported)
+}
+
+#[tauri::command]
+pub async fn get_audit_fail_closed(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<bool> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    audit_fail_closed(&*pool).await
+}
+
+#[tauri::command]
+pub async fn set_audit_fail_closed(
+    pool: State<'_, DbPool>,
+    token: String,
+    fail_closed: bool
+) -> AppResult<bool> {
+    let admin = check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    let mut tx = pool.begin().await?;
+    let previous = audit_fail_closed(&mut *tx).await?;
+
+    sqlx::query("UPDATE audit_settings SET fail_closed = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1")
+        .bind(fail_closed)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("UPDATE_AUDIT_SETTINGS", "SYSTEM", "AUDIT_SETTINGS")
+        .change("fail_closed", &previous, &fail_closed)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(fail_closed)
+}
@@ -0,0 +1,415 @@
+// Tamper evidence for the audit log. Each entry is hashed into a chain, which
+// catches edits to single entries; an attacker who can write to the database can
+// still recompute the whole chain. Signed checkpoints catch that, but only against
+// someone who cannot read the signing key, so the key is kept outside the workspace
+// folder (see workspace::key_dir) and its location is reported with each check.
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::check_permission;
+use crate::permissions::SYSTEM_AUDIT;
+use crate::audit_retention::{archive_path, file_sha256, list_archives};
+use tauri::{AppHandle, State};
+use serde::Serialize;
+use sqlx::{FromRow, SqliteConnection};
+use sha2::{Digest, Sha256};
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// prev_hash of the first entry
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+// A signed checkpoint is written automatically every this many entries
+const CHECKPOINT_INTERVAL: i64 = 100;
+const VERIFY_BATCH_SIZE: i64 = 1000;
+const KEY_FILE: &str = "audit_checkpoint.key";
+
+// Loaded whenever a database is opened, never stored in the database
+static CHECKPOINT_KEY: RwLock<Option<CheckpointKey>> = RwLock::new(None);
+
+#[derive(Clone)]
+struct CheckpointKey {
+    key: Vec<u8>,
+    path: PathBuf,
+}
+
+#[derive(Debug, FromRow)]
+struct ChainEntry {
+    id: i64,
+    actor_user_id: String,
+    action: String,
+    entity_type: String,
+    entity_id: String,
+    metadata_json: Option<String>,
+    created_at: String,
+    prev_hash: Option<String>,
+    entry_hash: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+struct Checkpoint {
+    id: i64,
+    last_entry_id: i64,
+    last_entry_hash: String,
+    entry_count: i64,
+    created_at: String,
+    signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    pub entry_id: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditChainReport {
+    pub valid: bool,
+    pub entries_checked: i64,
+    // First entry whose hash or link does not match; later entries are not trusted
+    pub first_broken_link: Option<BrokenLink>,
+    pub checkpoints_checked: i64,
+    pub invalid_checkpoints: Vec<i64>,
+    pub last_checkpoint_at: Option<String>,
+    // Anyone who can read this file can sign checkpoints for a rewritten log
+    pub checkpoint_key_path: Option<String>,
+    pub archives_checked: i64,
+    // Catalog signature or file hash does not match
+    pub invalid_archives: Vec<i64>,
+    pub missing_archives: Vec<i64>,
+}
+
+const CHAIN_COLUMNS: &str =
+    "id, actor_user_id, action, entity_type, entity_id, metadata_json, CAST(created_at AS TEXT) AS created_at, prev_hash, entry_hash";
+
+fn compute_hash(prev_hash: &str, entry: &ChainEntry) -> String {
+    // A JSON array keeps field boundaries unambiguous
+    let canonical = serde_json::json!([
+        prev_hash,
+        entry.id,
+        entry.actor_user_id,
+        entry.action,
+        entry.entity_type,
+        entry.entity_id,
+        entry.metadata_json,
+        entry.created_at,
+    ])
+    .to_string();
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+// HMAC with the key kept outside the database; None until the key is loaded
+pub fn sign(payload: &str) -> Option<String> {
+    let key = CHECKPOINT_KEY.read().unwrap_or_else(|e| e.into_inner()).clone()?.key;
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts any key length");
+    mac.update(payload.as_bytes());
+    Some(hex::encode(mac.finalize().into_bytes()))
+}
+
+fn checkpoint_payload(last_entry_id: i64, last_entry_hash: &str, entry_count: i64, created_at: &str) -> String {
+    format!("{}|{}|{}|{}", last_entry_id, last_entry_hash, entry_count, created_at)
+}
+
+fn key_path() -> Option<String> {
+    let key = CHECKPOINT_KEY.read().unwrap_or_else(|e| e.into_inner());
+    key.as_ref().map(|k| k.path.to_string_lossy().to_string())
+}
+
+// Reads the checkpoint signing key from `key_dir`, creating it on first run. A key
+// left in the workspace folder by earlier versions is moved out of it.
+pub fn load_checkpoint_key(key_dir: &Path, data_dir: &Path) -> AppResult<()> {
+    let path = key_dir.join(KEY_FILE);
+    let legacy = data_dir.join(KEY_FILE);
+
+    if !path.exists() && legacy.exists() {
+        std::fs::copy(&legacy, &path)?;
+        std::fs::remove_file(&legacy)?;
+    }
+
+    let key = if path.exists() {
+        hex::decode(std::fs::read_to_string(&path)?.trim())
+            .map_err(|e| AppError::Internal(format!("invalid audit checkpoint key: {}", e)))?
+    } else {
+        let mut key = vec![0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        std::fs::write(&path, hex::encode(&key))?;
+        key
+    };
+
+    *CHECKPOINT_KEY.write().unwrap_or_else(|e| e.into_inner()) = Some(CheckpointKey { key, path });
+    Ok(())
+}
+
+// Inserts an entry and links it to the chain. The INSERT takes SQLite's write lock
+// first, so concurrent writers cannot both read the same previous hash.
+pub async fn append_entry(
+    conn: &mut SqliteConnection,
+    actor_user_id: &str,
+    action: &str,
+    entity_type: &str,
+    entity_id: &str,
+    metadata: Option<&str>
+) -> AppResult<i64> {
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO audit_log (actor_user_id, action, entity_type, entity_id, metadata_json) VALUES (?, ?, ?, ?, ?) RETURNING id"
+    )
+    .bind(actor_user_id)
+    .bind(action)
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(metadata)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    seal_entry(conn, id).await?;
+
+    if id % CHECKPOINT_INTERVAL == 0 {
+        write_checkpoint(conn).await?;
+    }
+
+    Ok(id)
+}
+
+async fn seal_entry(conn: &mut SqliteConnection, id: i64) -> AppResult<()> {
+    let entry = sqlx::query_as::<_, ChainEntry>(&format!("SELECT {} FROM audit_log WHERE id = ?", CHAIN_COLUMNS))
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let previous: Option<Option<String>> = sqlx::query_scalar(
+        "SELECT entry_hash FROM audit_log WHERE id < ? ORDER BY id DESC LIMIT 1"
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let prev_hash = match previous {
+        Some(Some(hash)) => hash,
+        Some(None) => return Err(AppError::Internal(format!("audit entry before {} is not sealed", id))),
+        // First entry, or every earlier entry has been archived
+        None => sqlx::query_scalar::<_, String>("SELECT last_entry_hash FROM audit_archives ORDER BY id DESC LIMIT 1")
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or_else(|| GENESIS_HASH.to_string()),
+    };
+
+    sqlx::query("UPDATE audit_log SET prev_hash = ?, entry_hash = ? WHERE id = ?")
+        .bind(&prev_hash)
+        .bind(compute_hash(&prev_hash, &entry))
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+// Signs the current chain head; no-op without a key or when nothing changed
+async fn write_checkpoint(conn: &mut SqliteConnection) -> AppResult<bool> {
+    let head: Option<(i64, Option<String>, i64)> = sqlx::query_as(
+        "SELECT id, entry_hash, (SELECT COUNT(*) FROM audit_log) FROM audit_log ORDER BY id DESC LIMIT 1"
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some((last_entry_id, Some(last_entry_hash), entry_count)) = head else {
+        return Ok(false);
+    };
+
+    let already: Option<i64> = sqlx::query_scalar("SELECT 1 FROM audit_checkpoints WHERE last_entry_id = ?")
+        .bind(last_entry_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if already.is_some() {
+        return Ok(false);
+    }
+
+    let created_at = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let Some(signature) = sign(&checkpoint_payload(last_entry_id, &last_entry_hash, entry_count, &created_at)) else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        "INSERT INTO audit_checkpoints (last_entry_id, last_entry_hash, entry_count, created_at, signature) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(last_entry_id)
+    .bind(&last_entry_hash)
+    .bind(entry_count)
+    .bind(&created_at)
+    .bind(&signature)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(true)
+}
+
+// Startup: chains entries written before hashing existed, then checkpoints the head
+pub async fn seal_pending_entries(pool: &DbPool) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    let pending: Vec<i64> = sqlx::query_scalar("SELECT id FROM audit_log WHERE entry_hash IS NULL ORDER BY id")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for id in pending {
+        seal_entry(&mut tx, id).await?;
+    }
+
+    write_checkpoint(&mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn verify_chain(pool: &DbPool, app_dir: &Path) -> AppResult<AuditChainReport> {
+    let archives = list_archives(pool).await?;
+    let mut invalid_archives = Vec::new();
+    let mut missing_archives = Vec::new();
+    for archive in &archives {
+        let path = archive_path(app_dir, &archive.file_name);
+        if !path.is_file() {
+            missing_archives.push(archive.id);
+        } else if !archive.signature_valid() || file_sha256(&path)? != archive.file_sha256 {
+            invalid_archives.push(archive.id);
+        }
+    }
+
+    // After retention the chain continues from the last archived entry
+    let archived_through = archives.last().map(|a| a.last_entry_id).unwrap_or(0);
+    let mut expected_prev = archives
+        .last()
+        .map(|a| a.last_entry_hash.clone())
+        .unwrap_or_else(|| GENESIS_HASH.to_string());
+    let mut entries_checked = 0;
+    let mut first_broken_link = None;
+    let mut last_id = 0;
+
+    'batches: loop {
+        let batch = sqlx::query_as::<_, ChainEntry>(&format!(
+            "SELECT {} FROM audit_log WHERE id > ? ORDER BY id LIMIT ?",
+            CHAIN_COLUMNS
+        ))
+        .bind(last_id)
+        .bind(VERIFY_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        if batch.is_empty() {
+            break;
+        }
+
+        for entry in &batch {
+            last_id = entry.id;
+            entries_checked += 1;
+
+            let reason = match (&entry.prev_hash, &entry.entry_hash) {
+                (_, None) => Some("Entry was never sealed"),
+                (Some(prev), _) if *prev != expected_prev => Some("Previous hash does not match the preceding entry"),
+                (None, _) => Some("Previous hash is missing"),
+                (_, Some(hash)) if *hash != compute_hash(&expected_prev, entry) => Some("Entry content does not match its hash"),
+                _ => None,
+            };
+
+            if let Some(reason) = reason {
+                first_broken_link = Some(BrokenLink { entry_id: entry.id, reason: reason.to_string() });
+                break 'batches;
+            }
+
+            expected_prev = entry.entry_hash.clone().unwrap_or_default();
+        }
+    }
+
+    let checkpoints = sqlx::query_as::<_, Checkpoint>(
+        "SELECT id, last_entry_id, last_entry_hash, entry_count, created_at, signature FROM audit_checkpoints ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut invalid_checkpoints = Vec::new();
+    for checkpoint in &checkpoints {
+        let payload = checkpoint_payload(
+            checkpoint.last_entry_id,
+            &checkpoint.last_entry_hash,
+            checkpoint.entry_count,
+            &checkpoint.created_at,
+        );
+        let signature_ok = sign(&payload).is_some_and(|s| s == checkpoint.signature);
+
+        // The signed head must still be present with the same hash; catches a rewritten
+        // or truncated chain even when every link was recomputed
+        let head_hash: Option<Option<String>> = sqlx::query_scalar("SELECT entry_hash FROM audit_log WHERE id = ?")
+            .bind(checkpoint.last_entry_id)
+            .fetch_optional(pool)
+            .await?;
+        let head_ok = checkpoint.last_entry_id <= archived_through
+            || head_hash.flatten().as_deref() == Some(checkpoint.last_entry_hash.as_str());
+
+        if !signature_ok || !head_ok {
+            invalid_checkpoints.push(checkpoint.id);
+        }
+    }
+
+    Ok(AuditChainReport {
+        valid: first_broken_link.is_none()
+            && invalid_checkpoints.is_empty()
+            && invalid_archives.is_empty()
+            && missing_archives.is_empty(),
+        entries_checked,
+        first_broken_link,
+        checkpoints_checked: checkpoints.len() as i64,
+        invalid_checkpoints,
+        last_checkpoint_at: checkpoints.last().map(|c| c.created_at.clone()),
+        checkpoint_key_path: key_path(),
+        archives_checked: archives.len() as i64,
+        invalid_archives,
+        missing_archives,
+    })
+}
+
+#[tauri::command]
+pub async fn verify_audit_chain(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<AuditChainReport> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    let app_dir = crate::workspace::data_dir(&app)?;
+    verify_chain(&pool, &app_dir).await
+}
+
+// Lets an inspector pin the current head before a review
+#[tauri::command]
+pub async fn create_audit_checkpoint(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<bool> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+
+    let mut conn = pool.acquire().await?;
+    write_checkpoint(&mut conn).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(metadata: Option<&str>) -> ChainEntry {
+        ChainEntry {
+            id: 1,
+            actor_user_id: "admin".to_string(),
+            action: "UPDATE_USER".to_string(),
+            entity_type: "USER".to_string(),
+            entity_id: "u1".to_string(),
+            metadata_json: metadata.map(str::to_string),
+            created_at: "2026-01-01 00:00:00".to_string(),
+            prev_hash: None,
+            entry_hash: None,
+        }
+    }
+
+    #[test]
+    fn hash_covers_content_and_previous_link() {
+        let original = compute_hash(GENESIS_HASH, &entry(Some("{\"role\":\"TEACHER\"}")));
+        assert_eq!(original, compute_hash(GENESIS_HASH, &entry(Some("{\"role\":\"TEACHER\"}"))));
+        assert_ne!(original, compute_hash(GENESIS_HASH, &entry(Some("{\"role\":\"ADMIN\"}"))));
+        assert_ne!(original, compute_hash(&original, &entry(Some("{\"role\":\"TEACHER\"}"))));
+    }
+}
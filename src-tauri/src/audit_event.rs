@@ -0,0 +1,215 @@
+use crate::db::DbPool;
+use crate::audit::{log_audit, log_audit_tx};
+use crate::error::AppResult;
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use sqlx::{Sqlite, Transaction};
+
+// Fields whose values never reach the audit log; only the fact that they changed
+const SECRET_FIELDS: &[&str] = &["password", "password_hash", "totp_secret", "recovery_codes", "token"];
+// Personal data that is recorded partially so a reviewer can tell values apart
+const PERSONAL_FIELDS: &[&str] = &["address", "guardian_contact", "emergency_contact", "date_of_birth"];
+
+const REDACTED: &str = "[REDACTED]";
+const VISIBLE_SUFFIX: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+// Metadata stored in `audit_log.metadata_json` for every event:
+// { "changes": [{ field, before, after }], "context": { ... } }, empty parts omitted
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AuditMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<FieldChange>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub context: Map<String, Value>,
+}
+
+pub struct AuditEvent {
+    action: &'static str,
+    entity_type: String,
+    entity_id: String,
+    metadata: AuditMetadata,
+}
+
+fn mask(field: &str, value: Value) -> Value {
+    if value.is_null() {
+        return value;
+    }
+    if SECRET_FIELDS.contains(&field) {
+        return Value::String(REDACTED.to_string());
+    }
+    if PERSONAL_FIELDS.contains(&field) {
+        let text = match &value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let chars: Vec<char> = text.chars().collect();
+        let visible: String = chars[chars.len().saturating_sub(VISIBLE_SUFFIX)..].iter().collect();
+        return Value::String(format!("***{}", visible));
+    }
+    value
+}
+
+impl AuditEvent {
+    pub fn new(action: &'static str, entity_type: impl Into<String>, entity_id: impl Into<String>) -> Self {
+        AuditEvent {
+            action,
+            entity_type: entity_type.into(),
+            entity_id: entity_id.into(),
+            metadata: AuditMetadata::default(),
+        }
+    }
+
+    // Records a field change; unchanged values are skipped
+    pub fn change<T: Serialize + ?Sized>(mut self, field: &str, before: &T, after: &T) -> Self {
+        let before = serde_json::to_value(before).unwrap_or(Value::Null);
+        let after = serde_json::to_value(after).unwrap_or(Value::Null);
+        if before != after {
+            self.metadata.changes.push(FieldChange {
+                field: field.to_string(),
+                before: mask(field, before),
+                after: mask(field, after),
+            });
+        }
+        self
+    }
+
+    // Records every top-level field that differs between two snapshots of a record
+    pub fn diff<T: Serialize>(mut self, before: &T, after: &T) -> Self {
+        let as_map = |v: &T| match serde_json::to_value(v) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        let before = as_map(before);
+        let mut after = as_map(after);
+
+        for (field, old) in before {
+            let new = after.remove(&field).unwrap_or(Value::Null);
+            self = self.change(&field, &old, &new);
+        }
+        for (field, new) in after {
+            self = self.change(&field, &Value::Null, &new);
+        }
+        self
+    }
+
+    // Extra facts about the event that are not field changes, e.g. a row count
+    pub fn context<T: Serialize + ?Sized>(mut self, key: &str, value: &T) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.metadata.context.insert(key.to_string(), mask(key, value));
+        self
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.metadata.changes.is_empty()
+    }
+
+    pub fn metadata_json(&self) -> Option<String> {
+        if self.metadata.changes.is_empty() && self.metadata.context.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&self.metadata).ok()
+        }
+    }
+
+    pub async fn log(self, pool: &DbPool, actor_user_id: &str) -> AppResult<()> {
+        log_audit(
+            pool,
+            actor_user_id,
+            self.action,
+            &self.entity_type,
+            &self.entity_id,
+            self.metadata_json().as_deref()
+        ).await
+    }
+
+    // Logs as part of `tx`; propagate the error so the change is rolled back with it
+    pub async fn log_tx(self, tx: &mut Transaction<'_, Sqlite>, actor_user_id: &str) -> AppResult<()> {
+        log_audit_tx(
+            tx,
+            actor_user_id,
+            self.action,
+            &self.entity_type,
+            &self.entity_id,
+            self.metadata_json().as_deref()
+        ).await
+    }
+}
+
+// Entries written before structured metadata keep their text as a `note`
+pub fn parse_metadata(metadata_json: Option<&str>) -> AuditMetadata {
+    let Some(raw) = metadata_json else {
+        return AuditMetadata::default();
+    };
+
+    match serde_json::from_str::<Value>(raw) {
+        Ok(Value::Object(map)) if map.contains_key("changes") || map.contains_key("context") => {
+            serde_json::from_value(Value::Object(map)).unwrap_or_default()
+        }
+        Ok(Value::Object(map)) => AuditMetadata { changes: Vec::new(), context: map },
+        _ => {
+            let mut context = Map::new();
+            context.insert("note".to_string(), Value::String(raw.to_string()));
+            AuditMetadata { changes: Vec::new(), context }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Contact {
+        guardian_name: Option<String>,
+        guardian_contact: Option<String>,
+        address: Option<String>,
+    }
+
+    #[test]
+    fn diff_records_changed_fields_with_masking() {
+        let before = Contact {
+            guardian_name: Some("Ana".to_string()),
+            guardian_contact: Some("0812345678".to_string()),
+            address: Some("1 Main St".to_string()),
+        };
+        let after = Contact {
+            guardian_name: Some("Ana".to_string()),
+            guardian_contact: Some("0899999123".to_string()),
+            address: None,
+        };
+
+        let event = AuditEvent::new("UPDATE_STUDENT_CONTACT", "STUDENT", "s1")
+            .diff(&before, &after)
+            .change("password_hash", "old", "new");
+
+        let metadata: Value = serde_json::from_str(&event.metadata_json().unwrap()).unwrap();
+        assert_eq!(
+            metadata,
+            json!({
+                "changes": [
+                    { "field": "address", "before": "*** St", "after": null },
+                    { "field": "guardian_contact", "before": "***678", "after": "***123" },
+                    { "field": "password_hash", "before": "[REDACTED]", "after": "[REDACTED]" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn legacy_metadata_is_kept_as_context() {
+        let parsed = parse_metadata(Some("Processed 12 rows from /tmp/a.csv"));
+        assert_eq!(parsed.context["note"], "Processed 12 rows from /tmp/a.csv");
+
+        let parsed = parse_metadata(Some(r#"{"from":"TEACHER","to":"ADMIN"}"#));
+        assert!(parsed.changes.is_empty());
+        assert_eq!(parsed.context["to"], "ADMIN");
+    }
+}
@@ -0,0 +1,296 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit::{write_jsonl, AuditExportRow, EXPORT_COLUMNS};
+use crate::audit_chain::sign;
+use crate::audit_event::AuditEvent;
+use crate::permissions::SYSTEM_AUDIT;
+use tauri::{AppHandle, State};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use sha2::{Digest, Sha256};
+use flate2::{write::GzEncoder, Compression};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+const ARCHIVE_DIR: &str = "audit_archives";
+const ARCHIVE_BATCH_SIZE: i64 = 1000;
+const MAX_RETENTION_YEARS: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AuditArchive {
+    pub id: i64,
+    pub file_name: String,
+    pub file_sha256: String,
+    pub first_entry_id: i64,
+    pub last_entry_id: i64,
+    pub last_entry_hash: String,
+    pub entry_count: i64,
+    pub cutoff: String,
+    pub created_at: String,
+    #[serde(skip)]
+    pub signature: String,
+}
+
+impl AuditArchive {
+    fn payload(&self) -> String {
+        format!(
+            "archive|{}|{}|{}|{}|{}|{}|{}",
+            self.file_name, self.file_sha256, self.first_entry_id, self.last_entry_id,
+            self.last_entry_hash, self.entry_count, self.created_at
+        )
+    }
+
+    pub fn signature_valid(&self) -> bool {
+        sign(&self.payload()).is_some_and(|s| s == self.signature)
+    }
+}
+
+pub async fn list_archives(pool: &DbPool) -> AppResult<Vec<AuditArchive>> {
+    let archives = sqlx::query_as::<_, AuditArchive>(
+        r#"
+        SELECT id, file_name, file_sha256, first_entry_id, last_entry_id, last_entry_hash,
+               entry_count, cutoff, created_at, signature
+        FROM audit_archives
+        ORDER BY id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(archives)
+}
+
+async fn load_retention_years(pool: &DbPool) -> AppResult<Option<i64>> {
+    let years: Option<i64> = sqlx::query_scalar("SELECT retention_years FROM audit_retention_policy WHERE id = 1")
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    Ok(years)
+}
+
+pub fn archive_path(app_dir: &Path, file_name: &str) -> PathBuf {
+    app_dir.join(ARCHIVE_DIR).join(file_name)
+}
+
+pub fn file_sha256(path: &Path) -> AppResult<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Moves entries older than the retention period into a gzipped JSON Lines file,
+// then deletes them. The file is complete and synced before anything is deleted,
+// and only takes its final name once the catalog row and the deletion commit.
+pub async fn apply_retention(pool: &DbPool, app_dir: &Path, actor_user_id: &str) -> AppResult<Option<AuditArchive>> {
+    let Some(years) = load_retention_years(pool).await? else {
+        return Ok(None);
+    };
+
+    let cutoff: String = sqlx::query_scalar("SELECT datetime('now', ?)")
+        .bind(format!("-{} years", years))
+        .fetch_one(pool)
+        .await?;
+
+    // Always a prefix of the chain, so the remaining entries stay linked
+    let range: (Option<i64>, Option<i64>, i64) = sqlx::query_as(
+        r#"
+        SELECT MIN(id), MAX(id), COUNT(*) FROM audit_log
+        WHERE id <= (SELECT MAX(id) FROM audit_log WHERE created_at < ?)
+        "#
+    )
+    .bind(&cutoff)
+    .fetch_one(pool)
+    .await?;
+
+    let (Some(first_entry_id), Some(last_entry_id), entry_count) = range else {
+        return Ok(None);
+    };
+
+    let last_entry_hash: String = sqlx::query_scalar::<_, Option<String>>("SELECT entry_hash FROM audit_log WHERE id = ?")
+        .bind(last_entry_id)
+        .fetch_one(pool)
+        .await?
+        .ok_or_else(|| AppError::Internal(format!("audit entry {} is not sealed", last_entry_id)))?;
+
+    let archive_dir = app_dir.join(ARCHIVE_DIR);
+    fs::create_dir_all(&archive_dir)?;
+    let file_name = format!("audit_{:010}_{:010}.jsonl.gz", first_entry_id, last_entry_id);
+    let final_path = archive_path(app_dir, &file_name);
+    let partial_path = archive_dir.join(format!("{}.partial", file_name));
+
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(&partial_path)?), Compression::default());
+    let mut after_id = first_entry_id - 1;
+    while after_id < last_entry_id {
+        let rows = sqlx::query_as::<_, AuditExportRow>(&format!(
+            "SELECT {} FROM audit_log a LEFT JOIN users u ON u.id = a.actor_user_id WHERE a.id > ? AND a.id <= ? ORDER BY a.id LIMIT ?",
+            EXPORT_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(last_entry_id)
+        .bind(ARCHIVE_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for row in &rows {
+            write_jsonl(&mut encoder, row)?;
+        }
+    }
+    let file = encoder.finish()?.into_inner().map_err(|e| AppError::from(e.into_error()))?;
+    file.sync_all()?;
+    drop(file);
+
+    let mut archive = AuditArchive {
+        id: 0,
+        file_sha256: file_sha256(&partial_path)?,
+        file_name,
+        first_entry_id,
+        last_entry_id,
+        last_entry_hash,
+        entry_count,
+        cutoff,
+        created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        signature: String::new(),
+    };
+
+    // A file without a catalog row would never be verified or cleaned up
+    if let Err(e) = catalog_archive(pool, &mut archive, actor_user_id).await {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+    fs::rename(&partial_path, &final_path)?;
+
+    Ok(Some(archive))
+}
+
+// Records the archive and deletes the entries it holds in one transaction
+async fn catalog_archive(pool: &DbPool, archive: &mut AuditArchive, actor_user_id: &str) -> AppResult<()> {
+    archive.signature = sign(&archive.payload())
+        .ok_or_else(|| AppError::Internal("audit signing key is not loaded".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    archive.id = sqlx::query_scalar(
+        r#"
+        INSERT INTO audit_archives (
+            file_name, file_sha256, first_entry_id, last_entry_id, last_entry_hash,
+            entry_count, cutoff, created_at, signature
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id
+        "#
+    )
+    .bind(&archive.file_name)
+    .bind(&archive.file_sha256)
+    .bind(archive.first_entry_id)
+    .bind(archive.last_entry_id)
+    .bind(&archive.last_entry_hash)
+    .bind(archive.entry_count)
+    .bind(&archive.cutoff)
+    .bind(&archive.created_at)
+    .bind(&archive.signature)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let deleted = sqlx::query("DELETE FROM audit_log WHERE id <= ?")
+        .bind(archive.last_entry_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if deleted.rows_affected() as i64 != archive.entry_count {
+        return Err(AppError::Internal(format!(
+            "archived {} audit entries but {} matched for deletion",
+            archive.entry_count,
+            deleted.rows_affected()
+        )));
+    }
+
+    AuditEvent::new("ARCHIVE_AUDIT_LOG", "BATCH", "AUDIT_LOG")
+        .context("file_name", &archive.file_name)
+        .context("first_entry_id", &archive.first_entry_id)
+        .context("last_entry_id", &archive.last_entry_id)
+        .context("entry_count", &archive.entry_count)
+        .context("cutoff", &archive.cutoff)
+        .log_tx(&mut tx, actor_user_id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_audit_retention_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Option<i64>> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    load_retention_years(&pool).await
+}
+
+// `retention_years` of None keeps entries forever
+#[tauri::command]
+pub async fn set_audit_retention_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+    retention_years: Option<i64>
+) -> AppResult<Option<i64>> {
+    let admin = check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    if let Some(years) = retention_years {
+        if !(1..=MAX_RETENTION_YEARS).contains(&years) {
+            return Err(AppError::invalid_field(
+                "retention_years",
+                format!("Retention must be between 1 and {} years", MAX_RETENTION_YEARS)
+            ));
+        }
+    }
+
+    let previous = load_retention_years(&pool).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE audit_retention_policy SET retention_years = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1")
+        .bind(retention_years)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("UPDATE_AUDIT_RETENTION", "SYSTEM", "AUDIT_RETENTION_POLICY")
+        .change("retention_years", &previous, &retention_years)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(retention_years)
+}
+
+// Runs retention now instead of waiting for the next start-up
+#[tauri::command]
+pub async fn apply_audit_retention(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Option<AuditArchive>> {
+    let admin = check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    let app_dir = crate::workspace::data_dir(&app)?;
+    apply_retention(&pool, &app_dir, &admin.id).await
+}
+
+#[tauri::command]
+pub async fn list_audit_archives(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<AuditArchive>> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    list_archives(&pool).await
+}
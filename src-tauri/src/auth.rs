@@ -1,34 +1,220 @@
-use crate::models::{Role, User};
+use crate::models::User;
 use crate::db::DbPool;
-use sqlx::Row;
+use crate::error::{AppError, AppResult};
+use crate::totp::is_required_for_role;
+use crate::permissions::{role_has_permission, role_is_class_scoped};
+use sqlx::{Row, SqliteConnection};
+use sqlx::sqlite::SqliteRow;
+use sha2::{Digest, Sha256};
 
-pub async fn check_auth(
+// Sessions expire after this long regardless of activity
+pub const SESSION_MAX_LIFETIME: &str = "+12 hours";
+// Sessions not used within this window are treated as expired
+pub const SESSION_IDLE_TIMEOUT: &str = "-30 minutes";
+// Destructive commands need a password or 2FA confirmation at least this recent
+pub const STEP_UP_WINDOW: &str = "-5 minutes";
+
+fn user_from_row(row: &SqliteRow) -> AppResult<User> {
+    Ok(User {
+        id: row.try_get("id")?,
+        email: row.try_get("email").unwrap_or_default(),
+        password_hash: row.try_get("password_hash").unwrap_or_default(),
+        role: row.try_get("role")?,
+        active: row.try_get("active").unwrap_or(false),
+        created_at: row.try_get("created_at").unwrap_or_default(),
+        last_login_at: row.try_get("last_login_at").ok(),
+        must_change_password: row.try_get("must_change_password").unwrap_or(false),
+        totp_enabled: row.try_get("totp_enabled").unwrap_or(false),
+        staff_id: row.try_get("staff_id").unwrap_or(None),
+    })
+}
+
+// Opaque random token; two v4 UUIDs give 244 bits of OS randomness
+fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+// Only the hash is stored; the token itself is known only to the client. It is
+// high-entropy, so a fast unsalted hash is sufficient.
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub async fn create_session(
     pool: &DbPool,
     user_id: &str,
-    required_roles: &[Role],
-) -> Result<User, String> {
-    let row = sqlx::query("SELECT * FROM users WHERE id = ? AND active = 1")
+) -> AppResult<(String, chrono::NaiveDateTime)> {
+    let token = generate_token();
+
+    let expires_at: chrono::NaiveDateTime = sqlx::query_scalar(
+        "INSERT INTO sessions (token_hash, user_id, expires_at) VALUES (?, ?, datetime('now', ?)) RETURNING expires_at"
+    )
+    .bind(token_hash(&token))
+    .bind(user_id)
+    .bind(SESSION_MAX_LIFETIME)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((token, expires_at))
+}
+
+// Resolve the caller behind a session token, refreshing its idle timer
+pub async fn resolve_session(pool: &DbPool, token: &str) -> AppResult<User> {
+    let row = sqlx::query(
+        r#"
+        SELECT u.*
+        FROM sessions s
+        JOIN users u ON u.id = s.user_id
+        WHERE s.token_hash = ?
+          AND s.revoked_at IS NULL
+          AND s.expires_at > CURRENT_TIMESTAMP
+          AND s.last_seen_at > datetime('now', ?)
+          AND u.active = 1
+        "#
+    )
+    .bind(token_hash(token))
+    .bind(SESSION_IDLE_TIMEOUT)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::Unauthenticated)?;
+
+    sqlx::query("UPDATE sessions SET last_seen_at = CURRENT_TIMESTAMP WHERE token_hash = ?")
+        .bind(token_hash(token))
+        .execute(pool)
+        .await?;
+
+    user_from_row(&row)
+}
+
+pub async fn revoke_session(pool: &DbPool, token: &str) -> AppResult<()> {
+    sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE token_hash = ? AND revoked_at IS NULL")
+        .bind(token_hash(token))
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn revoke_user_sessions(conn: &mut SqliteConnection, user_id: &str) -> AppResult<u64> {
+    let result = sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = ? AND revoked_at IS NULL")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn revoke_other_sessions(conn: &mut SqliteConnection, user_id: &str, keep_token: &str) -> AppResult<u64> {
+    let result = sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE user_id = ? AND token_hash != ? AND revoked_at IS NULL")
         .bind(user_id)
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| e.to_string())?
-        .ok_or("User not found or inactive")?;
-
-    let role_str: String = row.try_get("role").map_err(|e| e.to_string())?;
-    let user_role = Role::from(role_str.clone());
-
-    if required_roles.contains(&user_role) {
-        let user = User {
-            id: row.try_get("id").unwrap_or_default(),
-            email: row.try_get("email").unwrap_or_default(),
-            password_hash: row.try_get("password_hash").unwrap_or_default(),
-            role: role_str,
-            active: row.try_get("active").unwrap_or(false),
-            created_at: row.try_get("created_at").unwrap_or_default(),
-            last_login_at: row.try_get("last_login_at").ok(),
-        };
+        .bind(token_hash(keep_token))
+        .execute(conn)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn mark_reauthenticated(conn: &mut SqliteConnection, token: &str) -> AppResult<()> {
+    sqlx::query("UPDATE sessions SET reauthenticated_at = CURRENT_TIMESTAMP WHERE token_hash = ?")
+        .bind(token_hash(token))
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// Call after the permission check in commands that can wipe or overwrite data
+pub async fn require_recent_auth(pool: &DbPool, token: &str) -> AppResult<()> {
+    let recent = sqlx::query_scalar::<_, i32>(
+        "SELECT 1 FROM sessions WHERE token_hash = ? AND reauthenticated_at > datetime('now', ?)"
+    )
+    .bind(token_hash(token))
+    .bind(STEP_UP_WINDOW)
+    .fetch_optional(pool)
+    .await?;
+
+    match recent {
+        Some(_) => Ok(()),
+        None => Err(AppError::ReauthRequired),
+    }
+}
+
+// Resolves the caller and applies the account gates shared by every check
+pub async fn authenticate(pool: &DbPool, token: &str) -> AppResult<User> {
+    let user = resolve_session(pool, token).await?;
+
+    // Accounts flagged for rotation may only call change_password
+    if user.must_change_password {
+        return Err(AppError::PasswordChangeRequired);
+    }
+
+    // Roles requiring 2FA may only call the enrollment commands until enrolled
+    if !user.totp_enabled && is_required_for_role(pool, &user.role).await? {
+        return Err(AppError::TwoFactorSetupRequired);
+    }
+
+    Ok(user)
+}
+
+pub async fn check_permission(
+    pool: &DbPool,
+    token: &str,
+    permission: &str,
+) -> AppResult<User> {
+    let user = authenticate(pool, token).await?;
+
+    if has_permission(pool, &user, permission).await? {
         Ok(user)
     } else {
-        Err(format!("Access denied. User role {:?} does not have permission.", user_role))
+        Err(AppError::Forbidden(format!("Access denied. Missing permission '{}'.", permission)))
+    }
+}
+
+pub async fn has_permission(pool: &DbPool, user: &User, permission: &str) -> AppResult<bool> {
+    role_has_permission(pool, &user.role, permission).await
+}
+
+// Classes a staff member teaches, either as homeroom teacher or via staff_classes.
+// Binds the staff id twice.
+pub const STAFF_CLASS_IDS: &str =
+    "SELECT id FROM classes WHERE homeroom_teacher_id = ? UNION SELECT class_id FROM staff_classes WHERE staff_id = ?";
+
+// Staff record whose classes bound what the user may see; None means unrestricted.
+// Members of class-scoped roles without a linked staff record are scoped to nothing.
+pub async fn class_scope(pool: &DbPool, user: &User) -> AppResult<Option<String>> {
+    if role_is_class_scoped(pool, &user.role).await? {
+        Ok(Some(user.staff_id.clone().unwrap_or_default()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn ensure_student_access(
+    pool: &DbPool,
+    user: &User,
+    student_id: &str,
+) -> AppResult<()> {
+    let Some(staff_id) = class_scope(pool, user).await? else {
+        return Ok(());
+    };
+
+    let visible = sqlx::query_scalar::<_, i32>(&format!(
+        "SELECT 1 FROM students WHERE id = ? AND class_id IN ({})",
+        STAFF_CLASS_IDS
+    ))
+    .bind(student_id)
+    .bind(&staff_id)
+    .bind(&staff_id)
+    .fetch_optional(pool)
+    .await?;
+
+    if visible.is_some() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Access denied. Student is not in one of your assigned classes.".to_string()))
     }
 }
@@ -0,0 +1,502 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit_event::AuditEvent;
+use crate::audit_retention::file_sha256;
+use crate::backup_crypto::{validate_passphrase, DecryptReader, EncryptWriter};
+use crate::encryption::{is_encrypted, is_plaintext, options_for};
+use crate::permissions::SYSTEM_BACKUP;
+use tauri::{AppHandle, State};
+use serde::{Serialize, Deserialize};
+use sqlx::{ConnectOptions, Connection, FromRow, SqliteConnection};
+use chrono::NaiveDateTime;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+// Bundled with the database in encrypted archives
+pub const PHOTO_DIRS: &[&str] = &["student_photos", "staff_photos"];
+pub const ARCHIVE_DB_NAME: &str = "resonance.db";
+const ARCHIVE_MANIFEST_NAME: &str = "manifest.json";
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+pub const RESTORE_STAGING_DIR: &str = "restore_staging";
+// Plaintext snapshots waiting to be encrypted, kept out of the backup folders that
+// are copied to removable drives
+const SNAPSHOT_STAGING_DIR: &str = "backup_staging";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupKind {
+    // Taken at start-up before migrations run
+    Auto,
+    Manual,
+}
+
+impl BackupKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackupKind::Auto => "AUTO",
+            BackupKind::Manual => "MANUAL",
+        }
+    }
+
+    pub fn dir(self) -> &'static str {
+        match self {
+            BackupKind::Auto => "backups",
+            BackupKind::Manual => "manual_backups",
+        }
+    }
+
+    fn file_prefix(self) -> &'static str {
+        match self {
+            BackupKind::Auto => "resonance_backup",
+            BackupKind::Manual => "resonance_manual",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BackupRecord {
+    pub id: i64,
+    pub kind: String,
+    pub file_name: String,
+    pub file_path: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub schema_version: Option<i64>,
+    pub encrypted: bool,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+pub const RECORD_COLUMNS: &str =
+    "id, kind, file_name, file_path, size_bytes, sha256, schema_version, encrypted, created_by, created_at, deleted_at";
+
+// Stored inside encrypted archives, so it is covered by the same authentication
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub created_at: String,
+    pub schema_version: Option<i64>,
+    pub db_sha256: String,
+}
+
+// A verified copy of the database that is not in the catalog yet
+pub struct BackupSnapshot {
+    pub kind: BackupKind,
+    pub path: PathBuf,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub schema_version: Option<i64>,
+    pub encrypted: bool,
+}
+
+// Runs the integrity check on a copy and reads its migration level
+pub async fn inspect_copy(path: &Path) -> AppResult<Option<i64>> {
+    let mut conn = options_for(path)
+        .read_only(true)
+        .connect()
+        .await?;
+
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await?;
+    if problems != ["ok"] {
+        return Err(AppError::Internal(format!("backup copy failed the integrity check: {}", problems.join("; "))));
+    }
+
+    // A database that was never migrated has no migrations table
+    let schema_version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(&mut conn)
+        .await
+        .unwrap_or(None);
+
+    conn.close().await?;
+    Ok(schema_version)
+}
+
+// Copies the live database with VACUUM INTO, which reads one consistent snapshot
+// (including pages still in the WAL) while other connections keep working
+pub async fn take_snapshot(conn: &mut SqliteConnection, app_dir: &Path, kind: BackupKind) -> AppResult<BackupSnapshot> {
+    let dir = app_dir.join(kind.dir());
+    fs::create_dir_all(&dir)?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let file_name = format!("{}_{}.db", kind.file_prefix(), timestamp);
+    let final_path = dir.join(&file_name);
+    let partial_path = dir.join(format!("{}.partial", file_name));
+
+    if final_path.exists() {
+        return Err(AppError::Conflict(format!("Backup '{}' already exists; try again in a moment", file_name)));
+    }
+
+    let schema_version = vacuum_into(conn, &partial_path).await?;
+    fs::rename(&partial_path, &final_path)?;
+
+    Ok(BackupSnapshot {
+        kind,
+        size_bytes: fs::metadata(&final_path)?.len() as i64,
+        sha256: file_sha256(&final_path)?,
+        schema_version,
+        encrypted: false,
+        path: final_path,
+    })
+}
+
+// Writes a verified copy to `path`, removing it again if verification fails
+async fn vacuum_into(conn: &mut SqliteConnection, path: &Path) -> AppResult<Option<i64>> {
+    // Left behind by an interrupted attempt; VACUUM INTO refuses to overwrite
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().as_ref())
+        .execute(conn)
+        .await?;
+
+    // SQLCipher writes the copy with the live database's key
+    let result = match is_plaintext(path) {
+        Ok(true) if is_encrypted() => Err(AppError::Internal("backup copy of an encrypted database was written unencrypted".to_string())),
+        _ => inspect_copy(path).await,
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+// Removes what an interrupted backup left behind: plaintext snapshots awaiting
+// encryption and unfinished copies in the backup folders. Run before anything
+// can start a backup.
+pub fn sweep_partial_files(app_dir: &Path) -> AppResult<()> {
+    let staging = app_dir.join(SNAPSHOT_STAGING_DIR);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+
+    for kind in [BackupKind::Auto, BackupKind::Manual] {
+        let dir = app_dir.join(kind.dir());
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "partial") {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Bundles a database snapshot and the photo directories into one encrypted file.
+// The plaintext snapshot only exists, outside the backup folders, while the
+// archive is being written.
+pub async fn take_encrypted_snapshot(pool: &DbPool, app_dir: &Path, passphrase: &str) -> AppResult<BackupSnapshot> {
+    validate_passphrase(passphrase)?;
+
+    let kind = BackupKind::Manual;
+    let dir = app_dir.join(kind.dir());
+    fs::create_dir_all(&dir)?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let file_name = format!("{}_{}.rbak", kind.file_prefix(), timestamp);
+    let final_path = dir.join(&file_name);
+    let partial_path = dir.join(format!("{}.partial", file_name));
+    let staging = app_dir.join(SNAPSHOT_STAGING_DIR);
+    fs::create_dir_all(&staging)?;
+    let db_copy = staging.join(format!("{}.db", file_name));
+
+    if final_path.exists() {
+        return Err(AppError::Conflict(format!("Backup '{}' already exists; try again in a moment", file_name)));
+    }
+
+    let schema_version = vacuum_into(&mut *pool.acquire().await?, &db_copy).await?;
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        schema_version,
+        db_sha256: file_sha256(&db_copy)?,
+    };
+
+    let written = write_archive(&partial_path, &db_copy, app_dir, &manifest, passphrase);
+    let _ = fs::remove_file(&db_copy);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+    fs::rename(&partial_path, &final_path)?;
+
+    Ok(BackupSnapshot {
+        kind,
+        size_bytes: fs::metadata(&final_path)?.len() as i64,
+        sha256: file_sha256(&final_path)?,
+        schema_version,
+        encrypted: true,
+        path: final_path,
+    })
+}
+
+fn write_archive(path: &Path, db_copy: &Path, app_dir: &Path, manifest: &ArchiveManifest, passphrase: &str) -> AppResult<()> {
+    let encryptor = EncryptWriter::new(BufWriter::new(File::create(path)?), passphrase)?;
+    let mut tar = tar::Builder::new(GzEncoder::new(encryptor, Compression::default()));
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o600);
+    header.set_cksum();
+    tar.append_data(&mut header, ARCHIVE_MANIFEST_NAME, manifest_json.as_slice())?;
+
+    tar.append_path_with_name(db_copy, ARCHIVE_DB_NAME)?;
+    for dir in PHOTO_DIRS {
+        let photo_dir = app_dir.join(dir);
+        if photo_dir.is_dir() {
+            tar.append_dir_all(dir, &photo_dir)?;
+        }
+    }
+
+    let file = tar.into_inner()?.finish()?.finish()?;
+    file.into_inner().map_err(|e| AppError::from(e.into_error()))?.sync_all()?;
+
+    Ok(())
+}
+
+// Decrypts an archive into `staging` and checks the database inside against the
+// manifest. Every chunk is authenticated, so nothing in `staging` can have been altered.
+pub async fn unpack_encrypted_backup(archive_path: &Path, passphrase: &str, staging: &Path) -> AppResult<ArchiveManifest> {
+    if staging.exists() {
+        fs::remove_dir_all(staging)?;
+    }
+    fs::create_dir_all(staging)?;
+
+    let decryptor = DecryptReader::new(BufReader::new(File::open(archive_path)?), passphrase)?;
+    tar::Archive::new(GzDecoder::new(decryptor))
+        .unpack(staging)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => AppError::invalid("The backup file is damaged or incomplete"),
+            _ => AppError::from(e),
+        })?;
+
+    let manifest: ArchiveManifest = serde_json::from_slice(&fs::read(staging.join(ARCHIVE_MANIFEST_NAME))?)
+        .map_err(|_| AppError::invalid("The backup has no readable manifest"))?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(AppError::invalid("The backup was made by a newer version of the application"));
+    }
+
+    let db_path = staging.join(ARCHIVE_DB_NAME);
+    if !db_path.is_file() || file_sha256(&db_path)? != manifest.db_sha256 {
+        return Err(AppError::invalid("The database in the backup does not match its manifest"));
+    }
+    inspect_copy(&db_path).await?;
+
+    Ok(manifest)
+}
+
+// Adds a snapshot to the catalog; the file is removed if it cannot be recorded
+pub async fn record_snapshot(pool: &DbPool, snapshot: BackupSnapshot, actor_user_id: &str) -> AppResult<BackupRecord> {
+    let result = catalog_snapshot(pool, &snapshot, actor_user_id).await;
+    if result.is_err() {
+        let _ = fs::remove_file(&snapshot.path);
+    }
+    result
+}
+
+// Like record_snapshot, but leaves the file in place if it cannot be cataloged
+pub async fn catalog_snapshot(pool: &DbPool, snapshot: &BackupSnapshot, actor_user_id: &str) -> AppResult<BackupRecord> {
+    let file_name = snapshot.path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut tx = pool.begin().await?;
+
+    let record = sqlx::query_as::<_, BackupRecord>(&format!(
+        r#"
+        INSERT INTO backup_catalog (kind, file_name, file_path, size_bytes, sha256, schema_version, encrypted, created_by)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING {}
+        "#,
+        RECORD_COLUMNS
+    ))
+    .bind(snapshot.kind.as_str())
+    .bind(&file_name)
+    .bind(snapshot.path.to_string_lossy().as_ref())
+    .bind(snapshot.size_bytes)
+    .bind(&snapshot.sha256)
+    .bind(snapshot.schema_version)
+    .bind(snapshot.encrypted)
+    .bind(actor_user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    AuditEvent::new("CREATE_BACKUP", "BACKUP", record.id.to_string())
+        .context("kind", &record.kind)
+        .context("file_name", &record.file_name)
+        .context("size_bytes", &record.size_bytes)
+        .context("sha256", &record.sha256)
+        .context("schema_version", &record.schema_version)
+        .context("encrypted", &record.encrypted)
+        .log_tx(&mut tx, actor_user_id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+pub async fn create_backup(pool: &DbPool, app_dir: &Path, kind: BackupKind, actor_user_id: &str) -> AppResult<BackupRecord> {
+    let snapshot = take_snapshot(&mut *pool.acquire().await?, app_dir, kind).await?;
+    record_snapshot(pool, snapshot, actor_user_id).await
+}
+
+pub async fn load_record(pool: &DbPool, id: i64) -> AppResult<BackupRecord> {
+    sqlx::query_as::<_, BackupRecord>(&format!("SELECT {} FROM backup_catalog WHERE id = ?", RECORD_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Backup not found".to_string()))
+}
+
+// Deletes a backup file. Its catalog row is kept and marked as deleted, and is
+// committed first: a file left behind is harmless, a row pointing at a missing
+// file is not.
+pub async fn retire_backup(pool: &DbPool, record: &BackupRecord, actor_user_id: &str, reason: &str) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query("UPDATE backup_catalog SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL")
+        .bind(record.id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict("The backup has already been deleted".to_string()));
+    }
+
+    AuditEvent::new("DELETE_BACKUP", "BACKUP", record.id.to_string())
+        .context("kind", &record.kind)
+        .context("file_name", &record.file_name)
+        .context("reason", reason)
+        .log_tx(&mut tx, actor_user_id)
+        .await?;
+
+    tx.commit().await?;
+
+    // A file that is already gone only needed its row updated
+    match fs::remove_file(&record.file_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            eprintln!("Backup {} was retired but its file could not be removed: {}", record.file_path, e);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+// With a passphrase, produces an encrypted archive that also holds the photos
+#[tauri::command]
+pub async fn backup_db(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    passphrase: Option<String>
+) -> AppResult<BackupRecord> {
+    let user = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+
+    let app_dir = crate::workspace::data_dir(&app)?;
+    match passphrase {
+        Some(passphrase) => {
+            let snapshot = take_encrypted_snapshot(&pool, &app_dir, &passphrase).await?;
+            record_snapshot(&pool, snapshot, &user.id).await
+        }
+        None => create_backup(&pool, &app_dir, BackupKind::Manual, &user.id).await,
+    }
+}
+
+#[tauri::command]
+pub async fn list_backups(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<BackupRecord>> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+
+    let backups = sqlx::query_as::<_, BackupRecord>(&format!(
+        "SELECT {} FROM backup_catalog ORDER BY created_at DESC, id DESC",
+        RECORD_COLUMNS
+    ))
+    .fetch_all(&*pool)
+    .await?;
+
+    Ok(backups)
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupInspection {
+    pub record: BackupRecord,
+    pub file_exists: bool,
+    pub checksum_matches: bool,
+    // Encrypted archives cannot be opened without their passphrase
+    pub integrity_ok: Option<bool>,
+    pub schema_version: Option<i64>,
+    pub problem: Option<String>,
+}
+
+// Re-verifies a cataloged backup: the file is still there, unchanged, and readable
+#[tauri::command]
+pub async fn inspect_backup(
+    pool: State<'_, DbPool>,
+    token: String,
+    backup_id: i64
+) -> AppResult<BackupInspection> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+
+    let record = load_record(&pool, backup_id).await?;
+    let path = PathBuf::from(&record.file_path);
+    let mut inspection = BackupInspection {
+        file_exists: path.is_file(),
+        checksum_matches: false,
+        integrity_ok: None,
+        schema_version: None,
+        problem: None,
+        record,
+    };
+
+    if !inspection.file_exists {
+        inspection.problem = Some("The backup file is missing".to_string());
+        return Ok(inspection);
+    }
+
+    inspection.checksum_matches = file_sha256(&path)? == inspection.record.sha256;
+    if !inspection.checksum_matches {
+        inspection.problem = Some("The file has changed since the backup was taken".to_string());
+    }
+
+    if !inspection.record.encrypted {
+        match inspect_copy(&path).await {
+            Ok(schema_version) => {
+                inspection.integrity_ok = Some(true);
+                inspection.schema_version = schema_version;
+            }
+            Err(e) => {
+                inspection.integrity_ok = Some(false);
+                inspection.problem.get_or_insert(e.to_string());
+            }
+        }
+    }
+
+    Ok(inspection)
+}
+
+#[tauri::command]
+pub async fn delete_backup(
+    pool: State<'_, DbPool>,
+    token: String,
+    backup_id: i64
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    let record = load_record(&pool, backup_id).await?;
+    retire_backup(&pool, &record, &admin.id, "manual").await
+}
@@ -0,0 +1,269 @@
+use crate::error::{AppError, AppResult};
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{rngs::OsRng, RngCore};
+use std::io::{self, BufRead, Read, Write};
+
+// File layout: MAGIC | KDF parameters | salt | nonce prefix | chunks.
+// Everything before the chunks is authenticated as associated data of every chunk.
+const MAGIC: &[u8; 8] = b"RSNBAK\x00\x01";
+const SALT_LEN: usize = 16;
+// AES-GCM's 12-byte nonce minus the 5 bytes the STREAM construction uses as a counter
+const NONCE_PREFIX_LEN: usize = 7;
+const HEADER_LEN: usize = MAGIC.len() + 3 * 4 + SALT_LEN + NONCE_PREFIX_LEN;
+
+// Plaintext bytes per chunk; each ciphertext chunk carries a 16-byte tag
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+
+// Argon2id cost for new archives; older archives carry their own parameters
+#[cfg(not(test))]
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+#[cfg(test)]
+const KDF_MEMORY_KIB: u32 = 1024;
+const KDF_ITERATIONS: u32 = 3;
+const KDF_PARALLELISM: u32 = 1;
+// Upper bounds accepted from a file, so a crafted header cannot exhaust memory
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 20;
+
+pub const MIN_PASSPHRASE_LENGTH: usize = 12;
+
+struct Header {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: [u8; SALT_LEN],
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+}
+
+impl Header {
+    fn generate() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce_prefix);
+
+        Header {
+            memory_kib: KDF_MEMORY_KIB,
+            iterations: KDF_ITERATIONS,
+            parallelism: KDF_PARALLELISM,
+            salt,
+            nonce_prefix,
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.memory_kib.to_be_bytes());
+        bytes.extend_from_slice(&self.iterations.to_be_bytes());
+        bytes.extend_from_slice(&self.parallelism.to_be_bytes());
+        bytes.extend_from_slice(&self.salt);
+        bytes.extend_from_slice(&self.nonce_prefix);
+        bytes
+    }
+
+    fn read<R: Read>(reader: &mut R) -> AppResult<(Self, Vec<u8>)> {
+        let mut bytes = vec![0u8; HEADER_LEN];
+        if reader.read_exact(&mut bytes).is_err() || !bytes.starts_with(MAGIC) {
+            return Err(AppError::invalid("The file is not an encrypted backup"));
+        }
+
+        let u32_at = |offset: usize| u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let params_end = MAGIC.len() + 12;
+        let header = Header {
+            memory_kib: u32_at(MAGIC.len()),
+            iterations: u32_at(MAGIC.len() + 4),
+            parallelism: u32_at(MAGIC.len() + 8),
+            salt: bytes[params_end..params_end + SALT_LEN].try_into().unwrap(),
+            nonce_prefix: bytes[params_end + SALT_LEN..].try_into().unwrap(),
+        };
+
+        if header.memory_kib > MAX_KDF_MEMORY_KIB || header.iterations > MAX_KDF_ITERATIONS {
+            return Err(AppError::invalid("The backup uses unsupported key derivation settings"));
+        }
+
+        Ok((header, bytes))
+    }
+
+    fn cipher(&self, passphrase: &str) -> AppResult<Aes256Gcm> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| AppError::invalid(format!("Invalid key derivation settings: {}", e)))?;
+
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut key)
+            .map_err(|e| AppError::Internal(format!("key derivation failed: {}", e)))?;
+
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+}
+
+pub fn validate_passphrase(passphrase: &str) -> AppResult<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(AppError::invalid_field(
+            "passphrase",
+            format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LENGTH)
+        ));
+    }
+    Ok(())
+}
+
+// Tampering and truncation surface as InvalidData while the archive is read
+fn damaged() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "backup chunk failed authentication")
+}
+
+// Encrypts everything written to it; call `finish` to write the final chunk
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: EncryptorBE32<Aes256Gcm>,
+    aad: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub fn new(mut inner: W, passphrase: &str) -> AppResult<Self> {
+        let header = Header::generate();
+        let aad = header.to_bytes();
+        inner.write_all(&aad)?;
+
+        let encryptor = EncryptorBE32::from_aead(header.cipher(passphrase)?, (&header.nonce_prefix).into());
+
+        Ok(EncryptWriter { inner, encryptor, aad, buffer: Vec::with_capacity(CHUNK_SIZE * 2) })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        let EncryptWriter { mut inner, encryptor, aad, buffer } = self;
+
+        let chunk = encryptor
+            .encrypt_last(Payload { msg: &buffer, aad: &aad })
+            .map_err(|_| io::Error::other("backup encryption failed"))?;
+        inner.write_all(&chunk)?;
+        inner.flush()?;
+
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        // Keep at least one byte back: only `finish` knows which chunk is last
+        while self.buffer.len() > CHUNK_SIZE {
+            let chunk = self.encryptor
+                .encrypt_next(Payload { msg: &self.buffer[..CHUNK_SIZE], aad: &self.aad })
+                .map_err(|_| io::Error::other("backup encryption failed"))?;
+            self.inner.write_all(&chunk)?;
+            self.buffer.drain(..CHUNK_SIZE);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Decrypts and authenticates chunk by chunk; a missing final chunk is an error
+pub struct DecryptReader<R: BufRead> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<Aes256Gcm>>,
+    aad: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> DecryptReader<R> {
+    pub fn new(mut inner: R, passphrase: &str) -> AppResult<Self> {
+        let (header, aad) = Header::read(&mut inner)?;
+        let decryptor = DecryptorBE32::from_aead(header.cipher(passphrase)?, (&header.nonce_prefix).into());
+
+        let mut reader = DecryptReader { inner, decryptor: Some(decryptor), aad, plain: Vec::new(), pos: 0 };
+
+        // The first chunk tells a wrong passphrase apart before anything is unpacked
+        reader.next_chunk().map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => AppError::InvalidCredentials("Wrong passphrase or damaged backup file".to_string()),
+            _ => AppError::from(e),
+        })?;
+
+        Ok(reader)
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE + TAG_LEN);
+        (&mut self.inner).take((CHUNK_SIZE + TAG_LEN) as u64).read_to_end(&mut chunk)?;
+        let last = self.inner.fill_buf()?.is_empty();
+
+        let payload = Payload { msg: &chunk, aad: &self.aad };
+        self.plain = if last {
+            let decryptor = self.decryptor.take().ok_or_else(damaged)?;
+            decryptor.decrypt_last(payload)
+        } else {
+            let decryptor = self.decryptor.as_mut().ok_or_else(damaged)?;
+            decryptor.decrypt_next(payload)
+        }
+        .map_err(|_| damaged())?;
+        self.pos = 0;
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+
+        let n = buf.len().min(self.plain.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encrypt(data: &[u8], passphrase: &str) -> Vec<u8> {
+        let mut writer = EncryptWriter::new(Vec::new(), passphrase).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(file: &[u8], passphrase: &str) -> AppResult<Vec<u8>> {
+        let mut plain = Vec::new();
+        DecryptReader::new(file, passphrase)?.read_to_end(&mut plain)?;
+        Ok(plain)
+    }
+
+    #[test]
+    fn round_trips_and_detects_tampering() {
+        // Spans several chunks and ends exactly on a chunk boundary
+        let data: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| (i % 251) as u8).collect();
+        let file = encrypt(&data, "correct horse battery");
+
+        assert_eq!(decrypt(&file, "correct horse battery").unwrap(), data);
+        assert!(matches!(decrypt(&file, "wrong passphrase!!"), Err(AppError::InvalidCredentials(_))));
+
+        let mut tampered = file.clone();
+        tampered[HEADER_LEN + CHUNK_SIZE + TAG_LEN + 10] ^= 1;
+        assert!(decrypt(&tampered, "correct horse battery").is_err());
+
+        // Dropping the final chunk must not look like a complete archive
+        let truncated = &file[..HEADER_LEN + 2 * (CHUNK_SIZE + TAG_LEN)];
+        assert!(decrypt(truncated, "correct horse battery").is_err());
+
+        assert_eq!(decrypt(&encrypt(b"", "correct horse battery"), "correct horse battery").unwrap(), b"");
+    }
+}
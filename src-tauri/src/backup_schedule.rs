@@ -0,0 +1,239 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit::SYSTEM_USER_ID;
+use crate::audit_event::AuditEvent;
+use crate::backup::{create_backup, retire_backup, BackupKind, BackupRecord, RECORD_COLUMNS};
+use crate::permissions::SYSTEM_BACKUP;
+use crate::system_status::{SystemState, SystemStatus};
+use tauri::{AppHandle, Manager, State};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use chrono::{Datelike, NaiveDateTime};
+use std::collections::HashSet;
+use std::time::Duration;
+
+// How often the scheduler checks whether a backup is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(15 * 60);
+const MAX_INTERVAL_HOURS: i64 = 720;
+const MAX_KEEP_DAILY: i64 = 366;
+const MAX_KEEP_WEEKLY: i64 = 260;
+const MAX_KEEP_MONTHLY: i64 = 240;
+
+// Applies to automatic backups only; manual backups are kept until deleted
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BackupSchedule {
+    pub interval_hours: Option<i64>,
+    pub keep_daily: i64,
+    pub keep_weekly: i64,
+    pub keep_monthly: i64,
+}
+
+pub async fn load_schedule(pool: &DbPool) -> AppResult<BackupSchedule> {
+    let schedule = sqlx::query_as::<_, BackupSchedule>(
+        "SELECT interval_hours, keep_daily, keep_weekly, keep_monthly FROM backup_schedule WHERE id = 1"
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(schedule)
+}
+
+// Marks the newest backup of each of the `limit` most recent periods.
+// `backups` is newest first, so each period's backups are contiguous.
+fn keep_newest_per<K: PartialEq>(
+    keep: &mut HashSet<i64>,
+    backups: &[(i64, NaiveDateTime)],
+    limit: i64,
+    period: impl Fn(&NaiveDateTime) -> K
+) {
+    let mut last_period = None;
+    let mut kept = 0;
+    for (id, created_at) in backups {
+        if kept >= limit {
+            break;
+        }
+        let current = period(created_at);
+        if last_period.as_ref() != Some(&current) {
+            keep.insert(*id);
+            kept += 1;
+            last_period = Some(current);
+        }
+    }
+}
+
+// Grandfather-father-son selection; the newest backup is always kept
+fn backups_to_keep(schedule: &BackupSchedule, backups: &[(i64, NaiveDateTime)]) -> HashSet<i64> {
+    let mut keep = HashSet::new();
+    if let Some((id, _)) = backups.first() {
+        keep.insert(*id);
+    }
+
+    keep_newest_per(&mut keep, backups, schedule.keep_daily, |t| t.date());
+    keep_newest_per(&mut keep, backups, schedule.keep_weekly, |t| t.iso_week());
+    keep_newest_per(&mut keep, backups, schedule.keep_monthly, |t| (t.year(), t.month()));
+
+    keep
+}
+
+// Deletes automatic backups that fall outside the retention scheme
+pub async fn rotate_backups(pool: &DbPool, actor_user_id: &str) -> AppResult<usize> {
+    let schedule = load_schedule(pool).await?;
+
+    let backups = sqlx::query_as::<_, BackupRecord>(&format!(
+        "SELECT {} FROM backup_catalog WHERE kind = 'AUTO' AND deleted_at IS NULL ORDER BY created_at DESC, id DESC",
+        RECORD_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let dates: Vec<(i64, NaiveDateTime)> = backups.iter().map(|b| (b.id, b.created_at)).collect();
+    let keep = backups_to_keep(&schedule, &dates);
+
+    let mut removed = 0;
+    for record in backups.iter().filter(|b| !keep.contains(&b.id)) {
+        retire_backup(pool, record, actor_user_id, "rotation").await?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+async fn run_due_backup(pool: &DbPool, app_dir: &std::path::Path) -> AppResult<()> {
+    let schedule = load_schedule(pool).await?;
+    let Some(interval_hours) = schedule.interval_hours else {
+        return Ok(());
+    };
+
+    let due: bool = sqlx::query_scalar(
+        "SELECT NOT EXISTS (SELECT 1 FROM backup_catalog WHERE kind = 'AUTO' AND created_at > datetime('now', ?))"
+    )
+    .bind(format!("-{} hours", interval_hours))
+    .fetch_one(pool)
+    .await?;
+    if !due {
+        return Ok(());
+    }
+
+    let record = create_backup(pool, app_dir, BackupKind::Auto, SYSTEM_USER_ID).await?;
+    println!("Scheduled backup created at {}", record.file_path);
+
+    rotate_backups(pool, SYSTEM_USER_ID).await?;
+    Ok(())
+}
+
+// Runs for the life of the app. Checking on a short tick rather than sleeping a
+// whole interval picks up schedule changes and time spent suspended.
+pub async fn run_scheduler(app: AppHandle, pool: DbPool) {
+    loop {
+        tokio::time::sleep(SCHEDULER_TICK).await;
+        // Skipped while a workspace is being switched, unlocked or re-encrypted
+        if !app.try_state::<SystemState>().is_some_and(|s| matches!(s.get(), SystemStatus::Ready)) {
+            continue;
+        }
+        let Ok(app_dir) = crate::workspace::data_dir(&app) else {
+            continue;
+        };
+        if let Err(e) = run_due_backup(&pool, &app_dir).await {
+            eprintln!("Scheduled backup failed: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_backup_schedule(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<BackupSchedule> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    load_schedule(&pool).await
+}
+
+// `interval_hours` of None turns periodic backups off. The retention counts take
+// effect at the next automatic backup.
+#[tauri::command]
+pub async fn set_backup_schedule(
+    pool: State<'_, DbPool>,
+    token: String,
+    schedule: BackupSchedule
+) -> AppResult<BackupSchedule> {
+    let admin = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    if let Some(hours) = schedule.interval_hours {
+        if !(1..=MAX_INTERVAL_HOURS).contains(&hours) {
+            return Err(AppError::invalid_field(
+                "interval_hours",
+                format!("Backup interval must be between 1 and {} hours", MAX_INTERVAL_HOURS)
+            ));
+        }
+    }
+    for (field, value, max) in [
+        ("keep_daily", schedule.keep_daily, MAX_KEEP_DAILY),
+        ("keep_weekly", schedule.keep_weekly, MAX_KEEP_WEEKLY),
+        ("keep_monthly", schedule.keep_monthly, MAX_KEEP_MONTHLY),
+    ] {
+        if !(0..=max).contains(&value) {
+            return Err(AppError::invalid_field(field, format!("Must be between 0 and {}", max)));
+        }
+    }
+
+    let previous = load_schedule(&pool).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE backup_schedule
+        SET interval_hours = ?, keep_daily = ?, keep_weekly = ?, keep_monthly = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = 1
+        "#
+    )
+    .bind(schedule.interval_hours)
+    .bind(schedule.keep_daily)
+    .bind(schedule.keep_weekly)
+    .bind(schedule.keep_monthly)
+    .execute(&mut *tx)
+    .await?;
+
+    AuditEvent::new("UPDATE_BACKUP_SCHEDULE", "SYSTEM", "BACKUP_SCHEDULE")
+        .diff(&previous, &schedule)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn keeps_newest_backup_per_period() {
+        let schedule = BackupSchedule { interval_hours: Some(24), keep_daily: 3, keep_weekly: 2, keep_monthly: 2 };
+
+        // Two backups a day for 60 days, newest first; ids count down with age
+        let start = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let backups: Vec<(i64, NaiveDateTime)> = (0..60)
+            .flat_map(|day| {
+                let date = start - chrono::Days::new(day);
+                [(18, 2 * day as i64), (6, 2 * day as i64 + 1)]
+                    .map(|(hour, n)| (1000 - n, date.and_hms_opt(hour, 0, 0).unwrap()))
+            })
+            .collect();
+
+        let mut keep: Vec<i64> = backups_to_keep(&schedule, &backups).into_iter().collect();
+        keep.sort_unstable_by(|a, b| b.cmp(a));
+
+        // Daily: Mar 31, 30, 29. Weekly: Sun Mar 31 (week 13), Sun Mar 24 (week 12).
+        // Monthly: Mar 31 and Feb 29.
+        assert_eq!(keep, vec![1000, 998, 996, 986, 938]);
+
+        let none = BackupSchedule { interval_hours: None, keep_daily: 0, keep_weekly: 0, keep_monthly: 0 };
+        assert_eq!(backups_to_keep(&none, &backups), HashSet::from([1000]));
+        assert!(backups_to_keep(&schedule, &[]).is_empty());
+    }
+}
@@ -1,50 +1,246 @@
 use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{
+    authenticate, check_permission, create_session, mark_reauthenticated, resolve_session,
+    revoke_other_sessions, revoke_session, revoke_user_sessions,
+};
+use crate::audit_event::AuditEvent;
+use crate::password::{enforce_policy, set_password};
+use crate::lockout::{check_login_allowed, record_failure, record_success};
+use crate::totp::{is_required_for_role, verify_second_factor};
+use crate::permissions::SYSTEM_USERS;
 use tauri::State;
 use serde::{Serialize};
-use bcrypt::verify;
+use bcrypt::{hash, verify, DEFAULT_COST};
 use sqlx::Row;
+use std::sync::OnceLock;
+
+// Checked against for unknown emails, so that they take as long as a wrong password
+fn dummy_password_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash("not-a-password", DEFAULT_COST).unwrap_or_default())
+}
 
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub id: String,
     pub email: String,
     pub role: String,
+    pub token: String,
+    pub expires_at: chrono::NaiveDateTime,
+    pub must_change_password: bool,
+    pub two_factor_setup_required: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionUser {
+    pub id: String,
+    pub email: String,
+    pub role: String,
+    pub must_change_password: bool,
+    pub two_factor_setup_required: bool,
 }
 
 #[tauri::command]
 pub async fn login(
     pool: State<'_, DbPool>,
     email: String,
-    password_plain: String
-) -> Result<AuthResponse, String> {
+    password_plain: String,
+    otp_code: Option<String>
+) -> AppResult<AuthResponse> {
+    // Stored lowercased by create_user; normalizing here also keeps the
+    // per-email failure counters from being split by case
+    let email = email.trim().to_lowercase();
+
     let row = sqlx::query("SELECT * FROM users WHERE email = ? AND active = 1")
         .bind(&email)
         .fetch_optional(&*pool)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if let Some(row) = row {
-        let hash_str: String = row.try_get("password_hash").map_err(|e| e.to_string())?;
-        
-        // Verify password
-        let valid = verify(&password_plain, &hash_str).map_err(|e| e.to_string())?;
+        .await?;
+
+    let user_id: Option<String> = match &row {
+        Some(row) => Some(row.try_get("id")?),
+        None => None,
+    };
+
+    // Throttled attempts are rejected before the password is even checked
+    check_login_allowed(&pool, &email, user_id.as_deref()).await?;
+
+    let hash_str: String = match &row {
+        Some(row) => row.try_get("password_hash")?,
+        None => dummy_password_hash().to_string(),
+    };
+
+    // Verify password; also for unknown emails, so timing does not reveal accounts
+    let valid = verify(&password_plain, &hash_str)?;
+
+    if let (Some(row), Some(user_id)) = (&row, &user_id) {
         if valid {
-            let user_id: String = row.try_get("id").map_err(|e| e.to_string())?;
-            let role: String = row.try_get("role").map_err(|e| e.to_string())?;
-            
-            // Update last login
-            let _ = sqlx::query("UPDATE users SET last_login_at = CURRENT_TIMESTAMP WHERE id = ?")
-                .bind(&user_id)
-                .execute(&*pool)
-                .await;
+            let user_id = user_id.clone();
+            let role: String = row.try_get("role")?;
+            let must_change_password: bool = row.try_get("must_change_password").unwrap_or(false);
+            let totp_enabled: bool = row.try_get("totp_enabled").unwrap_or(false);
+
+            if totp_enabled {
+                let code = otp_code.as_deref().map(str::trim).unwrap_or_default();
+                if code.is_empty() {
+                    return Err(AppError::TwoFactorRequired);
+                }
+                if !verify_second_factor(&pool, &user_id, code).await? {
+                    record_failure(&pool, &email, Some(&user_id)).await?;
+                    return Err(AppError::InvalidCredentials("Invalid two-factor code".to_string()));
+                }
+            }
+            let two_factor_setup_required = !totp_enabled && is_required_for_role(&pool, &role).await?;
+
+            // Resets failure counters and updates last login
+            record_success(&pool, &user_id).await?;
+
+            let (token, expires_at) = create_session(&pool, &user_id).await?;
 
             return Ok(AuthResponse {
                 id: user_id,
                 email,
                 role,
+                token,
+                expires_at,
+                must_change_password,
+                two_factor_setup_required,
             });
         }
     }
 
-    Err("Invalid credentials".to_string())
+    record_failure(&pool, &email, user_id.as_deref()).await?;
+
+    Err(AppError::InvalidCredentials("Invalid credentials".to_string()))
+}
+
+#[tauri::command]
+pub async fn logout(
+    pool: State<'_, DbPool>,
+    token: String
+) -> AppResult<()> {
+    revoke_session(&pool, &token).await
+}
+
+// Lets the UI restore a stored session on reload without re-entering credentials
+#[tauri::command]
+pub async fn get_current_user(
+    pool: State<'_, DbPool>,
+    token: String
+) -> AppResult<SessionUser> {
+    let user = resolve_session(&pool, &token).await?;
+    let two_factor_setup_required = !user.totp_enabled && is_required_for_role(&pool, &user.role).await?;
+
+    Ok(SessionUser {
+        id: user.id,
+        email: user.email,
+        role: user.role,
+        must_change_password: user.must_change_password,
+        two_factor_setup_required,
+    })
+}
+
+#[tauri::command]
+pub async fn change_password(
+    pool: State<'_, DbPool>,
+    token: String,
+    current_password_plain: String,
+    new_password_plain: String
+) -> AppResult<()> {
+    // Not check_permission: accounts flagged must_change_password need this command
+    let user = resolve_session(&pool, &token).await?;
+
+    // Shares the login throttle so a stolen session cannot be used to guess the password
+    check_login_allowed(&pool, &user.email, Some(&user.id)).await?;
+
+    let valid = verify(&current_password_plain, &user.password_hash)?;
+    if !valid {
+        record_failure(&pool, &user.email, Some(&user.id)).await?;
+        return Err(AppError::InvalidCredentials("Current password is incorrect".to_string()));
+    }
+
+    enforce_policy(&pool, Some((&user.id, &user.email)), &new_password_plain).await?;
+
+    let mut tx = pool.begin().await?;
+    set_password(&mut tx, &user.id, &new_password_plain, false).await?;
+
+    // Keep the caller signed in but end any other sessions
+    revoke_other_sessions(&mut tx, &user.id, &token).await?;
+
+    AuditEvent::new("CHANGE_PASSWORD", "USER", &user.id)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn revoke_sessions(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String
+) -> AppResult<u64> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let mut tx = pool.begin().await?;
+    let revoked = revoke_user_sessions(&mut tx, &target_user_id).await?;
+
+    AuditEvent::new("REVOKE_SESSIONS", "USER", &target_user_id)
+        .context("sessions_revoked", &revoked)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(revoked)
+}
+
+// Step-up confirmation for commands guarded by require_recent_auth.
+// Accepts the account password or, for enrolled users, a TOTP/recovery code.
+#[tauri::command]
+pub async fn confirm_reauth(
+    pool: State<'_, DbPool>,
+    token: String,
+    password_plain: Option<String>,
+    otp_code: Option<String>
+) -> AppResult<()> {
+    let user = authenticate(&pool, &token).await?;
+
+    // Shares the login throttle so a stolen session cannot be used to guess the password
+    check_login_allowed(&pool, &user.email, Some(&user.id)).await?;
+
+    let password = password_plain.as_deref().unwrap_or_default();
+    let code = otp_code.as_deref().map(str::trim).unwrap_or_default();
+
+    let (method, valid) = if !password.is_empty() {
+        ("PASSWORD", verify(password, &user.password_hash)?)
+    } else if !code.is_empty() && user.totp_enabled {
+        ("TWO_FACTOR", verify_second_factor(&pool, &user.id, code).await?)
+    } else {
+        return Err(AppError::invalid_field("password_plain", "Enter your password to confirm"));
+    };
+
+    if !valid {
+        // Counted before it is audited, so a failing audit cannot lift the throttle
+        record_failure(&pool, &user.email, Some(&user.id)).await?;
+        AuditEvent::new("REAUTH_FAILED", "USER", &user.id)
+            .context("method", method)
+            .log(&pool, &user.id)
+            .await?;
+        return Err(AppError::InvalidCredentials("Confirmation failed".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    mark_reauthenticated(&mut tx, &token).await?;
+
+    AuditEvent::new("REAUTHENTICATE", "USER", &user.id)
+        .context("method", method)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
 }
@@ -1,6 +1,8 @@
 use crate::db::DbPool;
-use crate::models::{Role};
-use crate::auth::check_auth;
+use crate::error::AppResult;
+use crate::auth::{check_permission, ensure_student_access};
+use crate::permissions::{ATTENDANCE_READ, GRADES_READ};
+use crate::read_audit::{record_read, VIEW_ASSESSMENTS, VIEW_ATTENDANCE};
 use tauri::State;
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
@@ -29,19 +31,20 @@ pub struct Assessment {
 #[tauri::command]
 pub async fn get_student_attendance(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     student_id: String
-) -> Result<Vec<AttendanceRecord>, String> {
-    // Finance cannot access attendance
-    check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher]).await?;
+) -> AppResult<Vec<AttendanceRecord>> {
+    let user = check_permission(&pool, &token, ATTENDANCE_READ).await?;
+    ensure_student_access(&pool, &user, &student_id).await?;
 
     let records = sqlx::query_as::<_, AttendanceRecord>(
         "SELECT id, student_id, class_id, date, status, note FROM attendance_records WHERE student_id = ? ORDER BY date DESC"
     )
-    .bind(student_id)
+    .bind(&student_id)
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+
+    record_read(&pool, &user.id, VIEW_ATTENDANCE, "STUDENT", &student_id).await?;
 
     Ok(records)
 }
@@ -49,11 +52,11 @@ pub async fn get_student_attendance(
 #[tauri::command]
 pub async fn get_student_assessments(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     student_id: String
-) -> Result<Vec<Assessment>, String> {
-    // Finance cannot access assessments
-    check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher]).await?;
+) -> AppResult<Vec<Assessment>> {
+    let user = check_permission(&pool, &token, GRADES_READ).await?;
+    ensure_student_access(&pool, &user, &student_id).await?;
 
     let assessments = sqlx::query_as::<_, Assessment>(
         r#"
@@ -63,10 +66,11 @@ pub async fn get_student_assessments(
         ORDER BY date DESC
         "#
     )
-    .bind(student_id)
+    .bind(&student_id)
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+
+    record_read(&pool, &user.id, VIEW_ASSESSMENTS, "STUDENT", &student_id).await?;
 
     Ok(assessments)
 }
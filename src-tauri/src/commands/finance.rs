@@ -1,6 +1,8 @@
 use crate::db::DbPool;
-use crate::models::{Role};
-use crate::auth::check_auth;
+use crate::error::AppResult;
+use crate::auth::{check_permission, ensure_student_access};
+use crate::permissions::FINANCE_INVOICES_READ;
+use crate::read_audit::{record_read, VIEW_INVOICES};
 use tauri::State;
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
@@ -27,17 +29,15 @@ pub struct Payment {
 #[tauri::command]
 pub async fn get_invoices(
     pool: State<'_, DbPool>,
-    user_id: String,
-) -> Result<Vec<Invoice>, String> {
-    // Teacher cannot access finance
-    check_auth(&pool, &user_id, &[Role::Admin, Role::ManagementFinance]).await?;
+    token: String,
+) -> AppResult<Vec<Invoice>> {
+    check_permission(&pool, &token, FINANCE_INVOICES_READ).await?;
 
     let invoices = sqlx::query_as::<_, Invoice>(
         "SELECT id, student_id, due_date, total_amount, status FROM invoices ORDER BY due_date DESC"
     )
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(invoices)
 }
@@ -45,19 +45,20 @@ pub async fn get_invoices(
 #[tauri::command]
 pub async fn get_student_invoices(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     student_id: String
-) -> Result<Vec<Invoice>, String> {
-    // Teacher cannot access finance
-    check_auth(&pool, &user_id, &[Role::Admin, Role::ManagementFinance]).await?;
+) -> AppResult<Vec<Invoice>> {
+    let user = check_permission(&pool, &token, FINANCE_INVOICES_READ).await?;
+    ensure_student_access(&pool, &user, &student_id).await?;
 
     let invoices = sqlx::query_as::<_, Invoice>(
         "SELECT id, student_id, due_date, total_amount, status FROM invoices WHERE student_id = ? ORDER BY due_date DESC"
     )
-    .bind(student_id)
+    .bind(&student_id)
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+
+    record_read(&pool, &user.id, VIEW_INVOICES, "STUDENT", &student_id).await?;
 
     Ok(invoices)
 }
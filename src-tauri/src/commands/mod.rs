@@ -3,3 +3,7 @@ pub mod staff;
 pub mod education;
 pub mod finance;
 pub mod auth;
+pub mod users;
+pub mod two_factor;
+pub mod permissions;
+pub mod roles;
@@ -0,0 +1,99 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::models::Role;
+use crate::auth::{authenticate, check_permission};
+use crate::audit_event::AuditEvent;
+use crate::permissions::{is_registered, role_permissions, validate_role, REGISTRY, SYSTEM_USERS};
+use tauri::State;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct PermissionEntry {
+    pub key: String,
+    pub description: String,
+    pub matrix_row: Option<String>,
+    pub roles: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn get_permission_matrix(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<PermissionEntry>> {
+    // Editing the mapping is tied to the Admin role itself so it can never be revoked
+    check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let grants = sqlx::query_as::<_, (String, String)>("SELECT permission_key, role FROM role_permissions ORDER BY role")
+        .fetch_all(&*pool)
+        .await?;
+
+    let entries = REGISTRY
+        .iter()
+        .map(|def| PermissionEntry {
+            key: def.key.to_string(),
+            description: def.description.to_string(),
+            matrix_row: def.matrix_row.map(str::to_string),
+            roles: grants
+                .iter()
+                .filter(|(key, _)| key == def.key)
+                .map(|(_, role)| role.clone())
+                .collect(),
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+#[tauri::command]
+pub async fn set_role_permission(
+    pool: State<'_, DbPool>,
+    token: String,
+    role: String,
+    permission_key: String,
+    granted: bool
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    validate_role(&pool, &role).await?;
+
+    if !is_registered(&permission_key) {
+        return Err(AppError::invalid_field("permission_key", format!("Unknown permission '{}'", permission_key)));
+    }
+    if !granted && role == Role::Admin.to_string() && permission_key == SYSTEM_USERS {
+        return Err(AppError::Conflict("Admins cannot lose user management".to_string()));
+    }
+
+    let query = if granted {
+        "INSERT OR IGNORE INTO role_permissions (role, permission_key) VALUES (?, ?)"
+    } else {
+        "DELETE FROM role_permissions WHERE role = ? AND permission_key = ?"
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(query)
+        .bind(&role)
+        .bind(&permission_key)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() > 0 {
+        AuditEvent::new(if granted { "GRANT_PERMISSION" } else { "REVOKE_PERMISSION" }, "ROLE", &role)
+            .context("permission", &permission_key)
+            .log_tx(&mut tx, &admin.id)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Lets the UI hide actions the caller cannot perform (the backend still enforces them)
+#[tauri::command]
+pub async fn get_my_permissions(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<String>> {
+    let user = authenticate(&pool, &token).await?;
+    role_permissions(&pool, &user.role).await
+}
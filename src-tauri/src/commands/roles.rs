@@ -0,0 +1,204 @@
+use crate::db::DbPool;
+use crate::auth::check_permission;
+use crate::audit_event::AuditEvent;
+use crate::error::{AppError, AppResult};
+use crate::permissions::{is_registered, role_permissions, SYSTEM_USERS};
+use tauri::State;
+use serde::Serialize;
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct RoleInfo {
+    pub name: String,
+    pub description: String,
+    pub built_in: bool,
+    pub class_scoped: bool,
+    pub user_count: i64,
+    #[sqlx(skip)]
+    pub permissions: Vec<String>,
+}
+
+// Role names are stored upper-case, e.g. COUNSELOR or READONLY_AUDITOR
+fn normalize_role_name(name: &str) -> AppResult<String> {
+    let name = name.trim().to_ascii_uppercase().replace([' ', '-'], "_");
+    let valid = name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+
+    if valid {
+        Ok(name)
+    } else {
+        Err(AppError::invalid_field("name", format!("Invalid role name '{}'. Use letters, digits and underscores.", name)))
+    }
+}
+
+fn validate_permissions(permissions: &[String]) -> AppResult<()> {
+    match permissions.iter().find(|p| !is_registered(p)) {
+        Some(unknown) => Err(AppError::invalid_field("permissions", format!("Unknown permission '{}'", unknown))),
+        None => Ok(()),
+    }
+}
+
+async fn fetch_role(pool: &DbPool, name: &str) -> AppResult<RoleInfo> {
+    let mut role = sqlx::query_as::<_, RoleInfo>(
+        r#"
+        SELECT r.name, r.description, r.built_in, r.class_scoped,
+               (SELECT COUNT(*) FROM users u WHERE u.role = r.name) AS user_count
+        FROM roles r
+        WHERE r.name = ?
+        "#
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Unknown role '{}'", name)))?;
+
+    role.permissions = role_permissions(pool, &role.name).await?;
+    Ok(role)
+}
+
+#[tauri::command]
+pub async fn list_roles(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<RoleInfo>> {
+    check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let mut roles = sqlx::query_as::<_, RoleInfo>(
+        r#"
+        SELECT r.name, r.description, r.built_in, r.class_scoped,
+               (SELECT COUNT(*) FROM users u WHERE u.role = r.name) AS user_count
+        FROM roles r
+        ORDER BY r.built_in DESC, r.name
+        "#
+    )
+    .fetch_all(&*pool)
+    .await?;
+
+    for role in &mut roles {
+        role.permissions = role_permissions(&pool, &role.name).await?;
+    }
+
+    Ok(roles)
+}
+
+#[tauri::command]
+pub async fn create_role(
+    pool: State<'_, DbPool>,
+    token: String,
+    name: String,
+    description: String,
+    class_scoped: bool,
+    permissions: Vec<String>
+) -> AppResult<RoleInfo> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    let name = normalize_role_name(&name)?;
+    validate_permissions(&permissions)?;
+
+    let mut tx = pool.begin().await?;
+
+    let inserted = sqlx::query(
+        "INSERT OR IGNORE INTO roles (name, description, built_in, class_scoped) VALUES (?, ?, 0, ?)"
+    )
+    .bind(&name)
+    .bind(description.trim())
+    .bind(class_scoped)
+    .execute(&mut *tx)
+    .await?;
+
+    if inserted.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("Role '{}' already exists", name)));
+    }
+
+    for permission in &permissions {
+        sqlx::query("INSERT OR IGNORE INTO role_permissions (role, permission_key) VALUES (?, ?)")
+            .bind(&name)
+            .bind(permission)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    AuditEvent::new("CREATE_ROLE", "ROLE", &name)
+        .context("class_scoped", &class_scoped)
+        .context("permissions", &permissions)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_role(&pool, &name).await
+}
+
+#[tauri::command]
+pub async fn update_role(
+    pool: State<'_, DbPool>,
+    token: String,
+    name: String,
+    description: String,
+    class_scoped: bool
+) -> AppResult<RoleInfo> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    let role = fetch_role(&pool, &name).await?;
+
+    // Built-in roles back hard-wired checks (e.g. ADMIN), so only their description may change
+    if role.built_in && role.class_scoped != class_scoped {
+        return Err(AppError::invalid_field("class_scoped", "Class scoping of built-in roles cannot be changed"));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE roles SET description = ?, class_scoped = ? WHERE name = ?")
+        .bind(description.trim())
+        .bind(class_scoped)
+        .bind(&role.name)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("UPDATE_ROLE", "ROLE", &role.name)
+        .change("description", role.description.as_str(), description.trim())
+        .change("class_scoped", &role.class_scoped, &class_scoped)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_role(&pool, &role.name).await
+}
+
+#[tauri::command]
+pub async fn delete_role(
+    pool: State<'_, DbPool>,
+    token: String,
+    name: String
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    let role = fetch_role(&pool, &name).await?;
+
+    if role.built_in {
+        return Err(AppError::Conflict("Built-in roles cannot be deleted".to_string()));
+    }
+    if role.user_count > 0 {
+        return Err(AppError::Conflict(format!("Role '{}' is still assigned to {} user(s)", role.name, role.user_count)));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for query in [
+        "DELETE FROM role_permissions WHERE role = ?",
+        "DELETE FROM two_factor_policy WHERE role = ?",
+        "DELETE FROM roles WHERE name = ?",
+    ] {
+        sqlx::query(query)
+            .bind(&role.name)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    AuditEvent::new("DELETE_ROLE", "ROLE", &role.name)
+        .context("permissions", &role.permissions)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
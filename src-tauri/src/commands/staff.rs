@@ -1,6 +1,7 @@
 use crate::db::DbPool;
-use crate::auth::check_auth;
-use crate::models::Role;
+use crate::error::AppResult;
+use crate::auth::check_permission;
+use crate::permissions::STAFF_READ;
 use tauri::State;
 use serde::Serialize;
 use sqlx::FromRow;
@@ -20,10 +21,9 @@ pub struct StaffProfile {
 #[tauri::command]
 pub async fn get_staff(
     pool: State<'_, DbPool>,
-    user_id: String,
-) -> Result<Vec<StaffProfile>, String> {
-    // All roles can see staff list (directory)
-    check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher, Role::ManagementFinance]).await?;
+    token: String,
+) -> AppResult<Vec<StaffProfile>> {
+    check_permission(&pool, &token, STAFF_READ).await?;
 
     let staff = sqlx::query_as::<_, StaffProfile>(
         r#"
@@ -35,8 +35,7 @@ pub async fn get_staff(
         "#
     )
     .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(staff)
 }
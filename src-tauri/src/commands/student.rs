@@ -1,6 +1,10 @@
 use crate::db::DbPool;
-use crate::models::{Role, StudentEducationProfile, StudentFinanceProfile};
-use crate::auth::check_auth;
+use crate::error::{AppError, AppResult};
+use crate::models::{StudentContact, StudentEducationProfile, StudentFinanceProfile};
+use crate::auth::{check_permission, class_scope, ensure_student_access, has_permission, STAFF_CLASS_IDS};
+use crate::permissions::{STUDENTS_READ, STUDENTS_READ_SENSITIVE, STUDENTS_WRITE_SENSITIVE};
+use crate::audit_event::AuditEvent;
+use crate::read_audit::{record_read, VIEW_STUDENT_PROFILE};
 use tauri::State;
 use serde::Serialize;
 
@@ -21,14 +25,21 @@ pub enum StudentProfileResult {
 #[tauri::command]
 pub async fn get_students(
     pool: State<'_, DbPool>,
-    user_id: String,
-) -> Result<StudentListResult, String> {
-    let user = check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher, Role::ManagementFinance]).await?;
-    let user_role = Role::from(user.role);
-
-    match user_role {
-        Role::ManagementFinance => {
-            let students = sqlx::query_as::<_, StudentFinanceProfile>(
+    token: String,
+) -> AppResult<StudentListResult> {
+    let user = check_permission(&pool, &token, STUDENTS_READ).await?;
+
+    // Class-scoped roles only see students in their assigned classes
+    let scope = class_scope(&pool, &user).await?;
+    let scope_filter = match scope {
+        Some(_) => format!(" AND s.class_id IN ({})", STAFF_CLASS_IDS),
+        None => String::new(),
+    };
+
+    // Without the sensitive-read permission (e.g. Finance) only minimal columns are returned
+    match has_permission(&pool, &user, STUDENTS_READ_SENSITIVE).await? {
+        false => {
+            let sql = format!(
                 r#"
                 SELECT 
                     s.id, s.full_name, s.student_code, 
@@ -36,16 +47,23 @@ pub async fn get_students(
                 FROM students s
                 JOIN classes c ON s.class_id = c.id
                 WHERE s.status = 'ACTIVE'
-                "#
-            )
-            .fetch_all(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
+                {}"#,
+                scope_filter
+            );
+
+            let mut query = sqlx::query_as::<_, StudentFinanceProfile>(&sql);
+            if let Some(staff_id) = &scope {
+                query = query.bind(staff_id).bind(staff_id);
+            }
+
+            let students = query
+                .fetch_all(&*pool)
+                .await?;
             
             Ok(StudentListResult::Finance(students))
         }
-        _ => {
-            let students = sqlx::query_as::<_, StudentEducationProfile>(
+        true => {
+            let sql = format!(
                 r#"
                 SELECT 
                     s.id, s.full_name, s.student_code, 
@@ -56,11 +74,18 @@ pub async fn get_students(
                 FROM students s
                 JOIN classes c ON s.class_id = c.id
                 WHERE s.status = 'ACTIVE'
-                "#
-            )
-            .fetch_all(&*pool)
-            .await
-            .map_err(|e| e.to_string())?;
+                {}"#,
+                scope_filter
+            );
+
+            let mut query = sqlx::query_as::<_, StudentEducationProfile>(&sql);
+            if let Some(staff_id) = &scope {
+                query = query.bind(staff_id).bind(staff_id);
+            }
+
+            let students = query
+                .fetch_all(&*pool)
+                .await?;
             
             Ok(StudentListResult::Education(students))
         }
@@ -70,14 +95,17 @@ pub async fn get_students(
 #[tauri::command]
 pub async fn get_student_details(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     student_id: String
-) -> Result<StudentProfileResult, String> {
-    let user = check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher, Role::ManagementFinance]).await?;
-    let user_role = Role::from(user.role);
+) -> AppResult<StudentProfileResult> {
+    let user = check_permission(&pool, &token, STUDENTS_READ).await?;
+
+    // Class scope applies to every profile level
+    ensure_student_access(&pool, &user, &student_id).await?;
 
-    match user_role {
-        Role::ManagementFinance => {
+    // Without the sensitive-read permission (e.g. Finance) only minimal columns are returned
+    match has_permission(&pool, &user, STUDENTS_READ_SENSITIVE).await? {
+        false => {
             let student = sqlx::query_as::<_, StudentFinanceProfile>(
                 r#"
                 SELECT 
@@ -88,15 +116,14 @@ pub async fn get_student_details(
                 WHERE s.id = ?
                 "#
             )
-            .bind(student_id)
+            .bind(&student_id)
             .fetch_optional(&*pool)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or("Student not found".to_string())?;
+            .await?
+            .ok_or_else(|| AppError::NotFound("Student not found".to_string()))?;
             
             Ok(StudentProfileResult::Finance(student))
         }
-        _ => {
+        true => {
             let student = sqlx::query_as::<_, StudentEducationProfile>(
                 r#"
                 SELECT 
@@ -110,13 +137,69 @@ pub async fn get_student_details(
                 WHERE s.id = ?
                 "#
             )
-            .bind(student_id)
+            .bind(&student_id)
             .fetch_optional(&*pool)
-            .await
-            .map_err(|e| e.to_string())?
-            .ok_or("Student not found".to_string())?;
+            .await?
+            .ok_or_else(|| AppError::NotFound("Student not found".to_string()))?;
+
+            record_read(&pool, &user.id, VIEW_STUDENT_PROFILE, "STUDENT", &student_id).await?;
             
             Ok(StudentProfileResult::Education(student))
         }
     }
 }
+
+#[tauri::command]
+pub async fn update_student_contact(
+    pool: State<'_, DbPool>,
+    token: String,
+    student_id: String,
+    contact: StudentContact
+) -> AppResult<StudentContact> {
+    let user = check_permission(&pool, &token, STUDENTS_WRITE_SENSITIVE).await?;
+    ensure_student_access(&pool, &user, &student_id).await?;
+
+    let before = sqlx::query_as::<_, StudentContact>(
+        "SELECT address, guardian_name, guardian_contact, emergency_contact FROM students WHERE id = ?"
+    )
+    .bind(&student_id)
+    .fetch_optional(&*pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Student not found".to_string()))?;
+
+    // Blank inputs clear the field
+    let clean = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let after = StudentContact {
+        address: clean(contact.address),
+        guardian_name: clean(contact.guardian_name),
+        guardian_contact: clean(contact.guardian_contact),
+        emergency_contact: clean(contact.emergency_contact),
+    };
+
+    let event = AuditEvent::new("UPDATE_STUDENT_CONTACT", "STUDENT", &student_id).diff(&before, &after);
+    if !event.has_changes() {
+        return Ok(after);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE students
+        SET address = ?, guardian_name = ?, guardian_contact = ?, emergency_contact = ?, updated_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        "#
+    )
+    .bind(&after.address)
+    .bind(&after.guardian_name)
+    .bind(&after.guardian_contact)
+    .bind(&after.emergency_contact)
+    .bind(&student_id)
+    .execute(&mut *tx)
+    .await?;
+
+    event.log_tx(&mut tx, &user.id).await?;
+    tx.commit().await?;
+
+    Ok(after)
+}
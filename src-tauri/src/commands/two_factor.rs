@@ -0,0 +1,242 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{authenticate, check_permission, require_recent_auth, resolve_session, revoke_user_sessions};
+use crate::commands::users::fetch_account;
+use crate::permissions::{validate_role, SYSTEM_USERS};
+use crate::audit_event::AuditEvent;
+use crate::lockout::{check_login_allowed, record_failure};
+use crate::totp::{
+    current_step, generate_recovery_codes, generate_secret, is_required_for_role, otpauth_uri,
+    store_recovery_codes, verify_code, verify_second_factor,
+};
+use tauri::State;
+use serde::Serialize;
+use sqlx::{FromRow, SqliteConnection};
+use bcrypt::verify;
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct TwoFactorRequirement {
+    pub role: String,
+    pub required: bool,
+}
+
+#[tauri::command]
+pub async fn begin_totp_enrollment(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<TotpEnrollment> {
+    // Not check_permission: users forced to enroll must be able to reach this
+    let user = resolve_session(&pool, &token).await?;
+    if user.totp_enabled {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = generate_secret();
+
+    sqlx::query("UPDATE users SET totp_secret = ?, totp_last_step = NULL WHERE id = ?")
+        .bind(&secret)
+        .bind(&user.id)
+        .execute(&*pool)
+        .await?;
+
+    Ok(TotpEnrollment {
+        otpauth_uri: otpauth_uri(&secret, &user.email),
+        secret,
+    })
+}
+
+// Activates the pending secret and returns one-time recovery codes (shown once)
+#[tauri::command]
+pub async fn confirm_totp_enrollment(
+    pool: State<'_, DbPool>,
+    token: String,
+    code: String
+) -> AppResult<Vec<String>> {
+    let user = resolve_session(&pool, &token).await?;
+    if user.totp_enabled {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret: Option<String> = sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = ?")
+        .bind(&user.id)
+        .fetch_one(&*pool)
+        .await?;
+    let secret = secret.ok_or_else(|| AppError::Conflict("No enrollment in progress".to_string()))?;
+
+    let step = verify_code(&secret, &code, current_step(), None)
+        .ok_or_else(|| AppError::invalid_field("code", "Invalid verification code"))?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE users SET totp_enabled = 1, totp_last_step = ? WHERE id = ?")
+        .bind(step)
+        .bind(&user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let codes = generate_recovery_codes();
+    store_recovery_codes(&mut tx, &user.id, &codes).await?;
+
+    AuditEvent::new("ENABLE_2FA", "USER", &user.id)
+        .change("totp_enabled", &false, &true)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+#[tauri::command]
+pub async fn disable_totp(
+    pool: State<'_, DbPool>,
+    token: String,
+    password_plain: String,
+    code: String
+) -> AppResult<()> {
+    let user = authenticate(&pool, &token).await?;
+
+    if is_required_for_role(&pool, &user.role).await? {
+        return Err(AppError::Forbidden("Two-factor authentication is required for your role".to_string()));
+    }
+
+    // Shares the login throttle so a stolen session cannot be used to guess the password
+    check_login_allowed(&pool, &user.email, Some(&user.id)).await?;
+
+    let valid = verify(&password_plain, &user.password_hash)?;
+    if !valid || !verify_second_factor(&pool, &user.id, &code).await? {
+        record_failure(&pool, &user.email, Some(&user.id)).await?;
+        return Err(AppError::InvalidCredentials("Invalid password or verification code".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    clear_two_factor(&mut tx, &user.id).await?;
+
+    AuditEvent::new("DISABLE_2FA", "USER", &user.id)
+        .change("totp_enabled", &true, &false)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn regenerate_recovery_codes(
+    pool: State<'_, DbPool>,
+    token: String,
+    code: String
+) -> AppResult<Vec<String>> {
+    let user = authenticate(&pool, &token).await?;
+
+    if !verify_second_factor(&pool, &user.id, &code).await? {
+        return Err(AppError::invalid_field("code", "Invalid verification code"));
+    }
+
+    let codes = generate_recovery_codes();
+
+    let mut tx = pool.begin().await?;
+    store_recovery_codes(&mut tx, &user.id, &codes).await?;
+    AuditEvent::new("REGENERATE_RECOVERY_CODES", "USER", &user.id).log_tx(&mut tx, &user.id).await?;
+    tx.commit().await?;
+
+    Ok(codes)
+}
+
+// For users who lost both their authenticator and recovery codes
+#[tauri::command]
+pub async fn reset_user_two_factor(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    require_recent_auth(&pool, &token).await?;
+    let account = fetch_account(&pool, &target_user_id).await?;
+
+    let mut tx = pool.begin().await?;
+    clear_two_factor(&mut tx, &account.id).await?;
+    revoke_user_sessions(&mut tx, &account.id).await?;
+    AuditEvent::new("RESET_2FA", "USER", &account.id)
+        .context("email", &account.email)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_two_factor_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<TwoFactorRequirement>> {
+    check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    // Roles without a policy row do not require 2FA
+    let policy = sqlx::query_as::<_, TwoFactorRequirement>(
+        r#"
+        SELECT r.name AS role, COALESCE(p.required, 0) AS required
+        FROM roles r
+        LEFT JOIN two_factor_policy p ON p.role = r.name
+        ORDER BY r.name
+        "#
+    )
+    .fetch_all(&*pool)
+    .await?;
+
+    Ok(policy)
+}
+
+#[tauri::command]
+pub async fn set_two_factor_requirement(
+    pool: State<'_, DbPool>,
+    token: String,
+    role: String,
+    required: bool
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    validate_role(&pool, &role).await?;
+    let previous = is_required_for_role(&pool, &role).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO two_factor_policy (role, required) VALUES (?, ?) ON CONFLICT(role) DO UPDATE SET required = excluded.required"
+    )
+    .bind(&role)
+    .bind(required)
+    .execute(&mut *tx)
+    .await?;
+
+    AuditEvent::new("UPDATE_2FA_POLICY", "SYSTEM", "TWO_FACTOR_POLICY")
+        .change("required", &previous, &required)
+        .context("role", &role)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn clear_two_factor(conn: &mut SqliteConnection, user_id: &str) -> AppResult<()> {
+    sqlx::query("UPDATE users SET totp_secret = NULL, totp_enabled = 0, totp_last_step = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,347 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, revoke_user_sessions};
+use crate::permissions::{validate_role, SYSTEM_USERS};
+use crate::audit::SYSTEM_USER_ID;
+use crate::audit_event::AuditEvent;
+use crate::lockout::unlock_account;
+use crate::password::{enforce_policy, load_policy, save_policy, set_password, PasswordPolicy};
+use tauri::State;
+use serde::Serialize;
+use sqlx::FromRow;
+use bcrypt::{hash, DEFAULT_COST};
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct UserAccount {
+    pub id: String,
+    pub email: String,
+    pub role: String,
+    pub active: bool,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_login_at: Option<chrono::NaiveDateTime>,
+    pub must_change_password: bool,
+    pub failed_login_count: i64,
+    pub locked_until: Option<chrono::NaiveDateTime>,
+    pub staff_id: Option<String>,
+}
+
+pub fn validate_email(email: &str) -> AppResult<()> {
+    let email = email.trim();
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.is_empty()
+                && !domain.contains('@')
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_field("email", format!("Invalid email address '{}'", email)))
+    }
+}
+
+pub async fn fetch_account(pool: &DbPool, user_id: &str) -> AppResult<UserAccount> {
+    // The system pseudo-user is not a manageable account
+    if user_id == SYSTEM_USER_ID {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    sqlx::query_as::<_, UserAccount>(
+        "SELECT id, email, role, active, created_at, last_login_at, must_change_password, failed_login_count, locked_until, staff_id FROM users WHERE id = ?"
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))
+}
+
+// Appended to an UPDATE of a single user so that it only applies while another
+// active admin remains; the check and the change are one statement, so two
+// concurrent demotions cannot both pass
+const KEEPS_AN_ADMIN: &str = "AND (role != 'ADMIN' OR active = 0 OR EXISTS (SELECT 1 FROM users other WHERE other.role = 'ADMIN' AND other.active = 1 AND other.id != users.id))";
+
+fn last_admin() -> AppError {
+    AppError::Conflict("Cannot remove the last active admin".to_string())
+}
+
+#[tauri::command]
+pub async fn list_users(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<UserAccount>> {
+    check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let users = sqlx::query_as::<_, UserAccount>(
+        "SELECT id, email, role, active, created_at, last_login_at, must_change_password, failed_login_count, locked_until, staff_id FROM users WHERE id != ? ORDER BY email"
+    )
+    .bind(SYSTEM_USER_ID)
+    .fetch_all(&*pool)
+    .await?;
+
+    Ok(users)
+}
+
+#[tauri::command]
+pub async fn create_user(
+    pool: State<'_, DbPool>,
+    token: String,
+    email: String,
+    role: String,
+    password_plain: String
+) -> AppResult<UserAccount> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let email = email.trim().to_lowercase();
+    validate_email(&email)?;
+    validate_role(&pool, &role).await?;
+    enforce_policy(&pool, None, &password_plain).await?;
+
+    let exists = sqlx::query_scalar::<_, i32>("SELECT 1 FROM users WHERE email = ?")
+        .bind(&email)
+        .fetch_optional(&*pool)
+        .await?;
+
+    if exists.is_some() {
+        return Err(AppError::Conflict(format!("A user with email '{}' already exists", email)));
+    }
+
+    let hashed = hash(&password_plain, DEFAULT_COST)?;
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut tx = pool.begin().await?;
+
+    // Admin-chosen passwords must be rotated on first login
+    sqlx::query("INSERT INTO users (id, email, password_hash, role, active, must_change_password) VALUES (?, ?, ?, ?, 1, 1)")
+        .bind(&id)
+        .bind(&email)
+        .bind(&hashed)
+        .bind(&role)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("CREATE_USER", "USER", &id)
+        .context("email", &email)
+        .context("role", &role)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_account(&pool, &id).await
+}
+
+#[tauri::command]
+pub async fn set_user_role(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String,
+    role: String
+) -> AppResult<UserAccount> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+    validate_role(&pool, &role).await?;
+
+    let account = fetch_account(&pool, &target_user_id).await?;
+    if account.role == role {
+        return Ok(account);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query(&format!("UPDATE users SET role = ? WHERE id = ? {}", KEEPS_AN_ADMIN))
+        .bind(&role)
+        .bind(&target_user_id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(last_admin());
+    }
+
+    // Existing sessions were issued under the old role
+    revoke_user_sessions(&mut tx, &target_user_id).await?;
+
+    AuditEvent::new("CHANGE_USER_ROLE", "USER", &target_user_id)
+        .change("role", &account.role, &role)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_account(&pool, &target_user_id).await
+}
+
+#[tauri::command]
+pub async fn set_user_active(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String,
+    active: bool
+) -> AppResult<UserAccount> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let account = fetch_account(&pool, &target_user_id).await?;
+    if account.active == active {
+        return Ok(account);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Reactivating can never remove an admin
+    let guard = if active { "" } else { KEEPS_AN_ADMIN };
+    let updated = sqlx::query(&format!("UPDATE users SET active = ? WHERE id = ? {}", guard))
+        .bind(active)
+        .bind(&target_user_id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(last_admin());
+    }
+
+    if !active {
+        revoke_user_sessions(&mut tx, &target_user_id).await?;
+    }
+
+    AuditEvent::new(if active { "ACTIVATE_USER" } else { "DEACTIVATE_USER" }, "USER", &target_user_id)
+        .change("active", &!active, &active)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_account(&pool, &target_user_id).await
+}
+
+#[tauri::command]
+pub async fn reset_user_password(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String,
+    new_password_plain: String
+) -> AppResult<()> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let account = fetch_account(&pool, &target_user_id).await?;
+    enforce_policy(&pool, Some((&account.id, &account.email)), &new_password_plain).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Temporary password; the user must pick their own at next login
+    set_password(&mut tx, &target_user_id, &new_password_plain, true).await?;
+
+    revoke_user_sessions(&mut tx, &target_user_id).await?;
+
+    AuditEvent::new("RESET_USER_PASSWORD", "USER", &target_user_id)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock_user(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String
+) -> AppResult<UserAccount> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let account = fetch_account(&pool, &target_user_id).await?;
+
+    let mut tx = pool.begin().await?;
+    unlock_account(&mut tx, &target_user_id).await?;
+
+    AuditEvent::new("UNLOCK_USER", "USER", &target_user_id)
+        .change("failed_login_count", &account.failed_login_count, &0)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_account(&pool, &target_user_id).await
+}
+
+// Links a login to a staff record, which determines a teacher's class scope
+#[tauri::command]
+pub async fn set_user_staff_link(
+    pool: State<'_, DbPool>,
+    token: String,
+    target_user_id: String,
+    staff_id: Option<String>
+) -> AppResult<UserAccount> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let account = fetch_account(&pool, &target_user_id).await?;
+
+    if let Some(staff_id) = &staff_id {
+        let exists = sqlx::query_scalar::<_, i32>("SELECT 1 FROM staff WHERE id = ?")
+            .bind(staff_id)
+            .fetch_optional(&*pool)
+            .await?;
+        if exists.is_none() {
+            return Err(AppError::invalid_field("staff_id", "Staff record not found"));
+        }
+
+        let linked_to: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE staff_id = ? AND id != ?")
+            .bind(staff_id)
+            .bind(&target_user_id)
+            .fetch_optional(&*pool)
+            .await?;
+        if linked_to.is_some() {
+            return Err(AppError::Conflict("Staff record is already linked to another user".to_string()));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE users SET staff_id = ? WHERE id = ?")
+        .bind(&staff_id)
+        .bind(&target_user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("LINK_USER_STAFF", "USER", &target_user_id)
+        .change("staff_id", &account.staff_id, &staff_id)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    fetch_account(&pool, &target_user_id).await
+}
+
+#[tauri::command]
+pub async fn get_password_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<PasswordPolicy> {
+    check_permission(&pool, &token, SYSTEM_USERS).await?;
+    load_policy(&pool).await
+}
+
+#[tauri::command]
+pub async fn update_password_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+    policy: PasswordPolicy
+) -> AppResult<PasswordPolicy> {
+    let admin = check_permission(&pool, &token, SYSTEM_USERS).await?;
+
+    let previous = load_policy(&pool).await?;
+
+    let mut tx = pool.begin().await?;
+    save_policy(&mut tx, &policy).await?;
+
+    AuditEvent::new("UPDATE_PASSWORD_POLICY", "SYSTEM", "PASSWORD_POLICY")
+        .diff(&previous, &policy)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(policy)
+}
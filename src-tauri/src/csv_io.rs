@@ -1,9 +1,12 @@
 use crate::db::DbPool;
-use crate::models::{Role};
-use crate::auth::check_auth;
-use crate::audit::log_audit;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, class_scope, require_recent_auth, STAFF_CLASS_IDS};
+use crate::permissions::{STUDENTS_EXPORT, STUDENTS_IMPORT};
+use crate::audit::{partial_export_path, publish_export};
+use crate::audit_event::AuditEvent;
 use tauri::State;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::Path;
 
 #[derive(Debug, Deserialize)]
@@ -25,48 +28,80 @@ pub struct ImportResult {
     errors: Vec<String>,
 }
 
+// Row numbers whose class is not one the importing user is scoped to
+fn rows_outside_scope(rows: &[(usize, StudentCsvRow)], allowed: &HashSet<String>) -> Vec<usize> {
+    rows.iter()
+        .filter(|(_, row)| !allowed.contains(&row.class_name))
+        .map(|(number, _)| *number)
+        .collect()
+}
+
 #[tauri::command]
 pub async fn import_students_csv(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     file_path: String,
-) -> Result<ImportResult, String> {
-    // Only Admin can import for now
-    check_auth(&pool, &user_id, &[Role::Admin]).await?;
+) -> AppResult<ImportResult> {
+    let user = check_permission(&pool, &token, STUDENTS_IMPORT).await?;
+    require_recent_auth(&pool, &token).await?;
 
     let path = Path::new(&file_path);
     if !path.exists() {
-        return Err("File not found".to_string());
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    let mut rdr = csv::Reader::from_path(path)?;
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, result) in rdr.deserialize::<StudentCsvRow>().enumerate() {
+        match result {
+            Ok(row) => rows.push((index + 1, row)),
+            Err(e) => errors.push(format!("Row {}: Parse error: {}", index + 1, e)),
+        }
+    }
+
+    // Class-scoped roles may only import into their own classes; checked before
+    // anything is written
+    if let Some(staff_id) = class_scope(&pool, &user).await? {
+        let allowed: HashSet<String> = sqlx::query_scalar(&format!(
+            "SELECT name FROM classes WHERE id IN ({})",
+            STAFF_CLASS_IDS
+        ))
+        .bind(&staff_id)
+        .bind(&staff_id)
+        .fetch_all(&*pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let outside = rows_outside_scope(&rows, &allowed);
+        if !outside.is_empty() {
+            let numbers: Vec<String> = outside.iter().map(usize::to_string).collect();
+            return Err(AppError::Forbidden(format!(
+                "Access denied. Rows {} are for classes outside your assigned classes.",
+                numbers.join(", ")
+            )));
+        }
     }
 
-    let mut rdr = csv::Reader::from_path(path).map_err(|e| e.to_string())?;
-    
     // Start transaction
-    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut tx = pool.begin().await?;
     
     let mut rows_processed = 0;
-    let mut errors = Vec::new();
-
-    for result in rdr.deserialize() {
-        let row: StudentCsvRow = match result {
-            Ok(row) => row,
-            Err(e) => {
-                errors.push(format!("Row {}: Parse error: {}", rows_processed + 1, e));
-                continue; // or break to fail hard
-            }
-        };
 
+    for (number, row) in rows {
         // Lookup class_id from class_name
         let class_id_opt = sqlx::query_scalar::<_, String>("SELECT id FROM classes WHERE name = ?")
             .bind(&row.class_name)
             .fetch_optional(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
 
         let class_id = match class_id_opt {
             Some(id) => id,
             None => {
-                errors.push(format!("Row {}: Class '{}' not found", rows_processed + 1, row.class_name));
+                errors.push(format!("Row {}: Class '{}' not found", number, row.class_name));
                 continue; 
             }
         };
@@ -75,11 +110,10 @@ pub async fn import_students_csv(
         let exists = sqlx::query_scalar::<_, i32>("SELECT 1 FROM students WHERE student_code = ?")
             .bind(&row.student_code)
             .fetch_optional(&mut *tx)
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
 
         if exists.is_some() {
-             errors.push(format!("Row {}: Duplicate student code '{}'", rows_processed + 1, row.student_code));
+             errors.push(format!("Row {}: Duplicate student code '{}'", number, row.student_code));
              continue;
         }
 
@@ -110,7 +144,7 @@ pub async fn import_students_csv(
         .await;
 
         if let Err(e) = res {
-            errors.push(format!("Row {}: DB Error: {}", rows_processed + 1, e));
+            errors.push(format!("Row {}: {}", number, AppError::from(e).user_message()));
         }
 
         rows_processed += 1;
@@ -118,7 +152,7 @@ pub async fn import_students_csv(
 
     if !errors.is_empty() {
         // Rollback if any critical errors
-        tx.rollback().await.map_err(|e| e.to_string())?;
+        tx.rollback().await?;
         return Ok(ImportResult {
             success: false,
             rows_processed: 0,
@@ -126,17 +160,14 @@ pub async fn import_students_csv(
         });
     }
 
-    tx.commit().await.map_err(|e| e.to_string())?;
+    // Audit Log, committed with the imported rows
+    AuditEvent::new("IMPORT_STUDENTS", "BATCH", "CSV")
+        .context("rows_processed", &rows_processed)
+        .context("file_path", &file_path)
+        .log_tx(&mut tx, &user.id)
+        .await?;
 
-    // Audit Log
-    let _ = log_audit(
-        &pool, 
-        &user_id, 
-        "IMPORT_STUDENTS", 
-        "BATCH", 
-        "CSV", 
-        Some(&format!("Processed {} rows from {}", rows_processed, file_path))
-    ).await;
+    tx.commit().await?;
 
     Ok(ImportResult {
         success: true,
@@ -148,43 +179,85 @@ pub async fn import_students_csv(
 #[tauri::command]
 pub async fn export_students_csv(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     export_path: String,
-) -> Result<String, String> {
-    let _user = check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher]).await?; 
+) -> AppResult<String> {
+    let user = check_permission(&pool, &token, STUDENTS_EXPORT).await?;
     
-    let rows = sqlx::query_as::<_, (String, String, String, String, String)>(
+    let mut sql = String::from(
         r#"
         SELECT s.student_code, s.full_name, c.name as class_name, s.enrollment_date, s.status
         FROM students s
         JOIN classes c ON s.class_id = c.id
         WHERE s.status = 'ACTIVE'
         "#
-    )
-    .fetch_all(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    );
+
+    // Class-scoped roles can only export their own classes
+    let scope = class_scope(&pool, &user).await?;
+    if scope.is_some() {
+        sql.push_str(&format!(" AND s.class_id IN ({})", STAFF_CLASS_IDS));
+    }
+
+    let mut query = sqlx::query_as::<_, (String, String, String, String, String)>(&sql);
+    if let Some(staff_id) = &scope {
+        query = query.bind(staff_id).bind(staff_id);
+    }
 
-    let path = Path::new(&export_path);
-    let mut wtr = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    let rows = query
+        .fetch_all(&*pool)
+        .await?;
 
-    wtr.write_record(&["Student Code", "Full Name", "Class", "Enrollment Date", "Status"]).map_err(|e| e.to_string())?;
+    let partial = partial_export_path(&export_path);
+    let mut wtr = csv::Writer::from_path(&partial)?;
+
+    wtr.write_record(&["Student Code", "Full Name", "Class", "Enrollment Date", "Status"])?;
 
     for row in &rows {
-        wtr.write_record(&[&row.0, &row.1, &row.2, &row.3, &row.4]).map_err(|e| e.to_string())?;
+        wtr.write_record(&[&row.0, &row.1, &row.2, &row.3, &row.4])?;
     }
 
-    wtr.flush().map_err(|e| e.to_string())?;
+    wtr.flush()?;
+    drop(wtr);
 
     // Audit Log
-    let _ = log_audit(
-        &pool, 
-        &user_id, 
-        "EXPORT_STUDENTS", 
-        "BATCH", 
-        "CSV", 
-        Some(&format!("Exported {} rows to {}", rows.len(), export_path))
-    ).await;
+    let event = AuditEvent::new("EXPORT_STUDENTS", "BATCH", "CSV")
+        .context("rows_exported", &rows.len())
+        .context("file_path", &export_path);
+    publish_export(&pool, &user.id, event, &partial, &export_path).await?;
 
     Ok(format!("Exported {} rows to {}", rows.len(), export_path))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(class_name: &str) -> StudentCsvRow {
+        StudentCsvRow {
+            full_name: "Test Student".to_string(),
+            student_code: "S001".to_string(),
+            class_name: class_name.to_string(),
+            enrollment_date: "2024-09-01".to_string(),
+            gender: None,
+            address: None,
+            guardian_name: None,
+            guardian_contact: None,
+        }
+    }
+
+    #[test]
+    fn rows_outside_scope_are_reported_by_number() {
+        let allowed: HashSet<String> = ["Grade 1A".to_string()].into_iter().collect();
+        let rows = vec![(1, row("Grade 1A")), (2, row("Grade 2B")), (4, row("grade 1a"))];
+
+        assert_eq!(rows_outside_scope(&rows, &allowed), vec![2, 4]);
+    }
+
+    #[test]
+    fn scoped_user_without_classes_cannot_import_any_row() {
+        let rows = vec![(1, row("Grade 1A")), (2, row("Grade 1A"))];
+
+        assert_eq!(rows_outside_scope(&rows, &HashSet::new()), vec![1, 2]);
+    }
+}
@@ -1,116 +1,221 @@
-use sqlx::{sqlite::{SqliteConnectOptions, SqlitePoolOptions}, Pool, Sqlite, Row};
-use std::fs;
-use std::path::PathBuf;
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    ConnectOptions, Connection, Pool, Sqlite,
+};
+use std::path::Path;
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 use std::str::FromStr;
+use crate::error::AppResult;
 
 pub type DbPool = Pool<Sqlite>;
 
-pub async fn init_db(app: &AppHandle) -> Result<DbPool, Box<dyn std::error::Error>> {
-    let app_dir = app.path().app_data_dir()?;
-    if !app_dir.exists() {
-        fs::create_dir_all(&app_dir)?;
+pub const DB_FILE_NAME: &str = "resonance.db";
+
+// Every migration this build knows about; restores are checked against it too
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// How connections to the live database are set up
+#[derive(Debug, Clone)]
+pub struct DbSettings {
+    pub max_connections: u32,
+    // WAL lets readers carry on while a write is in progress
+    pub journal_mode: SqliteJournalMode,
+    // NORMAL is safe with WAL; a power cut can only lose the last commits, not corrupt the file
+    pub synchronous: SqliteSynchronous,
+    // How long a connection waits for another's write lock before failing as busy
+    pub busy_timeout: Duration,
+    pub foreign_keys: bool,
+}
+
+impl Default for DbSettings {
+    fn default() -> Self {
+        DbSettings {
+            max_connections: 5,
+            journal_mode: SqliteJournalMode::Wal,
+            synchronous: SqliteSynchronous::Normal,
+            busy_timeout: Duration::from_secs(10),
+            foreign_keys: true,
+        }
     }
+}
 
-    let db_path = app_dir.join("resonance.db");
-    let db_str = db_path.to_str().unwrap();
+impl DbSettings {
+    pub fn apply(&self, options: SqliteConnectOptions) -> SqliteConnectOptions {
+        options
+            .journal_mode(self.journal_mode)
+            .synchronous(self.synchronous)
+            .busy_timeout(self.busy_timeout)
+            .foreign_keys(self.foreign_keys)
+    }
+
+    pub fn pool_options(&self) -> SqlitePoolOptions {
+        SqlitePoolOptions::new().max_connections(self.max_connections)
+    }
+}
 
-    // Auto-backup before migration
-    if db_path.exists() {
-        let backup_dir = app_dir.join("backups");
-        if !backup_dir.exists() {
-            fs::create_dir_all(&backup_dir)?;
+pub async fn init_db(app: &AppHandle, app_dir: &Path) -> Result<DbPool, Box<dyn std::error::Error>> {
+    // A report from the previously open workspace no longer applies
+    if let Some(status) = app.try_state::<crate::recovery::RecoveryStatus>() {
+        if let Ok(mut slot) = status.0.lock() {
+            *slot = None;
         }
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let backup_file = backup_dir.join(format!("resonance_backup_{}.db", timestamp));
-        
-        if let Err(e) = fs::copy(&db_path, &backup_file) {
-            eprintln!("Failed to create backup: {}", e);
+    }
+
+    if let Err(e) = crate::backup::sweep_partial_files(app_dir) {
+        eprintln!("Failed to remove leftover backup files: {}", e);
+    }
+
+    let db_path = app_dir.join(DB_FILE_NAME);
+    let db_str = db_path.to_str().unwrap();
+    let existed = db_path.exists();
+    let settings = DbSettings::default();
+
+    // Keyed when the file is encrypted; the key was checked before start-up got here
+    let options = crate::encryption::live_options(
+        &db_path,
+        settings.apply(SqliteConnectOptions::from_str(&format!("sqlite:{}", db_str))?.create_if_missing(true))
+    );
+
+    // Nothing is backed up or migrated until the file is known to be sound
+    let mut recovery = None;
+    if existed {
+        let problems = match options.connect().await {
+            Ok(mut conn) => {
+                let problems = crate::recovery::integrity_problems(&mut conn).await;
+                let _ = conn.close().await;
+                problems
+            }
+            Err(e) => vec![e.to_string()],
+        };
+
+        if problems.is_empty() {
+            println!("Database integrity check passed.");
         } else {
-             println!("Database backup created at {:?}", backup_file);
+            eprintln!("Database integrity check failed: {}", problems.join("; "));
+            if let Some(state) = app.try_state::<crate::system_status::SystemState>() {
+                state.set(app, crate::system_status::SystemStatus::Recovering);
+            }
+            let report = crate::recovery::recover(app_dir, &db_path, problems).await?;
+            eprintln!("Database recovered ({:?}); the damaged file was kept at {}", report.source, report.corrupt_copy);
+            recovery = Some(report);
         }
     }
 
-    let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", db_str))?
-        .create_if_missing(true);
-
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect_with(options)
+    let pool = settings
+        .pool_options()
+        .connect_with(options.clone())
         .await?;
 
-    // Integrity Check
-    let row = sqlx::query("PRAGMA integrity_check")
-        .fetch_one(&pool)
-        .await?;
-    
-    let integrity_status: String = row.get(0);
-    
-    if integrity_status == "ok" {
-        println!("Database integrity check passed.");
+    // Auto-backup before migration. It is cataloged afterwards, once the catalog table exists.
+    let auto_backup = if existed {
+        match crate::backup::take_snapshot(&mut *pool.acquire().await?, app_dir, crate::backup::BackupKind::Auto).await {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                eprintln!("Failed to create backup: {}", e);
+                None
+            }
+        }
     } else {
-        eprintln!("Database integrity check failed! Attempting recovery...");
-    }
-
-    sqlx::migrate!("./migrations")
-        .run(&pool)
+        None
+    };
+
+    // Rebuilding a table (SQLite's only way to drop a constraint) requires foreign keys
+    // off, which cannot be toggled inside a migration's transaction, so migrate on a
+    // dedicated connection with enforcement disabled and check the result afterwards.
+    let migrate_pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options.clone().foreign_keys(false))
+        .await?;
+    MIGRATOR
+        .run(&migrate_pool)
         .await?;
 
-    // Seed Data
-    if let Err(e) = crate::seed::seed_database(&pool).await {
-        eprintln!("Failed to seed database: {}", e);
+    // Enforcement only covers new writes, so rows broken earlier are recorded for review
+    let violations = crate::foreign_keys::record_violations(&migrate_pool).await?;
+    if !violations.is_empty() {
+        eprintln!("Database has {} foreign key violation(s) after migration.", violations.len());
     }
+    migrate_pool.close().await;
+
+    crate::audit_chain::load_checkpoint_key(&crate::workspace::key_dir(app)?, app_dir)?;
+    finish_open(&pool).await?;
+
+    if let Some(report) = recovery {
+        let failed_tables: Vec<&str> = report.tables
+            .iter()
+            .filter(|t| t.rows_failed > 0 || t.error.is_some())
+            .map(|t| t.name.as_str())
+            .collect();
+        let logged = crate::audit_event::AuditEvent::new("DATABASE_RECOVERED", "SYSTEM", "DATABASE")
+            .context("source", &report.source)
+            .context("backup_used", &report.backup_used)
+            .context("corrupt_copy", &report.corrupt_copy)
+            .context("damaged_tables", &failed_tables)
+            .log(&pool, crate::audit::SYSTEM_USER_ID)
+            .await;
+        if let Err(e) = logged {
+            eprintln!("Failed to audit database recovery: {}", e);
+        }
 
-    Ok(pool)
-}
-
-#[tauri::command]
-pub async fn backup_db(app: AppHandle) -> Result<String, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("resonance.db");
-    
-    if !db_path.exists() {
-        return Err("Database file not found".to_string());
+        if let Some(status) = app.try_state::<crate::recovery::RecoveryStatus>() {
+            if let Ok(mut slot) = status.0.lock() {
+                *slot = Some(report);
+            }
+        }
     }
 
-    let backup_dir = app_dir.join("manual_backups");
-    if !backup_dir.exists() {
-        if let Err(e) = fs::create_dir_all(&backup_dir) {
-             return Err(e.to_string());
+    if let Some(snapshot) = auto_backup {
+        match crate::backup::record_snapshot(&pool, snapshot, crate::audit::SYSTEM_USER_ID).await {
+            Ok(record) => println!("Database backup created at {}", record.file_path),
+            Err(e) => eprintln!("Failed to record backup: {}", e),
         }
     }
 
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let backup_file_name = format!("resonance_manual_{}.db", timestamp);
-    let backup_path = backup_dir.join(&backup_file_name);
-    
-    if let Err(e) = fs::copy(&db_path, &backup_path) {
-        return Err(e.to_string());
+    // Thin out older start-up and scheduled backups
+    if let Err(e) = crate::backup_schedule::rotate_backups(&pool, crate::audit::SYSTEM_USER_ID).await {
+        eprintln!("Failed to rotate backups: {}", e);
     }
 
-    Ok(backup_path.to_string_lossy().to_string())
-}
-
-#[tauri::command]
-pub async fn restore_db(app: AppHandle, backup_path_str: String) -> Result<String, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let db_path = app_dir.join("resonance.db");
-    
-    let source = PathBuf::from(&backup_path_str);
-    if !source.exists() {
-        return Err("Backup file not found".to_string());
+    if let Err(e) = crate::audit_retention::apply_retention(&pool, app_dir, crate::audit::SYSTEM_USER_ID).await {
+        eprintln!("Failed to apply audit retention: {}", e);
     }
 
-    // Backup current before restoring
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let safety_backup = app_dir.join(format!("safety_backup_before_restore_{}.db", timestamp));
-    if let Err(e) = fs::copy(&db_path, &safety_backup) {
-         return Err(format!("Failed to create safety backup: {}", e));
+    // Seed Data. A workspace created at runtime gets the admin chosen for it; only
+    // the original workspace gets the demo accounts.
+    let seeded = match crate::workspace::initial_admin(app_dir)? {
+        Some(admin) => crate::seed::seed_initial_admin(&pool, &admin).await,
+        None if crate::workspace::is_default(app)? => crate::seed::seed_database(&pool).await,
+        None => Ok(()),
+    };
+    match seeded {
+        Ok(()) => crate::workspace::clear_initial_admin(app_dir)?,
+        Err(e) => eprintln!("Failed to seed database: {}", e),
     }
 
-    if let Err(e) = fs::copy(source, &db_path) {
-         return Err(format!("Failed to restore database: {}", e));
+    Ok(pool)
+}
+
+// Repoints the managed pool at a freshly opened database. Commands still using the
+// previous one finish first; its connections are then closed.
+pub async fn adopt(live: &DbPool, fresh: DbPool) -> AppResult<()> {
+    let held = crate::restore::hold_all(live).await?;
+    live.set_connect_options((*fresh.connect_options()).clone());
+    fresh.close().await;
+    for conn in held {
+        let _ = conn.detach().close().await;
     }
+    Ok(())
+}
+
+// Steps that follow migrations whenever a database is opened or restored
+pub async fn finish_open(pool: &DbPool) -> AppResult<()> {
+    // Register any permissions added since this database was last opened
+    crate::permissions::sync_registry(pool).await?;
+
+    // Chain any entries written before hashing existed before anything new is appended
+    crate::audit_chain::seal_pending_entries(pool).await?;
 
-    Ok("Database restored successfully. Please restart the application.".to_string())
+    Ok(())
 }
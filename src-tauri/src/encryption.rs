@@ -0,0 +1,509 @@
+use crate::db::{DbPool, DB_FILE_NAME};
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit_event::AuditEvent;
+use crate::backup_crypto::validate_passphrase;
+use crate::permissions::SYSTEM_BACKUP;
+use crate::recovery::integrity_problems;
+use crate::restore::{hold_all, release, SQLITE_HEADER};
+use crate::system_status::{SystemState, SystemStatus};
+use tauri::{AppHandle, State};
+use serde::{Serialize, Deserialize};
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection, SqliteConnection};
+use rand::{rngs::OsRng, RngCore};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+// Says how the key is obtained, never the key itself. Read before the database is opened.
+const CONFIG_FILE: &str = "encryption.json";
+// The re-encrypted copy is written here and renamed over the live file once verified
+const STAGING_DB_NAME: &str = "reencrypt_staging.db";
+const KEY_FILE_BYTES: usize = 32;
+// Slows down guessing at the unlock screen; SQLCipher's key derivation adds to it
+const FAILED_UNLOCK_DELAY: Duration = Duration::from_secs(1);
+
+// The key of the open database, held only in memory
+static ACTIVE_KEY: RwLock<Option<DbKey>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EncryptionMode {
+    None,
+    // Entered by an administrator at every start-up
+    Passphrase,
+    // Read from a file, e.g. on a USB stick, so the app starts unattended
+    KeyFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    pub mode: EncryptionMode,
+    pub key_file: Option<String>,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        EncryptionConfig { mode: EncryptionMode::None, key_file: None }
+    }
+}
+
+// A SQLCipher key: a passphrase, or x'<hex>' for a raw key, which skips key derivation
+#[derive(Clone, PartialEq)]
+pub struct DbKey(String);
+
+impl DbKey {
+    fn passphrase(passphrase: &str) -> Self {
+        DbKey(passphrase.to_string())
+    }
+
+    fn raw(bytes: &[u8]) -> Self {
+        DbKey(format!("x'{}'", hex::encode_upper(bytes)))
+    }
+
+    // Pragma values are pasted into the statement, so this one is quoted here
+    fn pragma_value(&self) -> String {
+        format!("'{}'", self.0.replace('\'', "''"))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum KeyChange {
+    Encrypt,
+    Rekey,
+}
+
+impl KeyChange {
+    fn action(self) -> &'static str {
+        match self {
+            KeyChange::Encrypt => "ENCRYPT_DATABASE",
+            KeyChange::Rekey => "REKEY_DATABASE",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    // False when this build was made without SQLCipher
+    pub available: bool,
+    pub cipher_version: Option<String>,
+    pub encrypted: bool,
+    pub mode: EncryptionMode,
+    pub key_file: Option<String>,
+}
+
+fn active_key() -> Option<DbKey> {
+    ACTIVE_KEY.read().map(|k| k.clone()).unwrap_or_else(|e| e.into_inner().clone())
+}
+
+// Whether the live database was opened with a key
+pub fn is_encrypted() -> bool {
+    active_key().is_some()
+}
+
+fn set_active_key(key: Option<DbKey>) {
+    match ACTIVE_KEY.write() {
+        Ok(mut current) => *current = key,
+        Err(e) => *e.into_inner() = key,
+    }
+}
+
+pub fn load_config(app_dir: &Path) -> AppResult<EncryptionConfig> {
+    let path = app_dir.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(EncryptionConfig::default());
+    }
+    serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|e| AppError::Internal(format!("invalid encryption settings: {}", e)))
+}
+
+fn save_config(app_dir: &Path, config: &EncryptionConfig) -> AppResult<()> {
+    let path = app_dir.join(CONFIG_FILE);
+    let partial = app_dir.join(format!("{}.partial", CONFIG_FILE));
+    fs::write(&partial, serde_json::to_vec_pretty(config)?)?;
+    fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+// An encrypted file starts with its salt instead of the SQLite header. An empty
+// file is a database that was never written to.
+pub fn is_plaintext(path: &Path) -> AppResult<bool> {
+    let mut header = Vec::new();
+    File::open(path)?.take(SQLITE_HEADER.len() as u64).read_to_end(&mut header)?;
+    Ok(header.is_empty() || header == SQLITE_HEADER)
+}
+
+// The key that opens a database file: none for plaintext files and files not
+// created yet, otherwise the live database's key
+fn key_for(path: &Path) -> Option<DbKey> {
+    match is_plaintext(path) {
+        Ok(true) => None,
+        Ok(false) => active_key(),
+        Err(_) => None,
+    }
+}
+
+pub fn with_key(options: SqliteConnectOptions, key: Option<&DbKey>) -> SqliteConnectOptions {
+    match key {
+        Some(key) => options.pragma("key", key.pragma_value()),
+        None => options,
+    }
+}
+
+// Options for a copy, backup or candidate file, keyed to match its header
+pub fn options_for(path: &Path) -> SqliteConnectOptions {
+    with_key(SqliteConnectOptions::new().filename(path), key_for(path).as_ref())
+}
+
+// Options for a new file that should be encrypted like the live database
+pub fn options_for_new(path: &Path) -> SqliteConnectOptions {
+    with_key(SqliteConnectOptions::new().filename(path), active_key().as_ref())
+}
+
+// For `ATTACH ... KEY ?`. Without a KEY clause SQLCipher assumes the main database's
+// key, and an empty key attaches a plaintext file.
+pub fn attach_key(path: &Path) -> String {
+    key_for(path).map(|key| key.0).unwrap_or_default()
+}
+
+// Set for the live database before the pool is created
+pub fn live_options(db_path: &Path, options: SqliteConnectOptions) -> SqliteConnectOptions {
+    with_key(options, key_for(db_path).as_ref())
+}
+
+// Plain SQLite ignores the pragma and returns nothing
+async fn cipher_version(conn: &mut SqliteConnection) -> Option<String> {
+    sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(conn)
+        .await
+        .ok()
+        .flatten()
+}
+
+// SQLCipher only reports a wrong key when the first page is read
+async fn key_opens(path: &Path, key: &DbKey) -> bool {
+    let connected = with_key(SqliteConnectOptions::new().filename(path).read_only(true), Some(key))
+        .connect()
+        .await;
+    let Ok(mut conn) = connected else {
+        return false;
+    };
+    let opened = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sqlite_master")
+        .fetch_one(&mut conn)
+        .await
+        .is_ok();
+    let _ = conn.close().await;
+    opened
+}
+
+fn read_key_file(path: &Path) -> AppResult<DbKey> {
+    let bytes = hex::decode(fs::read_to_string(path)?.trim())
+        .ok()
+        .filter(|bytes| bytes.len() == KEY_FILE_BYTES)
+        .ok_or_else(|| AppError::invalid_field("key_file", "The key file is not a database key"))?;
+    Ok(DbKey::raw(&bytes))
+}
+
+// Uses an existing key file, so several installations can share one, or creates it
+fn read_or_create_key_file(path: &Path) -> AppResult<(DbKey, bool)> {
+    if path.exists() {
+        return Ok((read_key_file(path)?, false));
+    }
+    if !path.parent().is_some_and(|dir| dir.is_dir()) {
+        return Err(AppError::invalid_field("key_file", "The key file's folder does not exist"));
+    }
+    let mut bytes = vec![0u8; KEY_FILE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    fs::write(path, hex::encode(&bytes))?;
+    Ok((DbKey::raw(&bytes), true))
+}
+
+// Decides how the live database is opened at start-up. Returns false when it is
+// encrypted and its key has to be entered first.
+pub async fn load_startup_key(app_dir: &Path) -> AppResult<bool> {
+    let db_path = app_dir.join(DB_FILE_NAME);
+    let config = load_config(app_dir)?;
+
+    if !db_path.exists() || is_plaintext(&db_path)? {
+        if config.mode != EncryptionMode::None && db_path.exists() {
+            eprintln!("Encryption is configured but the database is not encrypted; opening it as is.");
+        }
+        set_active_key(None);
+        return Ok(true);
+    }
+
+    // A damaged header also looks encrypted; start-up recovery deals with it
+    if config.mode == EncryptionMode::None {
+        set_active_key(None);
+        return Ok(true);
+    }
+
+    // Already unlocked in this session
+    if let Some(key) = active_key() {
+        if key_opens(&db_path, &key).await {
+            return Ok(true);
+        }
+        set_active_key(None);
+    }
+
+    if let (EncryptionMode::KeyFile, Some(key_file)) = (config.mode, &config.key_file) {
+        match read_key_file(Path::new(key_file)) {
+            Ok(key) if key_opens(&db_path, &key).await => {
+                set_active_key(Some(key));
+                return Ok(true);
+            }
+            Ok(_) => eprintln!("The key file {} does not open the database", key_file),
+            Err(e) => eprintln!("Could not read the key file {}: {}", key_file, e),
+        }
+    }
+
+    Ok(false)
+}
+
+// Copies the open database into a new file under `key` and checks the copy
+async fn export_copy(conn: &mut SqliteConnection, target: &Path, key: &DbKey) -> AppResult<()> {
+    if target.exists() {
+        fs::remove_file(target)?;
+    }
+
+    sqlx::query("ATTACH DATABASE ? AS reencrypted KEY ?")
+        .bind(target.to_string_lossy().as_ref())
+        .bind(&key.0)
+        .execute(&mut *conn)
+        .await?;
+    let exported = sqlx::query("SELECT sqlcipher_export('reencrypted')")
+        .execute(&mut *conn)
+        .await;
+    let _ = sqlx::query("DETACH DATABASE reencrypted").execute(&mut *conn).await;
+    exported?;
+
+    let mut copy = with_key(SqliteConnectOptions::new().filename(target).read_only(true), Some(key))
+        .connect()
+        .await?;
+    let problems = integrity_problems(&mut copy).await;
+    copy.close().await?;
+    if !problems.is_empty() {
+        return Err(AppError::Internal(format!("re-encrypted copy failed the integrity check: {}", problems.join("; "))));
+    }
+    if is_plaintext(target)? {
+        return Err(AppError::Internal("re-encrypted copy was written unencrypted".to_string()));
+    }
+    Ok(())
+}
+
+// Re-encrypts the live database under `key`. Every connection is held while the
+// copy is made and closed before the copy replaces the file; new connections are
+// opened with the new key.
+async fn switch_key(pool: &DbPool, app_dir: &Path, key: DbKey, config: &EncryptionConfig) -> AppResult<()> {
+    let db_path = app_dir.join(DB_FILE_NAME);
+    let staging = app_dir.join(STAGING_DB_NAME);
+    let previous_options = (*pool.connect_options()).clone();
+    let previous_key = active_key();
+    let previous_config = load_config(app_dir)?;
+
+    let mut held = hold_all(pool).await?;
+    if let Err(e) = export_copy(&mut held[0], &staging, &key).await {
+        release(held);
+        let _ = fs::remove_file(&staging);
+        return Err(e);
+    }
+
+    // Written first: if the swap is interrupted, start-up still finds a plaintext
+    // file by its header, or asks for whichever key opens it
+    save_config(app_dir, config)?;
+    pool.set_connect_options(with_key(previous_options.clone(), Some(&key)));
+    set_active_key(Some(key));
+
+    for conn in held {
+        let _ = conn.detach().close().await;
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", db_path.display(), suffix));
+    }
+
+    if let Err(e) = fs::rename(&staging, &db_path) {
+        pool.set_connect_options(previous_options);
+        set_active_key(previous_key);
+        let _ = save_config(app_dir, &previous_config);
+        let _ = fs::remove_file(&staging);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+// Compares resolved paths, so `..` segments, symlinks and trailing separators
+// cannot hide a location inside `dir`. A key file that does not exist yet is
+// judged by its folder.
+fn is_inside(path: &Path, dir: &Path) -> bool {
+    let folder = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let resolved = fs::canonicalize(path).or_else(|_| fs::canonicalize(folder));
+    match (resolved, fs::canonicalize(dir)) {
+        (Ok(resolved), Ok(dir)) => resolved.starts_with(dir),
+        _ => false,
+    }
+}
+
+fn new_key(app_dir: &Path, passphrase: Option<String>, key_file: Option<String>) -> AppResult<(DbKey, EncryptionConfig, Option<PathBuf>)> {
+    match (passphrase, key_file) {
+        (Some(passphrase), None) => {
+            validate_passphrase(&passphrase)?;
+            Ok((DbKey::passphrase(&passphrase), EncryptionConfig { mode: EncryptionMode::Passphrase, key_file: None }, None))
+        }
+        (None, Some(key_file)) => {
+            let path = PathBuf::from(&key_file);
+            // Next to the database it would protect nothing
+            if is_inside(&path, app_dir) {
+                return Err(AppError::invalid_field("key_file", "Keep the key file outside the application's data folder"));
+            }
+            let (key, created) = read_or_create_key_file(&path)?;
+            let config = EncryptionConfig { mode: EncryptionMode::KeyFile, key_file: Some(key_file) };
+            Ok((key, config, created.then_some(path)))
+        }
+        _ => Err(AppError::invalid("Choose either a passphrase or a key file")),
+    }
+}
+
+async fn status(pool: &DbPool, app_dir: &Path) -> AppResult<EncryptionStatus> {
+    let config = load_config(app_dir)?;
+    let cipher_version = cipher_version(&mut *pool.acquire().await?).await;
+    Ok(EncryptionStatus {
+        available: cipher_version.is_some(),
+        cipher_version,
+        encrypted: !is_plaintext(&app_dir.join(DB_FILE_NAME))?,
+        mode: config.mode,
+        key_file: config.key_file,
+    })
+}
+
+async fn change_key(
+    app: &AppHandle,
+    pool: &DbPool,
+    system: &SystemState,
+    token: &str,
+    passphrase: Option<String>,
+    key_file: Option<String>,
+    change: KeyChange
+) -> AppResult<EncryptionStatus> {
+    let admin = check_permission(pool, token, SYSTEM_BACKUP).await?;
+    require_recent_auth(pool, token).await?;
+
+    let app_dir = crate::workspace::data_dir(app)?;
+    let current = status(pool, &app_dir).await?;
+    if !current.available {
+        return Err(AppError::invalid("This build of the application cannot encrypt databases"));
+    }
+    let expect_encrypted = matches!(change, KeyChange::Rekey);
+    if current.encrypted != expect_encrypted {
+        return Err(AppError::Conflict(if current.encrypted {
+            "The database is already encrypted; change its key instead".to_string()
+        } else {
+            "The database is not encrypted yet".to_string()
+        }));
+    }
+
+    let (key, config, created_key_file) = new_key(&app_dir, passphrase, key_file)?;
+    if expect_encrypted && active_key().as_ref() == Some(&key) {
+        return Err(AppError::invalid("The new key is the same as the current one"));
+    }
+
+    // Commands wait at the gate rather than on the held connections
+    system.set(app, SystemStatus::Maintenance);
+    let switched = switch_key(pool, &app_dir, key, &config).await;
+    system.set(app, SystemStatus::Ready);
+    if let Err(e) = switched {
+        if let Some(path) = created_key_file {
+            let _ = fs::remove_file(path);
+        }
+        return Err(e);
+    }
+
+    AuditEvent::new(change.action(), "DATABASE", DB_FILE_NAME)
+        .context("mode", &config.mode)
+        .context("key_file", &config.key_file)
+        .log(pool, &admin.id)
+        .await?;
+
+    status(pool, &app_dir).await
+}
+
+#[tauri::command]
+pub async fn get_database_encryption(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<EncryptionStatus> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    status(&pool, &crate::workspace::data_dir(&app)?).await
+}
+
+// Encrypts a plaintext database in place with a passphrase or a key file. Backups
+// taken before this stay unencrypted.
+#[tauri::command]
+pub async fn encrypt_database(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    system: State<'_, SystemState>,
+    token: String,
+    passphrase: Option<String>,
+    key_file: Option<String>
+) -> AppResult<EncryptionStatus> {
+    change_key(&app, &pool, &system, &token, passphrase, key_file, KeyChange::Encrypt).await
+}
+
+// Re-encrypts the database under a new passphrase or key file. Backups taken under
+// the old key can only be restored after switching back to it.
+#[tauri::command]
+pub async fn rekey_database(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    system: State<'_, SystemState>,
+    token: String,
+    passphrase: Option<String>,
+    key_file: Option<String>
+) -> AppResult<EncryptionStatus> {
+    change_key(&app, &pool, &system, &token, passphrase, key_file, KeyChange::Rekey).await
+}
+
+// Available while the start-up gate shows the database as locked, before anyone can
+// sign in. Start-up continues in the background once the key is accepted.
+#[tauri::command]
+pub async fn unlock_database(
+    app: AppHandle,
+    system: State<'_, SystemState>,
+    passphrase: Option<String>,
+    key_file: Option<String>
+) -> AppResult<()> {
+    if !matches!(system.get(), SystemStatus::Locked { .. }) {
+        return Err(AppError::Conflict("The database is not locked".to_string()));
+    }
+
+    let app_dir = crate::workspace::data_dir(&app)?;
+    let key = match (&passphrase, &key_file) {
+        (Some(passphrase), None) => DbKey::passphrase(passphrase),
+        (None, Some(key_file)) => read_key_file(Path::new(key_file))?,
+        _ => return Err(AppError::invalid("Enter the passphrase or choose the key file")),
+    };
+
+    if !key_opens(&app_dir.join(DB_FILE_NAME), &key).await {
+        tokio::time::sleep(FAILED_UNLOCK_DELAY).await;
+        return Err(AppError::InvalidCredentials(
+            "The passphrase or key file does not open the database, or the database is damaged".to_string()
+        ));
+    }
+
+    // The key file may be on a drive that was mounted somewhere else last time
+    let mut config = load_config(&app_dir)?;
+    if key_file.is_some() && config.mode == EncryptionMode::KeyFile && config.key_file != key_file {
+        config.key_file = key_file;
+        save_config(&app_dir, &config)?;
+    }
+
+    if !system.set_if(&app, |s| matches!(s, SystemStatus::Locked { .. }), SystemStatus::Initializing) {
+        return Err(AppError::Conflict("The database is already being unlocked".to_string()));
+    }
+    set_active_key(Some(key));
+    tauri::async_runtime::spawn(crate::system_status::open_database(app.clone(), system.inner().clone()));
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// Every command error. Serialized to the UI as { code, message, fields? } where `code`
+// is stable and `message` is safe to display; internal details are only logged.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Session expired or invalid. Please log in again.")]
+    Unauthenticated,
+    #[error("Password change required before continuing.")]
+    PasswordChangeRequired,
+    #[error("Two-factor enrollment required before continuing.")]
+    TwoFactorSetupRequired,
+    // The password was correct but a second factor is still needed
+    #[error("Two-factor code required")]
+    TwoFactorRequired,
+    #[error("Confirm your password to continue.")]
+    ReauthRequired,
+    #[error("{0}")]
+    InvalidCredentials(String),
+    #[error("{0}")]
+    RateLimited(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{message}")]
+    Validation { message: String, fields: Vec<FieldError> },
+    #[error("{0}")]
+    Conflict(String),
+    #[error("The database is busy. Please try again.")]
+    DatabaseBusy,
+    // Start-up has not finished, or failed
+    #[error("{0}")]
+    DatabaseNotReady(String),
+    #[error("database error: {0}")]
+    Database(String),
+    #[error("file error: {0}")]
+    Io(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Unauthenticated => "UNAUTHENTICATED",
+            AppError::PasswordChangeRequired => "PASSWORD_CHANGE_REQUIRED",
+            AppError::TwoFactorSetupRequired => "TWO_FACTOR_SETUP_REQUIRED",
+            AppError::TwoFactorRequired => "TWO_FACTOR_REQUIRED",
+            AppError::ReauthRequired => "REAUTH_REQUIRED",
+            AppError::InvalidCredentials(_) => "INVALID_CREDENTIALS",
+            AppError::RateLimited(_) => "RATE_LIMITED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Validation { .. } => "VALIDATION",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::DatabaseBusy => "DATABASE_BUSY",
+            AppError::DatabaseNotReady(_) => "DATABASE_NOT_READY",
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Io(_) => "IO_ERROR",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    // Message shown to the user; never includes SQL, paths or library errors
+    pub fn user_message(&self) -> String {
+        match self {
+            AppError::Database(_) => "A database error occurred.".to_string(),
+            AppError::Io(_) => "A file operation failed.".to_string(),
+            AppError::Internal(_) => "An unexpected error occurred.".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    // Validation failure not tied to a single input field
+    pub fn invalid(message: impl Into<String>) -> Self {
+        AppError::Validation { message: message.into(), fields: Vec::new() }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        AppError::Validation {
+            fields: vec![FieldError { field: field.to_string(), message: message.clone() }],
+            message,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Errors are serialized once, when they reach the UI, so internal details are logged here
+        if matches!(self, AppError::Database(_) | AppError::Io(_) | AppError::Internal(_)) {
+            eprintln!("{}", self);
+        }
+
+        let fields = match self {
+            AppError::Validation { fields, .. } if !fields.is_empty() => Some(fields),
+            _ => None,
+        };
+
+        let mut state = serializer.serialize_struct("AppError", if fields.is_some() { 3 } else { 2 })?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.user_message())?;
+        if let Some(fields) = fields {
+            state.serialize_field("fields", fields)?;
+        }
+        state.end()
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => AppError::NotFound("Record not found".to_string()),
+            sqlx::Error::PoolTimedOut => AppError::DatabaseBusy,
+            sqlx::Error::Database(db) => {
+                // SQLITE_BUSY / SQLITE_LOCKED, including extended codes
+                let primary = db.code().and_then(|c| c.parse::<i32>().ok()).map(|c| c & 0xff);
+                if matches!(primary, Some(5) | Some(6)) {
+                    AppError::DatabaseBusy
+                } else if db.is_unique_violation() {
+                    AppError::Conflict("A record with the same value already exists".to_string())
+                } else if db.is_foreign_key_violation() {
+                    AppError::Conflict("The record is referenced by, or refers to, missing data".to_string())
+                } else {
+                    AppError::Database(e.to_string())
+                }
+            }
+            _ => AppError::Database(e.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(e: sqlx::migrate::MigrateError) -> Self {
+        AppError::Database(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<csv::Error> for AppError {
+    fn from(e: csv::Error) -> Self {
+        match e.kind() {
+            csv::ErrorKind::Io(_) => AppError::Io(e.to_string()),
+            _ => AppError::invalid(format!("Invalid CSV file: {}", e)),
+        }
+    }
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_code_message_and_fields() {
+        let err = AppError::invalid_field("email", "Invalid email address 'x'");
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "code": "VALIDATION",
+                "message": "Invalid email address 'x'",
+                "fields": [{ "field": "email", "message": "Invalid email address 'x'" }]
+            })
+        );
+
+        assert_eq!(
+            serde_json::to_value(AppError::ReauthRequired).unwrap(),
+            json!({ "code": "REAUTH_REQUIRED", "message": "Confirm your password to continue." })
+        );
+    }
+
+    #[test]
+    fn internal_details_are_not_serialized() {
+        let err = AppError::Database("no such column: secret_hash in SELECT ...".to_string());
+        let value = serde_json::to_value(&err).unwrap();
+        assert_eq!(value["code"], "DATABASE_ERROR");
+        assert_eq!(value["message"], "A database error occurred.");
+    }
+}
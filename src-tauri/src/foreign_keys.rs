@@ -0,0 +1,116 @@
+use crate::db::DbPool;
+use crate::error::AppResult;
+use crate::auth::check_permission;
+use crate::audit_event::AuditEvent;
+use crate::permissions::SYSTEM_BACKUP;
+use tauri::State;
+use serde::Serialize;
+use sqlx::FromRow;
+use chrono::NaiveDateTime;
+use std::collections::{BTreeSet, HashSet};
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct ForeignKeyViolation {
+    pub id: i64,
+    pub table_name: String,
+    // NULL for WITHOUT ROWID tables
+    pub row_id: Option<i64>,
+    pub parent_table: String,
+    pub constraint_index: i64,
+    pub detected_at: NaiveDateTime,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+type ViolationKey = (String, Option<i64>, String, i64);
+
+async fn unresolved(pool: &DbPool) -> AppResult<Vec<ForeignKeyViolation>> {
+    let violations = sqlx::query_as::<_, ForeignKeyViolation>(
+        r#"
+        SELECT id, table_name, row_id, parent_table, constraint_index, detected_at, resolved_at
+        FROM foreign_key_violations
+        WHERE resolved_at IS NULL
+        ORDER BY table_name, row_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(violations)
+}
+
+// Brings the recorded violations in line with the data: new ones are added and
+// ones that have been fixed are marked resolved. Returns those still open.
+pub async fn record_violations(pool: &DbPool) -> AppResult<Vec<ForeignKeyViolation>> {
+    let mut tx = pool.begin().await?;
+
+    let current: HashSet<ViolationKey> = sqlx::query_as::<_, ViolationKey>(
+        r#"SELECT "table", rowid, parent, fkid FROM pragma_foreign_key_check"#
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let recorded: Vec<(i64, String, Option<i64>, String, i64)> = sqlx::query_as(
+        "SELECT id, table_name, row_id, parent_table, constraint_index FROM foreign_key_violations WHERE resolved_at IS NULL"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut known = HashSet::new();
+    for (id, table, row_id, parent, index) in recorded {
+        let key = (table, row_id, parent, index);
+        if current.contains(&key) {
+            known.insert(key);
+        } else {
+            sqlx::query("UPDATE foreign_key_violations SET resolved_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    for (table, row_id, parent, index) in current.difference(&known) {
+        sqlx::query(
+            "INSERT INTO foreign_key_violations (table_name, row_id, parent_table, constraint_index) VALUES (?, ?, ?, ?)"
+        )
+        .bind(table)
+        .bind(row_id)
+        .bind(parent)
+        .bind(index)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    unresolved(pool).await
+}
+
+// Rows that reference a missing parent, as of the last check
+#[tauri::command]
+pub async fn get_foreign_key_violations(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<ForeignKeyViolation>> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    unresolved(&pool).await
+}
+
+#[tauri::command]
+pub async fn check_foreign_keys(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<ForeignKeyViolation>> {
+    let user = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+
+    let violations = record_violations(&pool).await?;
+    let tables: BTreeSet<&str> = violations.iter().map(|v| v.table_name.as_str()).collect();
+    AuditEvent::new("CHECK_FOREIGN_KEYS", "DATABASE", "foreign_keys")
+        .context("violations", &violations.len())
+        .context("tables", &tables)
+        .log(&pool, &user.id)
+        .await?;
+
+    Ok(violations)
+}
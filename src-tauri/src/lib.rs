@@ -6,47 +6,123 @@ mod commands;
 mod trajectory;
 mod csv_io;
 mod audit;
+mod audit_chain;
+mod audit_retention;
+mod audit_event;
+mod read_audit;
+mod backup;
+mod backup_crypto;
+mod backup_schedule;
+mod encryption;
+mod foreign_keys;
+mod restore;
+mod recovery;
 mod seed;
+mod password;
+mod lockout;
+mod totp;
+mod permissions;
+mod error;
+mod system_status;
+mod workspace;
 
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let system_state = system_status::SystemState::default();
+    let startup_state = system_state.clone();
+
     tauri::Builder::default()
-        .setup(|app| {
-            let handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                match db::init_db(&handle).await {
-                    Ok(pool) => {
-                        handle.manage(pool);
-                    }
-                    Err(e) => {
-                        eprintln!("Error initializing database: {}", e);
-                    }
-                }
-            });
+        .manage(system_state.clone())
+        .setup(move |app| {
+            app.manage(recovery::RecoveryStatus::default());
+            app.manage(workspace::ActiveWorkspace::default());
+            tauri::async_runtime::spawn(system_status::open_database(app.handle().clone(), startup_state.clone()));
             Ok(())
         })
         .plugin(tauri_plugin_log::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![
-            db::backup_db,
-            db::restore_db,
+        .invoke_handler(system_status::gate_commands(system_state, tauri::generate_handler![
+            system_status::get_system_status,
+            backup::backup_db,
+            backup::list_backups,
+            backup::inspect_backup,
+            backup::delete_backup,
+            backup_schedule::get_backup_schedule,
+            backup_schedule::set_backup_schedule,
+            restore::restore_db,
+            restore::restore_encrypted_backup,
+            encryption::get_database_encryption,
+            encryption::encrypt_database,
+            encryption::rekey_database,
+            encryption::unlock_database,
             storage::save_photo,
             storage::delete_photo,
             storage::get_photo_path,
             commands::student::get_students,
             commands::student::get_student_details,
+            commands::student::update_student_contact,
             commands::staff::get_staff,
             commands::education::get_student_attendance,
             commands::education::get_student_assessments,
             commands::finance::get_invoices,
             commands::finance::get_student_invoices,
             commands::auth::login,
+            commands::auth::logout,
+            commands::auth::get_current_user,
+            commands::auth::revoke_sessions,
+            commands::auth::change_password,
+            commands::auth::confirm_reauth,
+            commands::users::list_users,
+            commands::users::create_user,
+            commands::users::set_user_role,
+            commands::users::set_user_active,
+            commands::users::reset_user_password,
+            commands::users::unlock_user,
+            commands::users::set_user_staff_link,
+            commands::users::get_password_policy,
+            commands::users::update_password_policy,
+            commands::two_factor::begin_totp_enrollment,
+            commands::two_factor::confirm_totp_enrollment,
+            commands::two_factor::disable_totp,
+            commands::two_factor::regenerate_recovery_codes,
+            commands::two_factor::reset_user_two_factor,
+            commands::two_factor::get_two_factor_policy,
+            commands::two_factor::set_two_factor_requirement,
+            commands::permissions::get_permission_matrix,
+            commands::permissions::set_role_permission,
+            commands::permissions::get_my_permissions,
+            commands::roles::list_roles,
+            commands::roles::create_role,
+            commands::roles::update_role,
+            commands::roles::delete_role,
             trajectory::compute_trajectory,
             csv_io::import_students_csv,
             csv_io::export_students_csv,
-            audit::get_audit_logs
-        ])
+            audit::get_audit_logs,
+            audit::query_audit_logs,
+            audit::get_entity_history,
+            audit::export_audit_logs,
+            audit::get_audit_fail_closed,
+            audit::set_audit_fail_closed,
+            audit_retention::get_audit_retention_policy,
+            audit_retention::set_audit_retention_policy,
+            audit_retention::apply_audit_retention,
+            audit_retention::list_audit_archives,
+            read_audit::get_read_audit_policy,
+            read_audit::set_read_audit_policy,
+            audit_chain::verify_audit_chain,
+            audit_chain::create_audit_checkpoint,
+            recovery::get_recovery_report,
+            foreign_keys::get_foreign_key_violations,
+            foreign_keys::check_foreign_keys,
+            workspace::list_workspaces,
+            workspace::create_workspace,
+            workspace::select_workspace,
+            workspace::archive_workspace,
+            workspace::unarchive_workspace,
+            workspace::set_data_root
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,196 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::audit::SYSTEM_USER_ID;
+use crate::audit_event::AuditEvent;
+use sqlx::SqliteConnection;
+use std::time::Duration;
+
+// Failures before per-account backoff starts, and before a full lockout
+const BACKOFF_AFTER_FAILURES: i64 = 3;
+const LOCKOUT_AFTER_FAILURES: i64 = 5;
+const BACKOFF_BASE_SECONDS: i64 = 5;
+const LOCKOUT_BASE_SECONDS: i64 = 15 * 60;
+const LOCKOUT_MAX_SECONDS: i64 = 24 * 60 * 60;
+
+const RECENT_WINDOW: &str = "-15 minutes";
+// Unknown emails have no account to lock, so their failures are counted per email
+// over the longest lockout
+const UNKNOWN_WINDOW: &str = "-24 hours";
+
+// A burst of failures across all emails slows every attempt down rather than
+// refusing logins, which would let anyone lock the whole school out
+const GLOBAL_SLOWDOWN_FAILURES: i64 = 30;
+const GLOBAL_SLOWDOWN_DELAY: Duration = Duration::from_secs(2);
+
+// Seconds an account must wait after `failures` consecutive failed attempts
+fn delay_seconds(failures: i64) -> i64 {
+    if failures < BACKOFF_AFTER_FAILURES {
+        0
+    } else if failures < LOCKOUT_AFTER_FAILURES {
+        BACKOFF_BASE_SECONDS << (failures - BACKOFF_AFTER_FAILURES)
+    } else {
+        let doublings = (failures - LOCKOUT_AFTER_FAILURES).min(16);
+        (LOCKOUT_BASE_SECONDS << doublings).min(LOCKOUT_MAX_SECONDS)
+    }
+}
+
+fn wait_message(seconds: i64) -> String {
+    if seconds >= 60 {
+        format!("Too many failed attempts. Try again in {} minutes.", (seconds + 59) / 60)
+    } else {
+        format!("Too many failed attempts. Try again in {} seconds.", seconds.max(1))
+    }
+}
+
+// Rejects the attempt outright if the account or unknown email is throttled.
+// `email` must already be normalized.
+pub async fn check_login_allowed(pool: &DbPool, email: &str, user_id: Option<&str>) -> AppResult<()> {
+    let recent_failures: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM login_failures WHERE attempted_at > datetime('now', ?)"
+    )
+    .bind(RECENT_WINDOW)
+    .fetch_one(pool)
+    .await?;
+
+    if recent_failures >= GLOBAL_SLOWDOWN_FAILURES {
+        tokio::time::sleep(GLOBAL_SLOWDOWN_DELAY).await;
+    }
+
+    // Unknown emails follow the same schedule as accounts, so the response does not
+    // reveal whether an account exists
+    let Some(user_id) = user_id else {
+        let (failures, seconds_since_last): (i64, Option<i64>) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), CAST((julianday('now') - julianday(MAX(attempted_at))) * 86400 AS INTEGER)
+            FROM login_failures
+            WHERE email = ? AND user_id IS NULL AND attempted_at > datetime('now', ?)
+            "#
+        )
+        .bind(email)
+        .bind(UNKNOWN_WINDOW)
+        .fetch_one(pool)
+        .await?;
+
+        let remaining = delay_seconds(failures) - seconds_since_last.unwrap_or(0);
+        if remaining > 0 {
+            return Err(AppError::RateLimited(wait_message(remaining)));
+        }
+        return Ok(());
+    };
+
+    let remaining: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT CAST((julianday(locked_until) - julianday('now')) * 86400 AS INTEGER)
+        FROM users
+        WHERE id = ? AND locked_until > CURRENT_TIMESTAMP
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    match remaining {
+        Some(seconds) => Err(AppError::RateLimited(wait_message(seconds))),
+        None => Ok(()),
+    }
+}
+
+pub async fn record_failure(
+    pool: &DbPool,
+    email: &str,
+    user_id: Option<&str>,
+) -> AppResult<()> {
+    sqlx::query("INSERT INTO login_failures (email, user_id) VALUES (?, ?)")
+        .bind(email)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    let Some(user_id) = user_id else {
+        AuditEvent::new("LOGIN_FAILED", "USER", email)
+            .context("email", email)
+            .context("reason", "UNKNOWN_ACCOUNT")
+            .log(pool, SYSTEM_USER_ID)
+            .await?;
+        return Ok(());
+    };
+
+    let failures: i64 = sqlx::query_scalar(
+        "UPDATE users SET failed_login_count = failed_login_count + 1 WHERE id = ? RETURNING failed_login_count"
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    AuditEvent::new("LOGIN_FAILED", "USER", user_id)
+        .change("failed_login_count", &(failures - 1), &failures)
+        .context("email", email)
+        .context("reason", "BAD_PASSWORD")
+        .log(pool, user_id)
+        .await?;
+
+    let delay = delay_seconds(failures);
+    if delay > 0 {
+        sqlx::query("UPDATE users SET locked_until = datetime('now', ?) WHERE id = ?")
+            .bind(format!("+{} seconds", delay))
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+    }
+
+    if failures >= LOCKOUT_AFTER_FAILURES {
+        AuditEvent::new("ACCOUNT_LOCKED", "USER", user_id)
+            .context("failed_count", &failures)
+            .context("locked_seconds", &delay)
+            .log(pool, SYSTEM_USER_ID)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn record_success(pool: &DbPool, user_id: &str) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE users SET failed_login_count = 0, locked_until = NULL, last_login_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("LOGIN_SUCCESS", "USER", user_id).log_tx(&mut tx, user_id).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn unlock_account(conn: &mut SqliteConnection, user_id: &str) -> AppResult<()> {
+    sqlx::query("UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = ?")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_then_locks_with_doubling_up_to_a_day() {
+        assert_eq!(delay_seconds(0), 0);
+        assert_eq!(delay_seconds(2), 0);
+
+        // Short backoff from the third failure, a 15-minute lock from the fifth
+        assert_eq!(delay_seconds(3), 5);
+        assert_eq!(delay_seconds(4), 10);
+        assert_eq!(delay_seconds(5), 15 * 60);
+        assert_eq!(delay_seconds(6), 30 * 60);
+        assert_eq!(delay_seconds(7), 60 * 60);
+
+        // Doubling stops at the 24-hour cap, however many failures follow
+        assert_eq!(delay_seconds(11), 16 * 60 * 60);
+        assert_eq!(delay_seconds(12), 24 * 60 * 60);
+        assert_eq!(delay_seconds(1000), 24 * 60 * 60);
+    }
+}
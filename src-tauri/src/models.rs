@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use chrono::NaiveDate;
 
+// Built-in roles only; custom roles exist solely as rows in the roles table
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Role {
     Admin,
@@ -19,17 +20,6 @@ impl ToString for Role {
     }
 }
 
-impl From<String> for Role {
-    fn from(s: String) -> Self {
-        match s.as_str() {
-            "ADMIN" => Role::Admin,
-            "TEACHER" => Role::Teacher,
-            "MANAGEMENT_FINANCE" => Role::ManagementFinance,
-            _ => Role::Teacher, 
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: String,
@@ -39,6 +29,9 @@ pub struct User {
     pub active: bool,
     pub created_at: chrono::NaiveDateTime,
     pub last_login_at: Option<chrono::NaiveDateTime>,
+    pub must_change_password: bool,
+    pub totp_enabled: bool,
+    pub staff_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -75,3 +68,12 @@ pub struct StudentEducationProfile {
     pub enrollment_date: NaiveDate,
     pub status: String,
 }
+
+// The "Edit Sensitive" fields of a student record
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct StudentContact {
+    pub address: Option<String>,
+    pub guardian_name: Option<String>,
+    pub guardian_contact: Option<String>,
+    pub emergency_contact: Option<String>,
+}
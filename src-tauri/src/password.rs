@@ -0,0 +1,167 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, SqliteConnection};
+use bcrypt::{hash, verify, DEFAULT_COST};
+
+// Bundled at compile time so the check works fully offline
+const COMMON_PASSWORDS: &str = include_str!("../resources/common_passwords.txt");
+
+// Lower bounds an admin cannot configure the policy below
+const MIN_ALLOWED_LENGTH: i64 = 8;
+const MAX_HISTORY_DEPTH: i64 = 24;
+
+// Field reported on policy violations, whichever command supplied the password
+const PASSWORD_FIELD: &str = "password";
+
+// Applies where no database exists yet, e.g. to a new workspace's first admin;
+// matches the default in 009_password_policy.sql
+const DEFAULT_MIN_LENGTH: i64 = 10;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PasswordPolicy {
+    pub min_length: i64,
+    pub history_depth: i64,
+}
+
+impl PasswordPolicy {
+    pub fn validate(&self) -> AppResult<()> {
+        if self.min_length < MIN_ALLOWED_LENGTH {
+            return Err(AppError::invalid_field("min_length", format!("Minimum length cannot be below {}", MIN_ALLOWED_LENGTH)));
+        }
+        if !(0..=MAX_HISTORY_DEPTH).contains(&self.history_depth) {
+            return Err(AppError::invalid_field("history_depth", format!("History depth must be between 0 and {}", MAX_HISTORY_DEPTH)));
+        }
+        Ok(())
+    }
+}
+
+pub async fn load_policy(pool: &DbPool) -> AppResult<PasswordPolicy> {
+    sqlx::query_as::<_, PasswordPolicy>("SELECT min_length, history_depth FROM password_policy WHERE id = 1")
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::from)
+}
+
+pub async fn save_policy(conn: &mut SqliteConnection, policy: &PasswordPolicy) -> AppResult<()> {
+    policy.validate()?;
+
+    sqlx::query("UPDATE password_policy SET min_length = ?, history_depth = ?, updated_at = CURRENT_TIMESTAMP WHERE id = 1")
+        .bind(policy.min_length)
+        .bind(policy.history_depth)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+fn is_common_password(candidate: &str) -> bool {
+    let candidate = candidate.to_lowercase();
+    COMMON_PASSWORDS
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .any(|l| l == candidate)
+}
+
+fn check_strength(candidate: &str, min_length: i64) -> AppResult<()> {
+    if (candidate.chars().count() as i64) < min_length {
+        return Err(AppError::invalid_field(PASSWORD_FIELD, format!("Password must be at least {} characters", min_length)));
+    }
+
+    if is_common_password(candidate) {
+        return Err(AppError::invalid_field(PASSWORD_FIELD, "Password is too common. Please choose a less predictable password."));
+    }
+
+    Ok(())
+}
+
+fn check_not_email(email: &str, candidate: &str) -> AppResult<()> {
+    let lowered = candidate.to_lowercase();
+    let local_part = email.split('@').next().unwrap_or_default().to_lowercase();
+    if lowered == email.to_lowercase() || (!local_part.is_empty() && lowered == local_part) {
+        return Err(AppError::invalid_field(PASSWORD_FIELD, "Password must not match your email address"));
+    }
+    Ok(())
+}
+
+// Default policy for an account whose database has not been created yet
+pub fn enforce_default_policy(email: &str, candidate: &str) -> AppResult<()> {
+    check_strength(candidate, DEFAULT_MIN_LENGTH)?;
+    check_not_email(email, candidate)
+}
+
+// Checks a candidate password against the policy. `user` is the account being
+// changed (id, email) when one exists, enabling the reuse and email checks.
+pub async fn enforce_policy(
+    pool: &DbPool,
+    user: Option<(&str, &str)>,
+    candidate: &str,
+) -> AppResult<()> {
+    let policy = load_policy(pool).await?;
+
+    check_strength(candidate, policy.min_length)?;
+
+    let Some((user_id, email)) = user else {
+        return Ok(());
+    };
+
+    check_not_email(email, candidate)?;
+
+    if policy.history_depth > 0 {
+        // Current hash plus the most recent previous hashes
+        let recent_hashes = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT password_hash FROM users WHERE id = ?
+            UNION ALL
+            SELECT password_hash FROM (
+                SELECT password_hash FROM password_history
+                WHERE user_id = ?
+                ORDER BY id DESC
+                LIMIT ?
+            )
+            "#
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(policy.history_depth - 1)
+        .fetch_all(pool)
+        .await?;
+
+        for old_hash in &recent_hashes {
+            if verify(candidate, old_hash).unwrap_or(false) {
+                return Err(AppError::invalid_field(PASSWORD_FIELD, format!(
+                    "Password was used recently. Choose one not among your last {} passwords.",
+                    policy.history_depth
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Stores a new password, archiving the previous hash into password_history.
+// Run inside a transaction so both writes land together.
+pub async fn set_password(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    new_password: &str,
+    must_change: bool,
+) -> AppResult<()> {
+    let hashed = hash(new_password, DEFAULT_COST)?;
+
+    sqlx::query("INSERT INTO password_history (user_id, password_hash) SELECT id, password_hash FROM users WHERE id = ?")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("UPDATE users SET password_hash = ?, must_change_password = ? WHERE id = ?")
+        .bind(&hashed)
+        .bind(must_change)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::models::Role;
+
+pub struct PermissionDef {
+    pub key: &'static str,
+    pub description: &'static str,
+    // Row in ROLE_TEST_MATRIX.md this permission implements, if any
+    pub matrix_row: Option<&'static str>,
+    // Roles granted this permission the first time it is registered
+    pub default_roles: &'static [Role],
+}
+
+const ALL_ROLES: &[Role] = &[Role::Admin, Role::Teacher, Role::ManagementFinance];
+const EDUCATION: &[Role] = &[Role::Admin, Role::Teacher];
+const FINANCE: &[Role] = &[Role::Admin, Role::ManagementFinance];
+const ADMIN_ONLY: &[Role] = &[Role::Admin];
+
+pub const DASHBOARD_VIEW: &str = "dashboard.view";
+pub const STUDENTS_READ: &str = "students.read";
+pub const STUDENTS_READ_SENSITIVE: &str = "students.read_sensitive";
+pub const STUDENTS_WRITE_SENSITIVE: &str = "students.write_sensitive";
+pub const STUDENTS_IMPORT: &str = "students.import";
+pub const STUDENTS_EXPORT: &str = "students.export";
+pub const ATTENDANCE_READ: &str = "attendance.read";
+pub const ATTENDANCE_WRITE: &str = "attendance.write";
+pub const GRADES_READ: &str = "grades.read";
+pub const GRADES_WRITE: &str = "grades.write";
+pub const NOTES_MANAGE: &str = "notes.manage";
+pub const TRAJECTORY_READ: &str = "trajectory.read";
+pub const STAFF_READ: &str = "staff.read";
+pub const PHOTOS_WRITE: &str = "photos.write";
+pub const PHOTOS_DELETE: &str = "photos.delete";
+pub const FINANCE_FEE_PLANS: &str = "finance.fee_plans";
+pub const FINANCE_INVOICES_READ: &str = "finance.invoices.read";
+pub const FINANCE_PAYMENTS_WRITE: &str = "finance.payments.write";
+pub const SYSTEM_USERS: &str = "system.users";
+pub const SYSTEM_AUDIT: &str = "system.audit";
+pub const SYSTEM_BACKUP: &str = "system.backup";
+
+pub const REGISTRY: &[PermissionDef] = &[
+    PermissionDef { key: DASHBOARD_VIEW, description: "View the role dashboard", matrix_row: Some("Dashboard"), default_roles: ALL_ROLES },
+    PermissionDef { key: STUDENTS_READ, description: "List students (minimal columns)", matrix_row: Some("List Students"), default_roles: ALL_ROLES },
+    PermissionDef { key: STUDENTS_READ_SENSITIVE, description: "View full student profiles incl. contacts", matrix_row: Some("View Profile"), default_roles: EDUCATION },
+    PermissionDef { key: STUDENTS_WRITE_SENSITIVE, description: "Edit student address and contacts", matrix_row: Some("Edit Sensitive"), default_roles: ADMIN_ONLY },
+    PermissionDef { key: STUDENTS_IMPORT, description: "Import students from CSV", matrix_row: Some("CSV Import"), default_roles: ADMIN_ONLY },
+    PermissionDef { key: STUDENTS_EXPORT, description: "Export students to CSV", matrix_row: None, default_roles: EDUCATION },
+    PermissionDef { key: ATTENDANCE_READ, description: "View attendance records", matrix_row: Some("View Attendance"), default_roles: EDUCATION },
+    PermissionDef { key: ATTENDANCE_WRITE, description: "Record attendance", matrix_row: Some("Take Attendance"), default_roles: EDUCATION },
+    PermissionDef { key: GRADES_READ, description: "View assessments and grades", matrix_row: Some("View Grades"), default_roles: EDUCATION },
+    PermissionDef { key: GRADES_WRITE, description: "Enter assessments and grades", matrix_row: Some("Enter Grades"), default_roles: EDUCATION },
+    PermissionDef { key: NOTES_MANAGE, description: "Manage student notes and interventions", matrix_row: Some("Notes/Interv."), default_roles: EDUCATION },
+    PermissionDef { key: TRAJECTORY_READ, description: "Compute student trajectories", matrix_row: None, default_roles: EDUCATION },
+    PermissionDef { key: STAFF_READ, description: "View the staff directory", matrix_row: None, default_roles: ALL_ROLES },
+    PermissionDef { key: PHOTOS_WRITE, description: "Upload student and staff photos", matrix_row: None, default_roles: EDUCATION },
+    PermissionDef { key: PHOTOS_DELETE, description: "Delete student and staff photos", matrix_row: None, default_roles: ADMIN_ONLY },
+    PermissionDef { key: FINANCE_FEE_PLANS, description: "Manage fee plans", matrix_row: Some("Fee Plans"), default_roles: FINANCE },
+    PermissionDef { key: FINANCE_INVOICES_READ, description: "View invoices", matrix_row: Some("Invoices"), default_roles: FINANCE },
+    PermissionDef { key: FINANCE_PAYMENTS_WRITE, description: "Record payments", matrix_row: Some("Payments"), default_roles: FINANCE },
+    PermissionDef { key: SYSTEM_USERS, description: "Manage users, roles and security policy", matrix_row: Some("User Mgmt"), default_roles: ADMIN_ONLY },
+    PermissionDef { key: SYSTEM_AUDIT, description: "View the audit log", matrix_row: Some("Audit Log"), default_roles: ADMIN_ONLY },
+    PermissionDef { key: SYSTEM_BACKUP, description: "Back up and restore the database", matrix_row: Some("Backup/Restore"), default_roles: ADMIN_ONLY },
+];
+
+pub fn is_registered(key: &str) -> bool {
+    REGISTRY.iter().any(|p| p.key == key)
+}
+
+// Registers permissions new to this database and grants their defaults.
+// Permissions already present keep whatever mapping an admin has set.
+pub async fn sync_registry(pool: &DbPool) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    for def in REGISTRY {
+        let inserted = sqlx::query("INSERT OR IGNORE INTO permissions (key, description) VALUES (?, ?)")
+            .bind(def.key)
+            .bind(def.description)
+            .execute(&mut *tx)
+            .await?;
+
+        if inserted.rows_affected() == 0 {
+            continue;
+        }
+
+        for role in def.default_roles {
+            sqlx::query("INSERT OR IGNORE INTO role_permissions (role, permission_key) VALUES (?, ?)")
+                .bind(role.to_string())
+                .bind(def.key)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn role_has_permission(pool: &DbPool, role: &str, key: &str) -> AppResult<bool> {
+    debug_assert!(is_registered(key), "unregistered permission {}", key);
+
+    let granted = sqlx::query_scalar::<_, i32>("SELECT 1 FROM role_permissions WHERE role = ? AND permission_key = ?")
+        .bind(role)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(granted.is_some())
+}
+
+pub async fn role_permissions(pool: &DbPool, role: &str) -> AppResult<Vec<String>> {
+    sqlx::query_scalar("SELECT permission_key FROM role_permissions WHERE role = ? ORDER BY permission_key")
+        .bind(role)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::from)
+}
+
+pub async fn role_names(pool: &DbPool) -> AppResult<Vec<String>> {
+    sqlx::query_scalar("SELECT name FROM roles ORDER BY built_in DESC, name")
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::from)
+}
+
+// Rejects role strings not defined in the roles table
+pub async fn validate_role(pool: &DbPool, role: &str) -> AppResult<()> {
+    let roles = role_names(pool).await?;
+    if roles.iter().any(|r| r == role) {
+        Ok(())
+    } else {
+        Err(AppError::invalid_field("role", format!("Invalid role '{}'. Expected one of: {}", role, roles.join(", "))))
+    }
+}
+
+pub async fn role_is_class_scoped(pool: &DbPool, role: &str) -> AppResult<bool> {
+    let scoped: Option<bool> = sqlx::query_scalar("SELECT class_scoped FROM roles WHERE name = ?")
+        .bind(role)
+        .fetch_optional(pool)
+        .await?;
+
+    // An unknown role should never get this far, but fail closed if it does
+    Ok(scoped.unwrap_or(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const MATRIX: &str = include_str!("../../ROLE_TEST_MATRIX.md");
+
+    // (command/page, roles marked ✅) for each row of the matrix table
+    fn parse_matrix() -> Vec<(String, Vec<Role>)> {
+        MATRIX
+            .lines()
+            .filter(|l| l.starts_with('|'))
+            .map(|l| l.split('|').map(str::trim).collect::<Vec<_>>())
+            .filter(|cells| cells.len() >= 6 && cells[3].starts_with(['✅', '❌']))
+            .map(|cells| {
+                let granted = [(cells[3], Role::Admin), (cells[4], Role::Teacher), (cells[5], Role::ManagementFinance)]
+                    .into_iter()
+                    .filter(|(cell, _)| cell.starts_with('✅'))
+                    .map(|(_, role)| role)
+                    .collect();
+                (cells[2].to_string(), granted)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn registry_defaults_match_role_test_matrix() {
+        let matrix = parse_matrix();
+        assert!(!matrix.is_empty(), "no rows parsed from ROLE_TEST_MATRIX.md");
+
+        for (row, expected) in &matrix {
+            // Login is available to every account and is not a permission
+            if row == "Login" {
+                continue;
+            }
+
+            let defs: Vec<_> = REGISTRY.iter().filter(|p| p.matrix_row == Some(row.as_str())).collect();
+            assert!(!defs.is_empty(), "matrix row '{}' has no permission in the registry", row);
+
+            for def in defs {
+                assert_eq!(
+                    def.default_roles.iter().map(Role::to_string).collect::<HashSet<_>>(),
+                    expected.iter().map(Role::to_string).collect::<HashSet<_>>(),
+                    "default roles for '{}' disagree with matrix row '{}'",
+                    def.key,
+                    row
+                );
+            }
+        }
+
+        let rows: HashSet<_> = matrix.iter().map(|(row, _)| row.as_str()).collect();
+        for def in REGISTRY {
+            if let Some(row) = def.matrix_row {
+                assert!(rows.contains(row), "permission '{}' points at missing matrix row '{}'", def.key, row);
+            }
+        }
+    }
+
+    #[test]
+    fn registry_keys_are_unique() {
+        let keys: HashSet<_> = REGISTRY.iter().map(|p| p.key).collect();
+        assert_eq!(keys.len(), REGISTRY.len());
+    }
+}
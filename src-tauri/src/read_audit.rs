@@ -0,0 +1,150 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit_event::AuditEvent;
+use crate::permissions::SYSTEM_AUDIT;
+use tauri::State;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+pub const VIEW_STUDENT_PROFILE: &str = "VIEW_STUDENT_PROFILE";
+pub const VIEW_ATTENDANCE: &str = "VIEW_ATTENDANCE";
+pub const VIEW_ASSESSMENTS: &str = "VIEW_ASSESSMENTS";
+pub const VIEW_INVOICES: &str = "VIEW_INVOICES";
+pub const VIEW_TRAJECTORY: &str = "VIEW_TRAJECTORY";
+
+// One page view reads several of these; any of them counts as the recent read
+const READ_ACTIONS: &[&str] = &[VIEW_STUDENT_PROFILE, VIEW_ATTENDANCE, VIEW_ASSESSMENTS, VIEW_INVOICES, VIEW_TRAJECTORY];
+
+const MAX_DEDUP_MINUTES: i64 = 24 * 60;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ReadAuditPolicy {
+    pub action: String,
+    pub description: String,
+    pub enabled: bool,
+    pub dedup_minutes: i64,
+}
+
+// Logs a read of a sensitive record unless auditing is off for `action` or the
+// same user already read any part of the same record within the de-duplication
+// window, so that one page view is one entry.
+// Call before returning the data; in fail-closed mode an error means the data
+// must not be returned.
+pub async fn record_read(
+    pool: &DbPool,
+    actor_user_id: &str,
+    action: &'static str,
+    entity_type: &'static str,
+    entity_id: &str
+) -> AppResult<()> {
+    let policy: Option<(bool, i64)> = sqlx::query_as(
+        "SELECT enabled, dedup_minutes FROM read_audit_policy WHERE action = ?"
+    )
+    .bind(action)
+    .fetch_optional(pool)
+    .await?;
+
+    // Actions without a policy row are audited, never silently skipped
+    let (enabled, dedup_minutes) = policy.unwrap_or((true, 0));
+    if !enabled {
+        return Ok(());
+    }
+
+    if dedup_minutes > 0 {
+        let placeholders = vec!["?"; READ_ACTIONS.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT 1 FROM audit_log
+            WHERE actor_user_id = ? AND entity_type = ? AND entity_id = ? AND action IN ({})
+              AND created_at >= datetime('now', ?)
+            LIMIT 1
+            "#,
+            placeholders
+        );
+        let mut query = sqlx::query_scalar::<_, i32>(&sql)
+            .bind(actor_user_id)
+            .bind(entity_type)
+            .bind(entity_id);
+        for read_action in READ_ACTIONS {
+            query = query.bind(*read_action);
+        }
+        let recent = query
+            .bind(format!("-{} minutes", dedup_minutes))
+            .fetch_optional(pool)
+            .await?;
+
+        if recent.is_some() {
+            return Ok(());
+        }
+    }
+
+    AuditEvent::new(action, entity_type, entity_id).log(pool, actor_user_id).await
+}
+
+#[tauri::command]
+pub async fn get_read_audit_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+) -> AppResult<Vec<ReadAuditPolicy>> {
+    check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+
+    let policy = sqlx::query_as::<_, ReadAuditPolicy>(
+        "SELECT action, description, enabled, dedup_minutes FROM read_audit_policy ORDER BY action"
+    )
+    .fetch_all(&*pool)
+    .await?;
+
+    Ok(policy)
+}
+
+#[tauri::command]
+pub async fn set_read_audit_policy(
+    pool: State<'_, DbPool>,
+    token: String,
+    action: String,
+    enabled: bool,
+    dedup_minutes: i64
+) -> AppResult<ReadAuditPolicy> {
+    let admin = check_permission(&pool, &token, SYSTEM_AUDIT).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    if !(0..=MAX_DEDUP_MINUTES).contains(&dedup_minutes) {
+        return Err(AppError::invalid_field(
+            "dedup_minutes",
+            format!("De-duplication window must be between 0 and {} minutes", MAX_DEDUP_MINUTES)
+        ));
+    }
+
+    let previous = sqlx::query_as::<_, ReadAuditPolicy>(
+        "SELECT action, description, enabled, dedup_minutes FROM read_audit_policy WHERE action = ?"
+    )
+    .bind(&action)
+    .fetch_optional(&*pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Unknown read audit action '{}'", action)))?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "UPDATE read_audit_policy SET enabled = ?, dedup_minutes = ?, updated_at = CURRENT_TIMESTAMP WHERE action = ?"
+    )
+    .bind(enabled)
+    .bind(dedup_minutes)
+    .bind(&action)
+    .execute(&mut *tx)
+    .await?;
+
+    AuditEvent::new("UPDATE_READ_AUDIT_POLICY", "SYSTEM", "READ_AUDIT_POLICY")
+        .change("enabled", &previous.enabled, &enabled)
+        .change("dedup_minutes", &previous.dedup_minutes, &dedup_minutes)
+        .context("action", &action)
+        .log_tx(&mut tx, &admin.id)
+        .await?;
+
+    tx.commit().await?;
+
+    let updated = ReadAuditPolicy { enabled, dedup_minutes, ..previous };
+
+    Ok(updated)
+}
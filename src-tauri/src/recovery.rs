@@ -0,0 +1,321 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::check_permission;
+use crate::backup::BackupKind;
+use crate::encryption::{attach_key, options_for_new};
+use crate::permissions::SYSTEM_BACKUP;
+use crate::restore::{prepare_candidate, quote_ident};
+use tauri::State;
+use serde::Serialize;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Damaged databases and salvage attempts are kept here for manual inspection
+const CORRUPT_DIR: &str = "corrupt";
+const RECOVERY_STAGING_DB: &str = "recovery_staging.db";
+// Newest backups tried before giving up on them
+const MAX_BACKUP_ATTEMPTS: usize = 5;
+const MAX_REPORTED_PROBLEMS: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RecoverySource {
+    // Every row of every table was copied out of the damaged file
+    Salvage,
+    Backup,
+    // Some rows could not be read and there was no usable backup
+    PartialSalvage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSalvage {
+    pub name: String,
+    pub rows_copied: u64,
+    pub rows_failed: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryReport {
+    pub recovered_at: String,
+    // Output of the integrity check that failed
+    pub problems: Vec<String>,
+    pub source: RecoverySource,
+    pub backup_used: Option<String>,
+    pub corrupt_copy: String,
+    pub salvage_copy: Option<String>,
+    pub tables: Vec<TableSalvage>,
+    pub schema_errors: Vec<String>,
+}
+
+// Set by `init_db` when the database had to be recovered during this start-up
+#[derive(Default)]
+pub struct RecoveryStatus(pub Mutex<Option<RecoveryReport>>);
+
+struct Salvage {
+    tables: Vec<TableSalvage>,
+    schema_errors: Vec<String>,
+}
+
+impl Salvage {
+    fn complete(&self) -> bool {
+        self.schema_errors.is_empty() && self.tables.iter().all(|t| t.rows_failed == 0 && t.error.is_none())
+    }
+}
+
+// Runs the integrity check; a file too damaged to check reports that as its problem
+pub async fn integrity_problems(conn: &mut SqliteConnection) -> Vec<String> {
+    match sqlx::query_scalar::<_, String>("PRAGMA integrity_check").fetch_all(&mut *conn).await {
+        Ok(rows) if rows == ["ok"] => Vec::new(),
+        Ok(rows) => rows.into_iter().take(MAX_REPORTED_PROBLEMS).collect(),
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+// Copies whatever can still be read into a fresh file: the schema first, then each
+// table in bulk, falling back to one row at a time for tables with damaged pages
+async fn salvage(damaged: &Path, target: &Path) -> AppResult<Salvage> {
+    if target.exists() {
+        fs::remove_file(target)?;
+    }
+
+    let mut conn = options_for_new(target)
+        .create_if_missing(true)
+        .foreign_keys(false)
+        .connect()
+        .await?;
+    sqlx::query("ATTACH DATABASE ? AS damaged KEY ?")
+        .bind(damaged.to_string_lossy().as_ref())
+        .bind(attach_key(damaged))
+        .execute(&mut conn)
+        .await?;
+
+    let result = salvage_attached(&mut conn).await;
+
+    let _ = sqlx::query("DETACH DATABASE damaged").execute(&mut conn).await;
+    conn.close().await?;
+    result
+}
+
+async fn salvage_attached(conn: &mut SqliteConnection) -> AppResult<Salvage> {
+    // Without a readable schema there is nothing to salvage
+    let objects: Vec<(String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT type, name, sql FROM damaged.sqlite_master
+        WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+        ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut salvage = Salvage { tables: Vec::new(), schema_errors: Vec::new() };
+    for (kind, name, sql) in &objects {
+        if let Err(e) = sqlx::query(sql).execute(&mut *conn).await {
+            salvage.schema_errors.push(format!("{} {}: {}", kind, name, e));
+            continue;
+        }
+        if kind == "table" {
+            salvage.tables.push(salvage_table(conn, name).await);
+        }
+    }
+
+    // Best effort: without it, AUTOINCREMENT ids could be reused
+    let _ = sqlx::query("INSERT INTO main.sqlite_sequence (name, seq) SELECT name, seq FROM damaged.sqlite_sequence")
+        .execute(&mut *conn)
+        .await;
+
+    Ok(salvage)
+}
+
+async fn salvage_table(conn: &mut SqliteConnection, name: &str) -> TableSalvage {
+    let table = quote_ident(name);
+    let mut report = TableSalvage { name: name.to_string(), rows_copied: 0, rows_failed: 0, error: None };
+
+    let bulk = sqlx::query(&format!("INSERT INTO main.{0} SELECT * FROM damaged.{0}", table))
+        .execute(&mut *conn)
+        .await;
+    let bulk_error = match bulk {
+        Ok(done) => {
+            report.rows_copied = done.rows_affected();
+            return report;
+        }
+        Err(e) => e.to_string(),
+    };
+
+    // Reach past a damaged region from both ends: forward until the first unreadable
+    // page, then backward from the last row down to the rows already found
+    let (mut rowids, error) = walk_rowids(conn, &table, false, i64::MIN).await;
+    if error.is_some() {
+        let floor = rowids.last().copied().unwrap_or(i64::MIN);
+        let (tail, _) = walk_rowids(conn, &table, true, floor).await;
+        rowids.extend(tail.into_iter().rev());
+        report.error = error;
+    }
+
+    for rowid in rowids {
+        let copied = sqlx::query(&format!("INSERT INTO main.{0} SELECT * FROM damaged.{0} WHERE rowid = ?", table))
+            .bind(rowid)
+            .execute(&mut *conn)
+            .await;
+        match copied {
+            Ok(_) => report.rows_copied += 1,
+            Err(_) => report.rows_failed += 1,
+        }
+    }
+
+    // The bulk copy failed, so something was lost even if every reachable row copied
+    if report.error.is_none() && report.rows_failed == 0 {
+        report.error = Some(bulk_error);
+    }
+    report
+}
+
+// Row ids in one direction, one seek at a time so a bad page only stops the walk
+// where it is. Descending walks stop above `floor`.
+async fn walk_rowids(conn: &mut SqliteConnection, table: &str, descending: bool, floor: i64) -> (Vec<i64>, Option<String>) {
+    let sql = if descending {
+        format!("SELECT rowid FROM damaged.{} WHERE rowid < ? AND rowid > ? ORDER BY rowid DESC LIMIT 1", table)
+    } else {
+        format!("SELECT rowid FROM damaged.{} WHERE rowid > ? AND rowid > ? ORDER BY rowid LIMIT 1", table)
+    };
+
+    let mut found = Vec::new();
+    let mut cursor = if descending { i64::MAX } else { i64::MIN };
+    loop {
+        let next = sqlx::query_scalar::<_, i64>(&sql)
+            .bind(cursor)
+            .bind(floor)
+            .fetch_optional(&mut *conn)
+            .await;
+        match next {
+            Ok(Some(rowid)) => {
+                found.push(rowid);
+                cursor = rowid;
+            }
+            Ok(None) => return (found, None),
+            Err(e) => return (found, Some(e.to_string())),
+        }
+    }
+}
+
+// Plain database backups, newest first
+fn backup_files(app_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = [BackupKind::Auto, BackupKind::Manual]
+        .iter()
+        .filter_map(|kind| fs::read_dir(app_dir.join(kind.dir())).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+// Replaces a database that failed its integrity check. The damaged file is copied
+// aside untouched, then replaced by a complete salvage, else by the newest backup
+// that passes verification, else by a partial salvage. If none of those can be
+// prepared, the live file is left exactly as it was.
+pub async fn recover(app_dir: &Path, db_path: &Path, problems: Vec<String>) -> AppResult<RecoveryReport> {
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let corrupt_dir = app_dir.join(CORRUPT_DIR);
+    fs::create_dir_all(&corrupt_dir)?;
+
+    let corrupt_copy = corrupt_dir.join(format!("resonance_corrupt_{}.db", timestamp));
+    fs::copy(db_path, &corrupt_copy)?;
+    for suffix in ["-wal", "-shm"] {
+        let journal = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+        if journal.exists() {
+            fs::copy(&journal, format!("{}{}", corrupt_copy.display(), suffix))?;
+        }
+    }
+
+    let salvage_path = corrupt_dir.join(format!("resonance_salvage_{}.db", timestamp));
+    let staging = app_dir.join(RECOVERY_STAGING_DB);
+
+    let salvaged = match salvage(db_path, &salvage_path).await {
+        Ok(salvaged) => Some(salvaged),
+        Err(e) => {
+            eprintln!("Salvage failed: {}", e);
+            None
+        }
+    };
+
+    let mut source = None;
+    let mut backup_used = None;
+    if let Some(salvaged) = salvaged.as_ref().filter(|s| s.complete()) {
+        if prepare_candidate(&salvage_path, &staging).await.is_ok() {
+            source = Some(RecoverySource::Salvage);
+        } else {
+            eprintln!("Salvaged database failed verification ({} tables)", salvaged.tables.len());
+        }
+    }
+
+    if source.is_none() {
+        for backup in backup_files(app_dir).into_iter().take(MAX_BACKUP_ATTEMPTS) {
+            match prepare_candidate(&backup, &staging).await {
+                Ok(_) => {
+                    source = Some(RecoverySource::Backup);
+                    backup_used = Some(backup.to_string_lossy().to_string());
+                    break;
+                }
+                Err(e) => eprintln!("Backup {} is not usable: {}", backup.display(), e),
+            }
+        }
+    }
+
+    if source.is_none() && salvaged.is_some() && prepare_candidate(&salvage_path, &staging).await.is_ok() {
+        source = Some(RecoverySource::PartialSalvage);
+    }
+
+    let Some(source) = source else {
+        let _ = fs::remove_file(&staging);
+        return Err(AppError::Internal(format!(
+            "the database is damaged and could not be recovered; a copy was kept at {}",
+            corrupt_copy.display()
+        )));
+    };
+
+    // The stale journal belongs to the damaged file
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", db_path.display(), suffix));
+    }
+    fs::rename(&staging, db_path)?;
+
+    // The salvage is only worth keeping when it was not used as is
+    let salvage_copy = if source == RecoverySource::Salvage || salvaged.is_none() {
+        let _ = fs::remove_file(&salvage_path);
+        None
+    } else {
+        Some(salvage_path.to_string_lossy().to_string())
+    };
+
+    let Salvage { tables, schema_errors } = salvaged.unwrap_or(Salvage { tables: Vec::new(), schema_errors: Vec::new() });
+    Ok(RecoveryReport {
+        recovered_at: chrono::Utc::now().to_rfc3339(),
+        problems,
+        source,
+        backup_used,
+        corrupt_copy: corrupt_copy.to_string_lossy().to_string(),
+        salvage_copy,
+        tables,
+        schema_errors,
+    })
+}
+
+// What happened the last time the database had to be recovered at start-up, if it did
+#[tauri::command]
+pub async fn get_recovery_report(
+    pool: State<'_, DbPool>,
+    status: State<'_, RecoveryStatus>,
+    token: String,
+) -> AppResult<Option<RecoveryReport>> {
+    check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+
+    let report = status.0.lock().map_err(|_| AppError::Internal("recovery status lock poisoned".to_string()))?.clone();
+    Ok(report)
+}
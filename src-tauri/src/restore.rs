@@ -0,0 +1,413 @@
+use crate::db::{finish_open, DbPool, DB_FILE_NAME, MIGRATOR};
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit::SYSTEM_USER_ID;
+use crate::audit_event::AuditEvent;
+use crate::backup::{
+    catalog_snapshot, take_snapshot, unpack_encrypted_backup, BackupKind, BackupSnapshot,
+    ARCHIVE_DB_NAME, PHOTO_DIRS, RESTORE_STAGING_DIR,
+};
+use crate::encryption::{attach_key, is_encrypted, options_for};
+use crate::permissions::SYSTEM_BACKUP;
+use tauri::{AppHandle, State};
+use serde::Serialize;
+use sqlx::{pool::PoolConnection, sqlite::SqlitePoolOptions, Connection, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+// The candidate is copied here and migrated, so the chosen file is never modified
+const STAGING_DB_NAME: &str = "restore_staging.db";
+
+static RESTORE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+struct RestoreGuard;
+
+impl RestoreGuard {
+    fn acquire() -> AppResult<Self> {
+        if RESTORE_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+            return Err(AppError::Conflict("A restore is already in progress".to_string()));
+        }
+        Ok(RestoreGuard)
+    }
+}
+
+impl Drop for RestoreGuard {
+    fn drop(&mut self) {
+        RESTORE_IN_PROGRESS.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub source: String,
+    pub schema_version: Option<i64>,
+    pub migrations_applied: usize,
+    pub safety_backup: String,
+}
+
+pub struct Candidate {
+    pub schema_version: Option<i64>,
+    pub migrations_applied: usize,
+}
+
+// Checks that `source` is a healthy database from this or an older version of the
+// application, then copies it to `staging` and brings the copy up to date
+pub async fn prepare_candidate(source: &Path, staging: &Path) -> AppResult<Candidate> {
+    // Files encrypted with the live database's key have no header; opening them with
+    // the key tells whether they are databases
+    let mut header = [0u8; 16];
+    let has_header = File::open(source)?.read_exact(&mut header).is_ok() && &header == SQLITE_HEADER;
+    if !has_header && !is_encrypted() {
+        return Err(AppError::invalid("The selected file is not a database backup"));
+    }
+
+    if staging.exists() {
+        fs::remove_file(staging)?;
+    }
+    fs::copy(source, staging)?;
+
+    // Migrations that rebuild tables need foreign keys off, as at start-up
+    let candidate_pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options_for(staging).foreign_keys(false))
+        .await?;
+    let result = check_and_migrate(&candidate_pool).await;
+    candidate_pool.close().await;
+    result
+}
+
+async fn check_and_migrate(pool: &DbPool) -> AppResult<Candidate> {
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await
+        .map_err(|_| AppError::invalid("The selected file is not a readable database"))?;
+    if problems != ["ok"] {
+        return Err(AppError::invalid(format!("The backup failed the integrity check: {}", problems.join("; "))));
+    }
+
+    let has_history: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')"
+    )
+    .fetch_one(pool)
+    .await?;
+    if !has_history {
+        return Err(AppError::invalid("The selected database was not created by this application"));
+    }
+
+    let applied: Vec<(i64, Vec<u8>, bool)> = sqlx::query_as(
+        "SELECT version, checksum, success FROM _sqlx_migrations ORDER BY version"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let known: HashMap<i64, &[u8]> = MIGRATOR.iter().map(|m| (m.version, m.checksum.as_ref())).collect();
+    let latest = known.keys().copied().max().unwrap_or(0);
+    for (version, checksum, success) in &applied {
+        if !success {
+            return Err(AppError::invalid("The backup was taken while an upgrade was unfinished"));
+        }
+        match known.get(version) {
+            None if *version > latest => {
+                return Err(AppError::invalid("The backup was made by a newer version of the application"));
+            }
+            Some(expected) if *expected == checksum.as_slice() => {}
+            _ => {
+                return Err(AppError::invalid("The backup's schema history does not match this version of the application"));
+            }
+        }
+    }
+
+    let migrations_applied = known.len() - applied.len();
+    MIGRATOR.run(pool).await?;
+
+    let schema_version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(Candidate { schema_version, migrations_applied })
+}
+
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+// Replaces every table, index, view and trigger in the live database with those of
+// `source` in one transaction, so a failure part-way leaves the live data untouched
+async fn replace_contents(conn: &mut SqliteConnection, source: &Path, revoke_sessions: bool, event: AuditEvent) -> AppResult<()> {
+    // Tables are dropped and recreated in no particular order; the pragma has no
+    // effect inside a transaction, and the connection is closed afterwards anyway
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+    sqlx::query("ATTACH DATABASE ? AS restore_source KEY ?")
+        .bind(source.to_string_lossy().as_ref())
+        .bind(attach_key(source))
+        .execute(&mut *conn)
+        .await?;
+
+    let result = copy_attached(conn, revoke_sessions, event).await;
+
+    let _ = sqlx::query("DETACH DATABASE restore_source").execute(&mut *conn).await;
+    result
+}
+
+async fn copy_attached(conn: &mut SqliteConnection, revoke_sessions: bool, event: AuditEvent) -> AppResult<()> {
+    let mut tx = conn.begin().await?;
+
+    let existing: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT type, name FROM main.sqlite_master
+        WHERE type IN ('view', 'table') AND name NOT LIKE 'sqlite_%'
+        ORDER BY type = 'view' DESC
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for (kind, name) in &existing {
+        sqlx::query(&format!("DROP {} main.{}", kind.to_uppercase(), quote_ident(name)))
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Tables are filled before their indexes are built
+    let objects: Vec<(String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT type, name, sql FROM restore_source.sqlite_master
+        WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+        ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'index' THEN 1 WHEN 'view' THEN 2 ELSE 3 END
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    for (kind, name, sql) in &objects {
+        sqlx::query(sql).execute(&mut *tx).await?;
+        if kind == "table" {
+            let table = quote_ident(name);
+            sqlx::query(&format!("INSERT INTO main.{0} SELECT * FROM restore_source.{0}", table))
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    // AUTOINCREMENT counters can be ahead of the highest surviving id
+    let has_sequence: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM restore_source.sqlite_master WHERE name = 'sqlite_sequence')"
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if has_sequence {
+        sqlx::query("DELETE FROM main.sqlite_sequence").execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO main.sqlite_sequence (name, seq) SELECT name, seq FROM restore_source.sqlite_sequence")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Sessions in the restored data may belong to accounts that have since changed
+    if revoke_sessions {
+        sqlx::query("UPDATE sessions SET revoked_at = CURRENT_TIMESTAMP WHERE revoked_at IS NULL")
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    event.log_tx(&mut tx, SYSTEM_USER_ID).await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Holding every connection keeps other commands waiting until the swap is done
+pub async fn hold_all(pool: &DbPool) -> AppResult<Vec<PoolConnection<Sqlite>>> {
+    let mut held = Vec::new();
+    for _ in 0..pool.options().get_max_connections() {
+        held.push(pool.acquire().await?);
+    }
+    Ok(held)
+}
+
+// Closed connections are reopened on demand, with fresh statement caches
+pub fn release(held: Vec<PoolConnection<Sqlite>>) {
+    for mut conn in held {
+        conn.close_on_drop();
+    }
+}
+
+async fn swap_in(pool: &DbPool, app_dir: &Path, staging: &Path, event: AuditEvent) -> AppResult<BackupSnapshot> {
+    let mut held = hold_all(pool).await?;
+
+    let result = async {
+        // Taken while every connection is held, so nothing is lost between the two
+        let safety = take_snapshot(&mut held[0], app_dir, BackupKind::Manual).await?;
+        let safety_name = safety.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+        let event = event.context("safety_backup", &safety_name);
+        if let Err(e) = replace_contents(&mut held[0], staging, true, event).await {
+            let _ = fs::remove_file(&safety.path);
+            return Err(e);
+        }
+        Ok(safety)
+    }
+    .await;
+
+    release(held);
+    result
+}
+
+// Swaps a prepared candidate into the live database. If the restored database cannot
+// be opened, the safety copy taken just before the swap is put back the same way.
+async fn restore_database(
+    pool: &DbPool,
+    app_dir: &Path,
+    source: &Path,
+    source_label: &str,
+    admin_id: &str,
+    event: AuditEvent
+) -> AppResult<RestoreReport> {
+    let live_db = app_dir.join(DB_FILE_NAME);
+    if live_db.exists() && fs::canonicalize(source)? == fs::canonicalize(&live_db)? {
+        return Err(AppError::invalid("The selected file is the database currently in use"));
+    }
+
+    let staging = app_dir.join(STAGING_DB_NAME);
+    let candidate = match prepare_candidate(source, &staging).await {
+        Ok(candidate) => candidate,
+        Err(e) => {
+            let _ = fs::remove_file(&staging);
+            return Err(e);
+        }
+    };
+
+    // Written to the outgoing database, which the safety backup preserves
+    let mut tx = pool.begin().await?;
+    AuditEvent::new("RESTORE_DATABASE", "BACKUP", source_label)
+        .context("schema_version", &candidate.schema_version)
+        .context("migrations_applied", &candidate.migrations_applied)
+        .log_tx(&mut tx, admin_id)
+        .await?;
+    tx.commit().await?;
+
+    let event = event
+        .context("restored_by", admin_id)
+        .context("schema_version", &candidate.schema_version)
+        .context("migrations_applied", &candidate.migrations_applied);
+    let swapped = swap_in(pool, app_dir, &staging, event).await;
+    let _ = fs::remove_file(&staging);
+    let safety = swapped?;
+
+    if let Err(e) = finish_open(pool).await {
+        let event = AuditEvent::new("RESTORE_ROLLED_BACK", "BACKUP", source_label)
+            .context("restored_by", admin_id)
+            .context("error", &e.to_string());
+
+        let mut held = hold_all(pool).await?;
+        let rolled_back = replace_contents(&mut held[0], &safety.path, false, event).await;
+        release(held);
+
+        if let Err(rollback_error) = rolled_back {
+            return Err(AppError::Internal(format!(
+                "restore failed ({}) and could not be rolled back ({}); the previous database is at {}",
+                e, rollback_error, safety.path.display()
+            )));
+        }
+        if let Err(catalog_error) = catalog_snapshot(pool, &safety, SYSTEM_USER_ID).await {
+            eprintln!("Failed to record safety backup: {}", catalog_error);
+        }
+        return Err(e);
+    }
+
+    // The restore stands even if its safety copy cannot be cataloged
+    let safety_backup = match catalog_snapshot(pool, &safety, SYSTEM_USER_ID).await {
+        Ok(record) => record.file_path,
+        Err(e) => {
+            eprintln!("Failed to record safety backup: {}", e);
+            safety.path.to_string_lossy().to_string()
+        }
+    };
+
+    Ok(RestoreReport {
+        source: source_label.to_string(),
+        schema_version: candidate.schema_version,
+        migrations_applied: candidate.migrations_applied,
+        safety_backup,
+    })
+}
+
+// Restores a plain database backup in place. All sessions, including the caller's,
+// are revoked, so the frontend signs out afterwards.
+#[tauri::command]
+pub async fn restore_db(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    backup_path_str: String
+) -> AppResult<RestoreReport> {
+    let admin = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    require_recent_auth(&pool, &token).await?;
+    let _guard = RestoreGuard::acquire()?;
+
+    let app_dir = crate::workspace::data_dir(&app)?;
+    let source = PathBuf::from(&backup_path_str);
+    if !source.is_file() {
+        return Err(AppError::NotFound("Backup file not found".to_string()));
+    }
+
+    let event = AuditEvent::new("DATABASE_RESTORED", "BACKUP", backup_path_str.as_str())
+        .context("encrypted", &false);
+    restore_database(&pool, &app_dir, &source, &backup_path_str, &admin.id, event).await
+}
+
+// Restores the database and photo directories from an encrypted archive. Nothing
+// live is touched until the archive has been fully decrypted and verified.
+#[tauri::command]
+pub async fn restore_encrypted_backup(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    archive_path: String,
+    passphrase: String
+) -> AppResult<RestoreReport> {
+    let admin = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    require_recent_auth(&pool, &token).await?;
+    let _guard = RestoreGuard::acquire()?;
+
+    let app_dir = crate::workspace::data_dir(&app)?;
+    let source = PathBuf::from(&archive_path);
+    if !source.is_file() {
+        return Err(AppError::NotFound("Backup file not found".to_string()));
+    }
+
+    let staging = app_dir.join(RESTORE_STAGING_DIR);
+    let result = async {
+        let manifest = unpack_encrypted_backup(&source, &passphrase, &staging).await?;
+        let event = AuditEvent::new("DATABASE_RESTORED", "BACKUP", archive_path.as_str())
+            .context("encrypted", &true)
+            .context("backup_created_at", &manifest.created_at);
+        restore_database(&pool, &app_dir, &staging.join(ARCHIVE_DB_NAME), &archive_path, &admin.id, event).await
+    }
+    .await;
+
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    // Current photos are set aside rather than deleted
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    for dir in PHOTO_DIRS {
+        let live = app_dir.join(dir);
+        let staged = staging.join(dir);
+        if live.exists() {
+            fs::rename(&live, app_dir.join(format!("{}_before_restore_{}", dir, timestamp)))?;
+        }
+        if staged.is_dir() {
+            fs::rename(&staged, &live)?;
+        }
+    }
+    let _ = fs::remove_dir_all(&staging);
+
+    Ok(report)
+}
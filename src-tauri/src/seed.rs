@@ -1,9 +1,43 @@
 use crate::db::DbPool;
+use crate::audit_event::AuditEvent;
+use crate::workspace::InitialAdmin;
 use bcrypt::{hash, DEFAULT_COST};
 
+// A new workspace's only account, with the password chosen when it was created
+pub async fn seed_initial_admin(pool: &DbPool, admin: &InitialAdmin) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tx = pool.begin().await?;
+
+    let user_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id != ?")
+        .bind(crate::audit::SYSTEM_USER_ID)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if user_count > 0 {
+        return Ok(());
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO users (id, email, password_hash, role, active, must_change_password) VALUES (?, ?, ?, 'ADMIN', 1, 0)")
+        .bind(&id)
+        .bind(&admin.email)
+        .bind(&admin.password_hash)
+        .execute(&mut *tx)
+        .await?;
+
+    AuditEvent::new("CREATE_USER", "USER", &id)
+        .context("email", &admin.email)
+        .context("role", "ADMIN")
+        .log_tx(&mut tx, crate::audit::SYSTEM_USER_ID)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn seed_database(pool: &DbPool) -> Result<(), Box<dyn std::error::Error>> {
     // Check if users exist
-    let user_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+    let user_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE id != ?")
+        .bind(crate::audit::SYSTEM_USER_ID)
         .fetch_one(pool)
         .await?;
 
@@ -19,11 +53,11 @@ pub async fn seed_database(pool: &DbPool) -> Result<(), Box<dyn std::error::Erro
 
     sqlx::query(
         r#"
-        INSERT INTO users (id, email, password_hash, role, active)
+        INSERT INTO users (id, email, password_hash, role, active, must_change_password)
         VALUES 
-        ('user_admin', 'admin@local', ?, 'ADMIN', 1),
-        ('user_teacher', 'teacher@local', ?, 'TEACHER', 1),
-        ('user_finance', 'finance@local', ?, 'MANAGEMENT_FINANCE', 1)
+        ('user_admin', 'admin@local', ?, 'ADMIN', 1, 1),
+        ('user_teacher', 'teacher@local', ?, 'TEACHER', 1, 1),
+        ('user_finance', 'finance@local', ?, 'MANAGEMENT_FINANCE', 1, 1)
         "#
     )
     .bind(&hashed)
@@ -84,6 +118,13 @@ pub async fn seed_database(pool: &DbPool) -> Result<(), Box<dyn std::error::Erro
         "#
     ).execute(pool).await?;
 
+    // Link the demo teacher account to its staff record and homeroom class
+    sqlx::query("UPDATE users SET staff_id = 'staff_01' WHERE id = 'user_teacher'")
+        .execute(pool).await?;
+
+    sqlx::query("UPDATE classes SET homeroom_teacher_id = 'staff_01' WHERE id = 'class_10a'")
+        .execute(pool).await?;
+
     // 5. Seed Finance (Fee Plan + Invoices)
     sqlx::query(
         r#"
@@ -1,35 +1,34 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use tauri::{State, AppHandle, Manager};
+use tauri::{State, AppHandle};
 use crate::db::DbPool;
-use crate::auth::check_auth;
-use crate::models::Role;
-use crate::audit::log_audit;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, ensure_student_access, require_recent_auth};
+use crate::permissions::{PHOTOS_DELETE, PHOTOS_WRITE};
+use crate::audit_event::AuditEvent;
 
 // Prevent path traversal by ensuring the final path is within the intended directory
-fn get_safe_photo_dir(app: &AppHandle, sub_dir: &str) -> Result<PathBuf, String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+fn get_safe_photo_dir(app: &AppHandle, sub_dir: &str) -> AppResult<PathBuf> {
+    let app_dir = crate::workspace::data_dir(app)?;
     let photo_dir = app_dir.join(sub_dir);
     
     if !photo_dir.exists() {
-        if let Err(e) = fs::create_dir_all(&photo_dir) {
-            return Err(e.to_string());
-        }
+        fs::create_dir_all(&photo_dir)?;
     }
     
     Ok(photo_dir)
 }
 
-fn validate_image_file(file_path: &str) -> Result<(), String> {
+fn validate_image_file(file_path: &str) -> AppResult<()> {
     let path = Path::new(file_path);
     let extension = path.extension()
         .and_then(|e| e.to_str())
         .map(|e| e.to_lowercase())
-        .ok_or("Invalid file extension")?;
+        .ok_or_else(|| AppError::invalid_field("source_path", "Invalid file extension"))?;
         
     match extension.as_str() {
         "jpg" | "jpeg" | "png" => Ok(()),
-        _ => Err("Only JPG and PNG files are allowed".to_string()),
+        _ => Err(AppError::invalid_field("source_path", "Only JPG and PNG files are allowed")),
     }
 }
 
@@ -37,30 +36,32 @@ fn validate_image_file(file_path: &str) -> Result<(), String> {
 pub async fn save_photo(
     app: AppHandle,
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     entity_type: String, // "student" or "staff"
     entity_id: String, 
     source_path: String
-) -> Result<String, String> {
+) -> AppResult<String> {
     // Auth Check
-    check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher]).await?;
+    let user = check_permission(&pool, &token, PHOTOS_WRITE).await?;
 
-    if let Err(e) = validate_image_file(&source_path) {
-        return Err(e);
-    }
+    validate_image_file(&source_path)?;
     
     // Check file size (limit to 5MB)
-    let metadata = fs::metadata(&source_path).map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(&source_path)?;
     if metadata.len() > 5 * 1024 * 1024 {
-        return Err("File size exceeds 5MB limit".to_string());
+        return Err(AppError::invalid_field("source_path", "File size exceeds 5MB limit"));
     }
 
     let sub_dir = match entity_type.as_str() {
         "student" => "student_photos",
         "staff" => "staff_photos",
-        _ => return Err("Invalid entity type".to_string()),
+        _ => return Err(AppError::invalid_field("entity_type", "Invalid entity type")),
     };
 
+    if entity_type == "student" {
+        ensure_student_access(&pool, &user, &entity_id).await?;
+    }
+
     let photo_dir = get_safe_photo_dir(&app, sub_dir)?;
     
     // Determine extension from source
@@ -70,21 +71,17 @@ pub async fn save_photo(
     let target_filename = format!("{}.{}", entity_id, ext);
     let target_path = photo_dir.join(&target_filename);
 
-    if let Err(e) = fs::copy(&source_path, &target_path) {
-        return Err(e.to_string());
-    }
-
     let relative_path = format!("{}/{}", sub_dir, target_filename);
 
-    // Audit Log
-    let _ = log_audit(
-        &pool, 
-        &user_id, 
-        "SAVE_PHOTO", 
-        &entity_type.to_uppercase(), 
-        &entity_id, 
-        Some(&relative_path)
-    ).await;
+    // Audit Log, written first so a failed copy rolls it back
+    let mut tx = pool.begin().await?;
+    AuditEvent::new("SAVE_PHOTO", entity_type.to_uppercase(), &entity_id)
+        .context("photo_path", &relative_path)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+
+    fs::copy(&source_path, &target_path)?;
+    tx.commit().await?;
 
     // Ideally, we store "student_photos/student_123.jpg"
     Ok(relative_path)
@@ -94,45 +91,44 @@ pub async fn save_photo(
 pub async fn delete_photo(
     app: AppHandle,
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     entity_type: String,
     entity_id: String
-) -> Result<(), String> {
-    check_auth(&pool, &user_id, &[Role::Admin]).await?; // Only admin delete? Or Teacher? Let's say Admin.
+) -> AppResult<()> {
+    let user = check_permission(&pool, &token, PHOTOS_DELETE).await?;
+    require_recent_auth(&pool, &token).await?;
 
     let sub_dir = match entity_type.as_str() {
         "student" => "student_photos",
         "staff" => "staff_photos",
-        _ => return Err("Invalid entity type".to_string()),
+        _ => return Err(AppError::invalid_field("entity_type", "Invalid entity type")),
     };
 
     let photo_dir = get_safe_photo_dir(&app, sub_dir)?;
     
     // Try to find file with jpg or png
     let possible_exts = ["jpg", "jpeg", "png"];
-    let mut deleted = false;
-    for ext in possible_exts {
-        let filename = format!("{}.{}", entity_id, ext);
-        let path = photo_dir.join(&filename); // Join safe path
-        if path.exists() {
-            if let Err(e) = fs::remove_file(path) {
-                return Err(e.to_string());
-            }
-            deleted = true;
-        }
+    let existing: Vec<_> = possible_exts
+        .iter()
+        .map(|ext| photo_dir.join(format!("{}.{}", entity_id, ext))) // Join safe path
+        .filter(|path| path.exists())
+        .collect();
+
+    if existing.is_empty() {
+        return Ok(());
     }
-    
-    if deleted {
-        let _ = log_audit(
-            &pool, 
-            &user_id, 
-            "DELETE_PHOTO", 
-            &entity_type.to_uppercase(), 
-            &entity_id, 
-            None
-        ).await;
+
+    let mut tx = pool.begin().await?;
+    AuditEvent::new("DELETE_PHOTO", entity_type.to_uppercase(), &entity_id)
+        .log_tx(&mut tx, &user.id)
+        .await?;
+    tx.commit().await?;
+
+    // Only once the deletion is audited; a failure here is reported so it can be retried
+    for path in existing {
+        fs::remove_file(path)?;
     }
-    
+
     Ok(())
 }
 
@@ -140,18 +136,18 @@ pub async fn delete_photo(
 pub async fn get_photo_path(
     app: AppHandle,
     relative_path: String
-) -> Result<String, String> {
+) -> AppResult<String> {
     // Validate relative path prevents traversal
     if relative_path.contains("..") || relative_path.starts_with("/") || relative_path.contains("\\") {
-        return Err("Invalid path".to_string());
+        return Err(AppError::invalid_field("relative_path", "Invalid path"));
     }
     
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let app_dir = crate::workspace::data_dir(&app)?;
     let full_path = app_dir.join(&relative_path);
     
     if full_path.exists() {
         Ok(full_path.to_string_lossy().to_string())
     } else {
-        Err("File not found".to_string())
+        Err(AppError::NotFound("File not found".to_string()))
     }
 }
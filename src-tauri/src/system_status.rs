@@ -0,0 +1,177 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::encryption::EncryptionMode;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// Emitted with the new status whenever it changes
+pub const STATUS_EVENT: &str = "system-status";
+
+// Commands that work before the database is ready
+const ALWAYS_AVAILABLE: &[&str] = &[
+    "get_system_status",
+    "unlock_database",
+    "list_workspaces",
+    // These check for an admin session themselves once the database is ready
+    "create_workspace",
+    "select_workspace",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SystemStatus {
+    Initializing,
+    // The database failed its integrity check and is being salvaged or restored
+    Recovering,
+    // The database is encrypted and waits for its passphrase or key file
+    Locked { mode: EncryptionMode },
+    // The chosen workspace's folder cannot be reached, e.g. an external drive is missing
+    WorkspaceUnavailable { workspace: String, reason: String },
+    // The database file is being replaced, e.g. while it is re-encrypted
+    Maintenance,
+    Ready,
+    Failed { reason: String },
+}
+
+// Shared between managed state and the command gate, which runs outside any command
+#[derive(Clone)]
+pub struct SystemState(Arc<Mutex<SystemStatus>>);
+
+impl Default for SystemState {
+    fn default() -> Self {
+        SystemState(Arc::new(Mutex::new(SystemStatus::Initializing)))
+    }
+}
+
+impl SystemState {
+    pub fn get(&self) -> SystemStatus {
+        self.0.lock().map(|s| s.clone()).unwrap_or_else(|e| e.into_inner().clone())
+    }
+
+    pub fn set(&self, app: &AppHandle, status: SystemStatus) {
+        match self.0.lock() {
+            Ok(mut current) => *current = status.clone(),
+            Err(e) => *e.into_inner() = status.clone(),
+        }
+        if let Err(e) = app.emit(STATUS_EVENT, &status) {
+            eprintln!("Failed to emit system status: {}", e);
+        }
+    }
+
+    // Changes the status only if `expected` holds, so two callers cannot both act on it
+    pub fn set_if(&self, app: &AppHandle, expected: impl Fn(&SystemStatus) -> bool, status: SystemStatus) -> bool {
+        {
+            let mut current = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            if !expected(&current) {
+                return false;
+            }
+            *current = status.clone();
+        }
+        if let Err(e) = app.emit(STATUS_EVENT, &status) {
+            eprintln!("Failed to emit system status: {}", e);
+        }
+        true
+    }
+
+    fn check_ready(&self, command: &str) -> AppResult<()> {
+        if ALWAYS_AVAILABLE.contains(&command) {
+            return Ok(());
+        }
+        match self.get() {
+            SystemStatus::Ready => Ok(()),
+            SystemStatus::Initializing => Err(AppError::DatabaseNotReady("The database is still starting up. Please wait a moment.".to_string())),
+            SystemStatus::Recovering => Err(AppError::DatabaseNotReady("The database is being recovered. Please wait.".to_string())),
+            SystemStatus::Locked { .. } => Err(AppError::DatabaseNotReady("The database is locked. Unlock it to continue.".to_string())),
+            SystemStatus::WorkspaceUnavailable { reason, .. } => Err(AppError::DatabaseNotReady(reason)),
+            SystemStatus::Maintenance => Err(AppError::DatabaseNotReady("Database maintenance is in progress. Please wait.".to_string())),
+            SystemStatus::Failed { reason } => Err(AppError::DatabaseNotReady(format!("The database could not be opened: {}", reason))),
+        }
+    }
+}
+
+// Start-up failures are shown before anyone signs in, so raw database and file
+// errors stay in the log
+pub fn failed(e: &(dyn std::error::Error + 'static)) -> SystemStatus {
+    let reason = match e.downcast_ref::<AppError>() {
+        Some(e) => e.user_message(),
+        None => "An unexpected error occurred. Details were written to the application log.".to_string(),
+    };
+    SystemStatus::Failed { reason }
+}
+
+// Opens the database at start-up, or once it has been unlocked, and reports the outcome
+pub async fn open_database(app: AppHandle, state: SystemState) {
+    let workspace = match crate::workspace::activate(&app) {
+        Ok(workspace) => workspace,
+        Err(status) => {
+            state.set(&app, status);
+            return;
+        }
+    };
+    let app_dir = PathBuf::from(&workspace.path);
+
+    match crate::encryption::load_startup_key(&app_dir).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let mode = crate::encryption::load_config(&app_dir).map(|c| c.mode).unwrap_or(EncryptionMode::Passphrase);
+            state.set(&app, SystemStatus::Locked { mode });
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error reading database encryption settings: {}", e);
+            state.set(&app, failed(&e));
+            return;
+        }
+    }
+
+    let pool = match crate::db::init_db(&app, &app_dir).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("Error initializing database: {}", e);
+            state.set(&app, failed(e.as_ref()));
+            return;
+        }
+    };
+
+    // Commands hold the managed pool, so after a workspace switch it is repointed
+    // at the new database instead of being replaced
+    match app.try_state::<DbPool>() {
+        Some(live) => {
+            if let Err(e) = crate::db::adopt(&live, pool).await {
+                eprintln!("Error switching workspace: {}", e);
+                state.set(&app, failed(&e));
+                return;
+            }
+        }
+        None => {
+            tauri::async_runtime::spawn(crate::backup_schedule::run_scheduler(app.clone(), pool.clone()));
+            app.manage(pool);
+        }
+    }
+    println!("Opened workspace '{}' at {}", workspace.name, workspace.path);
+    state.set(&app, SystemStatus::Ready);
+}
+
+// Wraps the generated command handler. Until `init_db` finishes the pool is not
+// managed, and commands would otherwise fail with an opaque missing-state error.
+pub fn gate_commands(
+    state: SystemState,
+    handler: impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static
+) -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if let Err(e) = state.check_ready(invoke.message.command()) {
+            invoke.resolver.reject(e);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+// Needs no session: the UI checks it before showing the login screen
+#[tauri::command]
+pub async fn get_system_status(state: State<'_, SystemState>) -> AppResult<SystemStatus> {
+    Ok(state.get())
+}
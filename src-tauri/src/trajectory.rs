@@ -1,6 +1,8 @@
 use crate::db::DbPool;
-use crate::models::{Role};
-use crate::auth::check_auth;
+use crate::error::AppResult;
+use crate::auth::{check_permission, ensure_student_access};
+use crate::permissions::TRAJECTORY_READ;
+use crate::read_audit::{record_read, VIEW_TRAJECTORY};
 use tauri::State;
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
@@ -174,10 +176,11 @@ fn get_minimal_lever(state: &TrajectoryState) -> Vec<InterventionRecommendation>
 #[tauri::command]
 pub async fn compute_trajectory(
     pool: State<'_, DbPool>,
-    user_id: String,
+    token: String,
     student_id: String
-) -> Result<TrajectoryResult, String> {
-    check_auth(&pool, &user_id, &[Role::Admin, Role::Teacher]).await?;
+) -> AppResult<TrajectoryResult> {
+    let user = check_permission(&pool, &token, TRAJECTORY_READ).await?;
+    ensure_student_access(&pool, &user, &student_id).await?;
 
     let att_stats = sqlx::query_as::<_, AttendanceStats>(
         r#"
@@ -191,16 +194,14 @@ pub async fn compute_trajectory(
     )
     .bind(&student_id)
     .fetch_one(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     
     let score_stats = sqlx::query_as::<_, ScoreStats>(
         "SELECT AVG(score/max_score*100) as avg_score FROM assessments WHERE student_id = ?"
     )
     .bind(&student_id)
     .fetch_one(&*pool)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     let input = InputData {
         att_total: att_stats.total,
@@ -215,6 +216,8 @@ pub async fn compute_trajectory(
     let state = compute_state_vector(&input);
     let recommendations = get_minimal_lever(&state);
 
+    record_read(&pool, &user.id, VIEW_TRAJECTORY, "STUDENT", &student_id).await?;
+
     Ok(TrajectoryResult {
         state,
         recommendations,
@@ -0,0 +1,480 @@
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::auth::{check_permission, require_recent_auth};
+use crate::audit_event::AuditEvent;
+use crate::models::User;
+use crate::permissions::SYSTEM_BACKUP;
+use crate::system_status::{SystemState, SystemStatus};
+use tauri::{AppHandle, Manager, State};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+// Kept in the application's own data directory, never on a removable data root
+const REGISTRY_FILE: &str = "workspaces.json";
+// The database from before workspaces existed stays where it was, under this id
+const DEFAULT_WORKSPACE_ID: &str = "default";
+const DEFAULT_WORKSPACE_NAME: &str = "Default";
+// New workspaces go here unless a data root has been chosen
+const DEFAULT_DATA_ROOT: &str = "workspaces";
+const MAX_NAME_LENGTH: usize = 80;
+// Per-workspace secrets under the app's config directory, away from any data root
+const KEYS_DIR: &str = "workspace_keys";
+// The first admin of a new workspace, until its database is created
+const SETUP_FILE: &str = "workspace_setup.json";
+
+// One school's (or the training) database with its backups, photos and keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub created_at: String,
+    // Archived workspaces are hidden from the login screen; their files are kept
+    pub archived_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspaceRegistry {
+    data_root: Option<String>,
+    active: String,
+    workspaces: Vec<Workspace>,
+}
+
+// Only the password hash is written to disk
+#[derive(Serialize, Deserialize)]
+pub struct InitialAdmin {
+    pub email: String,
+    pub password_hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceList {
+    pub active: String,
+    pub data_root: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+// The workspace that is open, or being opened
+#[derive(Default)]
+pub struct ActiveWorkspace(RwLock<Option<Workspace>>);
+
+impl WorkspaceRegistry {
+    fn legacy(config_dir: &Path) -> Self {
+        WorkspaceRegistry {
+            data_root: None,
+            active: DEFAULT_WORKSPACE_ID.to_string(),
+            workspaces: vec![Workspace {
+                id: DEFAULT_WORKSPACE_ID.to_string(),
+                name: DEFAULT_WORKSPACE_NAME.to_string(),
+                path: config_dir.to_string_lossy().to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                archived_at: None,
+            }],
+        }
+    }
+
+    fn find(&self, id: &str) -> AppResult<&Workspace> {
+        self.workspaces
+            .iter()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound("Workspace not found".to_string()))
+    }
+
+    fn find_mut(&mut self, id: &str) -> AppResult<&mut Workspace> {
+        self.workspaces
+            .iter_mut()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound("Workspace not found".to_string()))
+    }
+
+    fn data_root(&self, config_dir: &Path) -> PathBuf {
+        match &self.data_root {
+            Some(root) => PathBuf::from(root),
+            None => config_dir.join(DEFAULT_DATA_ROOT),
+        }
+    }
+
+    fn list(&self, config_dir: &Path) -> WorkspaceList {
+        WorkspaceList {
+            active: self.active.clone(),
+            data_root: self.data_root(config_dir).to_string_lossy().to_string(),
+            workspaces: self.workspaces.clone(),
+        }
+    }
+}
+
+fn config_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    let dir = app.path().app_data_dir()?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn load_registry(config_dir: &Path) -> AppResult<WorkspaceRegistry> {
+    let path = config_dir.join(REGISTRY_FILE);
+    if !path.exists() {
+        return Ok(WorkspaceRegistry::legacy(config_dir));
+    }
+    serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|e| AppError::Internal(format!("invalid workspace registry: {}", e)))
+}
+
+fn save_registry(config_dir: &Path, registry: &WorkspaceRegistry) -> AppResult<()> {
+    let path = config_dir.join(REGISTRY_FILE);
+    let partial = config_dir.join(format!("{}.partial", REGISTRY_FILE));
+    fs::write(&partial, serde_json::to_vec_pretty(registry)?)?;
+    fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+// Audits a registry change that has just been saved. If the audit fails the
+// previous registry is put back, so the change is not left unaudited.
+async fn log_saved(config_dir: &Path, previous: &WorkspaceRegistry, event: AuditEvent, pool: &DbPool, actor_user_id: &str) -> AppResult<()> {
+    if let Err(e) = event.log(pool, actor_user_id).await {
+        if let Err(restore) = save_registry(config_dir, previous) {
+            eprintln!("Failed to restore the workspace registry: {}", restore);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+// The folder every command works in: database, backups, photos and key files
+pub fn data_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    active(app).map(|workspace| PathBuf::from(workspace.path))
+}
+
+fn active(app: &AppHandle) -> AppResult<Workspace> {
+    let active = app.state::<ActiveWorkspace>();
+    let workspace = active.0.read().unwrap_or_else(|e| e.into_inner()).clone();
+    workspace.ok_or_else(|| AppError::DatabaseNotReady("No workspace is open".to_string()))
+}
+
+// Where the open workspace's signing keys live. Kept out of the workspace folder so
+// that a copy of the folder, or write access to it, is not enough to forge them.
+pub fn key_dir(app: &AppHandle) -> AppResult<PathBuf> {
+    let dir = app.path().app_config_dir()?.join(KEYS_DIR).join(active(app)?.id);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// Only the workspace from before workspaces existed gets the demo accounts
+pub fn is_default(app: &AppHandle) -> AppResult<bool> {
+    Ok(active(app)?.id == DEFAULT_WORKSPACE_ID)
+}
+
+// The admin chosen when the workspace was created, if its database has not been
+// seeded yet
+pub fn initial_admin(app_dir: &Path) -> AppResult<Option<InitialAdmin>> {
+    let path = app_dir.join(SETUP_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    serde_json::from_slice(&fs::read(&path)?)
+        .map(Some)
+        .map_err(|e| AppError::Internal(format!("invalid workspace setup file: {}", e)))
+}
+
+pub fn clear_initial_admin(app_dir: &Path) -> AppResult<()> {
+    let path = app_dir.join(SETUP_FILE);
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+// Points data_dir at the workspace chosen last. A workspace on a drive that is not
+// connected is reported rather than recreated empty.
+pub fn activate(app: &AppHandle) -> Result<Workspace, SystemStatus> {
+    let unavailable = |workspace: &str, reason: String| SystemStatus::WorkspaceUnavailable {
+        workspace: workspace.to_string(),
+        reason,
+    };
+
+    let registry = config_dir(app)
+        .and_then(|dir| {
+            let registry = load_registry(&dir)?;
+            // First start with workspaces: record the existing database as the default
+            if !dir.join(REGISTRY_FILE).exists() {
+                save_registry(&dir, &registry)?;
+            }
+            Ok(registry)
+        })
+        .map_err(|e| crate::system_status::failed(&e))?;
+    let workspace = registry.find(&registry.active).map_err(|e| unavailable(&registry.active, e.to_string()))?;
+    if !Path::new(&workspace.path).is_dir() {
+        return Err(unavailable(
+            &workspace.name,
+            format!("The folder {} cannot be found. Connect the drive it is on, or open another workspace.", workspace.path)
+        ));
+    }
+
+    let active = app.state::<ActiveWorkspace>();
+    *active.0.write().unwrap_or_else(|e| e.into_inner()) = Some(workspace.clone());
+    Ok(workspace.clone())
+}
+
+fn validate_name(registry: &WorkspaceRegistry, name: &str) -> AppResult<()> {
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::invalid_field("name", format!("Name must be 1 to {} characters", MAX_NAME_LENGTH)));
+    }
+    if registry.workspaces.iter().any(|w| w.name.eq_ignore_ascii_case(name)) {
+        return Err(AppError::invalid_field("name", "A workspace with this name already exists"));
+    }
+    Ok(())
+}
+
+// Folder-safe id derived from the name, unique among registered workspaces
+fn unique_id(registry: &WorkspaceRegistry, name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = if slug.is_empty() { "workspace".to_string() } else { slug };
+
+    let mut id = base.clone();
+    let mut n = 2;
+    while registry.workspaces.iter().any(|w| w.id == id) {
+        id = format!("{}-{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+// Before a database is open, i.e. on the first-run and workspace-picker screens,
+// no one can sign in, so these commands need no session. Once one is open they
+// replace or add to it, and need an administrator who has just re-entered their
+// password.
+async fn authorize(app: &AppHandle, status: &SystemStatus, token: Option<&str>) -> AppResult<Option<User>> {
+    match status {
+        SystemStatus::Ready => {
+            let pool = app
+                .try_state::<DbPool>()
+                .ok_or_else(|| AppError::DatabaseNotReady("The database is still starting up. Please wait a moment.".to_string()))?;
+            let token = token.ok_or(AppError::Unauthenticated)?;
+            let admin = check_permission(&pool, token, SYSTEM_BACKUP).await?;
+            require_recent_auth(&pool, token).await?;
+            Ok(Some(admin))
+        }
+        SystemStatus::Locked { .. } | SystemStatus::Failed { .. } | SystemStatus::WorkspaceUnavailable { .. } => Ok(None),
+        _ => Err(AppError::Conflict("The database is busy. Try again in a moment.".to_string())),
+    }
+}
+
+// Needs no session: the login screen lists workspaces before anyone signs in
+#[tauri::command]
+pub async fn list_workspaces(app: AppHandle) -> AppResult<WorkspaceList> {
+    let config_dir = config_dir(&app)?;
+    Ok(load_registry(&config_dir)?.list(&config_dir))
+}
+
+// Creates a folder under the data root holding the new workspace's first admin.
+// The database is created, migrated and given that account the first time the
+// workspace is opened.
+#[tauri::command]
+pub async fn create_workspace(
+    app: AppHandle,
+    system: State<'_, SystemState>,
+    token: Option<String>,
+    name: String,
+    admin_email: String,
+    admin_password: String
+) -> AppResult<Workspace> {
+    let user = authorize(&app, &system.get(), token.as_deref()).await?;
+
+    let config_dir = config_dir(&app)?;
+    let mut registry = load_registry(&config_dir)?;
+
+    let name = name.trim().to_string();
+    validate_name(&registry, &name)?;
+    let admin_email = admin_email.trim().to_lowercase();
+    crate::commands::users::validate_email(&admin_email)?;
+    crate::password::enforce_default_policy(&admin_email, &admin_password)?;
+
+    let data_root = registry.data_root(&config_dir);
+    if registry.data_root.is_some() && !data_root.is_dir() {
+        return Err(AppError::invalid("The data folder cannot be found. Connect the drive it is on first."));
+    }
+    let id = unique_id(&registry, &name);
+    let path = data_root.join(&id);
+    if path.exists() {
+        return Err(AppError::Conflict(format!("The folder {} already exists", path.display())));
+    }
+
+    let admin = InitialAdmin {
+        password_hash: bcrypt::hash(&admin_password, bcrypt::DEFAULT_COST)?,
+        email: admin_email,
+    };
+    let workspace = Workspace {
+        id,
+        name,
+        path: path.to_string_lossy().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        archived_at: None,
+    };
+    let previous = registry.clone();
+    registry.workspaces.push(workspace.clone());
+
+    // Audited only once the workspace is registered
+    let created = async {
+        fs::create_dir_all(&path)?;
+        fs::write(path.join(SETUP_FILE), serde_json::to_vec_pretty(&admin)?)?;
+        save_registry(&config_dir, &registry)?;
+        if let (Some(user), Some(pool)) = (&user, app.try_state::<DbPool>()) {
+            let event = AuditEvent::new("CREATE_WORKSPACE", "WORKSPACE", workspace.id.as_str())
+                .context("name", &workspace.name)
+                .context("path", &workspace.path)
+                .context("admin_email", &admin.email);
+            log_saved(&config_dir, &previous, event, &pool, &user.id).await?;
+        }
+        Ok(())
+    };
+    if let Err(e) = created.await {
+        if let Err(cleanup) = fs::remove_dir_all(&path) {
+            eprintln!("Failed to remove workspace folder {}: {}", path.display(), cleanup);
+        }
+        return Err(e);
+    }
+
+    Ok(workspace)
+}
+
+// Closes the open workspace and opens another. Sessions belong to a workspace's
+// database, so everyone is signed out; the frontend returns to the login screen.
+#[tauri::command]
+pub async fn select_workspace(
+    app: AppHandle,
+    system: State<'_, SystemState>,
+    token: Option<String>,
+    workspace_id: String
+) -> AppResult<()> {
+    let status = system.get();
+    let user = authorize(&app, &status, token.as_deref()).await?;
+
+    let config_dir = config_dir(&app)?;
+    let mut registry = load_registry(&config_dir)?;
+    let workspace = registry.find(&workspace_id)?.clone();
+    if workspace.archived_at.is_some() {
+        return Err(AppError::invalid("The workspace is archived. Unarchive it first."));
+    }
+    if registry.active == workspace.id && matches!(status, SystemStatus::Ready) {
+        return Ok(());
+    }
+
+    if let (Some(user), Some(pool)) = (&user, app.try_state::<DbPool>()) {
+        AuditEvent::new("SWITCH_WORKSPACE", "WORKSPACE", workspace.id.as_str())
+            .context("from", &registry.active)
+            .context("name", &workspace.name)
+            .log(&pool, &user.id)
+            .await?;
+    }
+
+    // Only from the state the caller was authorized in; opening is in progress otherwise
+    let signed_in = user.is_some();
+    let switchable = |s: &SystemStatus| match s {
+        SystemStatus::Ready => signed_in,
+        SystemStatus::Locked { .. } | SystemStatus::Failed { .. } | SystemStatus::WorkspaceUnavailable { .. } => !signed_in,
+        _ => false,
+    };
+    if !system.set_if(&app, switchable, SystemStatus::Initializing) {
+        return Err(AppError::Conflict("The database is busy. Try again in a moment.".to_string()));
+    }
+
+    registry.active = workspace.id;
+    if let Err(e) = save_registry(&config_dir, &registry) {
+        system.set(&app, crate::system_status::failed(&e));
+        return Err(e);
+    }
+
+    tauri::async_runtime::spawn(crate::system_status::open_database(app.clone(), system.inner().clone()));
+    Ok(())
+}
+
+async fn set_archived(app: &AppHandle, pool: &DbPool, token: &str, workspace_id: &str, archive: bool) -> AppResult<WorkspaceList> {
+    let admin = check_permission(pool, token, SYSTEM_BACKUP).await?;
+    require_recent_auth(pool, token).await?;
+
+    let config_dir = config_dir(app)?;
+    let mut registry = load_registry(&config_dir)?;
+    let previous = registry.clone();
+    if archive && registry.active == workspace_id {
+        return Err(AppError::invalid("The open workspace cannot be archived. Switch to another one first."));
+    }
+
+    let workspace = registry.find_mut(workspace_id)?;
+    if workspace.archived_at.is_some() == archive {
+        return Err(AppError::Conflict(if archive {
+            "The workspace is already archived".to_string()
+        } else {
+            "The workspace is not archived".to_string()
+        }));
+    }
+    workspace.archived_at = archive.then(|| chrono::Utc::now().to_rfc3339());
+    let event = AuditEvent::new(if archive { "ARCHIVE_WORKSPACE" } else { "UNARCHIVE_WORKSPACE" }, "WORKSPACE", workspace_id)
+        .context("name", &workspace.name)
+        .context("path", &workspace.path);
+    save_registry(&config_dir, &registry)?;
+
+    log_saved(&config_dir, &previous, event, pool, &admin.id).await?;
+    Ok(registry.list(&config_dir))
+}
+
+#[tauri::command]
+pub async fn archive_workspace(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    workspace_id: String
+) -> AppResult<WorkspaceList> {
+    set_archived(&app, &pool, &token, &workspace_id, true).await
+}
+
+#[tauri::command]
+pub async fn unarchive_workspace(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    workspace_id: String
+) -> AppResult<WorkspaceList> {
+    set_archived(&app, &pool, &token, &workspace_id, false).await
+}
+
+// Where new workspaces are created, e.g. on an external drive. Existing workspaces
+// stay where they are. None goes back to the application's data folder.
+#[tauri::command]
+pub async fn set_data_root(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    token: String,
+    data_root: Option<String>
+) -> AppResult<WorkspaceList> {
+    let admin = check_permission(&pool, &token, SYSTEM_BACKUP).await?;
+    require_recent_auth(&pool, &token).await?;
+
+    let data_root = data_root.map(|root| root.trim().to_string()).filter(|root| !root.is_empty());
+    if let Some(root) = &data_root {
+        let path = Path::new(root);
+        if !path.is_absolute() || !path.is_dir() {
+            return Err(AppError::invalid_field("data_root", "Choose an existing folder"));
+        }
+    }
+
+    let config_dir = config_dir(&app)?;
+    let mut registry = load_registry(&config_dir)?;
+    let previous = registry.clone();
+    registry.data_root = data_root;
+    save_registry(&config_dir, &registry)?;
+
+    let event = AuditEvent::new("SET_DATA_ROOT", "WORKSPACE", "data_root")
+        .context("from", &previous.data_root(&config_dir))
+        .context("to", &registry.data_root(&config_dir));
+    log_saved(&config_dir, &previous, event, &pool, &admin.id).await?;
+
+    Ok(registry.list(&config_dir))
+}
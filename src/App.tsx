@@ -8,46 +8,55 @@ import { FinanceDashboard } from './pages/FinanceDashboard';
 import { TrajectoryView } from './pages/TrajectoryView';
 import { AuditLogPage } from './pages/AuditLogPage';
 import { SettingsPage } from './pages/SettingsPage';
+import { ChangePasswordPage } from './pages/ChangePasswordPage';
+import { TwoFactorSetupPage } from './pages/TwoFactorSetupPage';
 import { Toaster } from '@/components/ui/toaster';
+import { SystemStatusGate } from '@/components/SystemStatusGate';
 
 function ProtectedRoute({ children }: { children: React.ReactNode }) {
     const { user, isLoading } = useAuth();
     if (isLoading) return <div>Loading...</div>;
     if (!user) return <Navigate to="/login" replace />;
+    if (user.must_change_password) return <Navigate to="/change-password" replace />;
+    if (user.two_factor_setup_required) return <Navigate to="/two-factor-setup" replace />;
     return <>{children}</>;
 }
 
 function App() {
     return (
-        <AuthProvider>
-            <BrowserRouter>
-                <Routes>
-                    <Route path="/login" element={<LoginPage />} />
+        <SystemStatusGate>
+            <AuthProvider>
+                <BrowserRouter>
+                    <Routes>
+                        <Route path="/login" element={<LoginPage />} />
+                        <Route path="/change-password" element={<ChangePasswordPage />} />
+                        <Route path="/two-factor-setup" element={<TwoFactorSetupPage />} />
                     
-                    <Route path="/" element={
-                        <ProtectedRoute>
-                            <DashboardLayout />
-                        </ProtectedRoute>
-                    }>
-                        <Route index element={<Navigate to="/dashboard" replace />} />
-                        <Route path="dashboard" element={<div className="p-8"><h1 className="text-2xl font-bold">Dashboard</h1><p>Welcome to Resonance School Engine.</p></div>} />
+                        <Route path="/" element={
+                            <ProtectedRoute>
+                                <DashboardLayout />
+                            </ProtectedRoute>
+                        }>
+                            <Route index element={<Navigate to="/dashboard" replace />} />
+                            <Route path="dashboard" element={<div className="p-8"><h1 className="text-2xl font-bold">Dashboard</h1><p>Welcome to Resonance School Engine.</p></div>} />
                         
-                        <Route path="students" element={<div className="p-8">Student List Placeholder (Use search or click demo link)</div>} />
-                        <Route path="students/:id" element={<StudentProfilePage />} />
+                            <Route path="students" element={<div className="p-8">Student List Placeholder (Use search or click demo link)</div>} />
+                            <Route path="students/:id" element={<StudentProfilePage />} />
                         
-                        <Route path="classes/*" element={<div className="p-8">Classes Module</div>} />
+                            <Route path="classes/*" element={<div className="p-8">Classes Module</div>} />
                         
-                        <Route path="finance" element={<FinanceDashboard />} />
+                            <Route path="finance" element={<FinanceDashboard />} />
                         
-                        <Route path="trajectory" element={<TrajectoryView />} />
+                            <Route path="trajectory" element={<TrajectoryView />} />
                         
-                        <Route path="audit" element={<AuditLogPage />} />
-                        <Route path="settings" element={<SettingsPage />} />
-                    </Route>
-                </Routes>
-                <Toaster />
-            </BrowserRouter>
-        </AuthProvider>
+                            <Route path="audit" element={<AuditLogPage />} />
+                            <Route path="settings" element={<SettingsPage />} />
+                        </Route>
+                    </Routes>
+                    <Toaster />
+                </BrowserRouter>
+            </AuthProvider>
+        </SystemStatusGate>
     );
 }
 
@@ -0,0 +1,220 @@
+import { useCallback, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table';
+import { Badge } from '@/components/ui/badge';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import { useAuth, isReauthRequired } from '@/contexts/AuthContext';
+import { useToast } from '@/components/ui/use-toast';
+import { errorMessage } from '@/lib/errors';
+
+export interface BackupRecord {
+    id: number;
+    kind: 'AUTO' | 'MANUAL';
+    file_name: string;
+    file_path: string;
+    size_bytes: number;
+    sha256: string;
+    schema_version: number | null;
+    encrypted: boolean;
+    created_at: string;
+    deleted_at: string | null;
+}
+
+interface BackupInspection {
+    record: BackupRecord;
+    file_exists: boolean;
+    checksum_matches: boolean;
+    integrity_ok: boolean | null;
+    schema_version: number | null;
+    problem: string | null;
+}
+
+interface BackupSchedule {
+    interval_hours: number | null;
+    keep_daily: number;
+    keep_weekly: number;
+    keep_monthly: number;
+}
+
+const formatSize = (bytes: number) =>
+    bytes >= 1024 * 1024 ? `${(bytes / 1024 / 1024).toFixed(1)} MB` : `${Math.ceil(bytes / 1024)} KB`;
+
+// `refreshKey` changes whenever the parent creates a backup
+export function BackupCatalog({ refreshKey }: { refreshKey?: string }) {
+    const { user, confirmReauth } = useAuth();
+    const { toast } = useToast();
+    const [backups, setBackups] = useState<BackupRecord[]>([]);
+    const [schedule, setSchedule] = useState<BackupSchedule | null>(null);
+    const [inspections, setInspections] = useState<Record<number, BackupInspection>>({});
+    const [showDeleted, setShowDeleted] = useState(false);
+    const [accountPassword, setAccountPassword] = useState('');
+
+    const load = useCallback(async () => {
+        if (!user) return;
+        try {
+            const [list, current] = await Promise.all([
+                invoke<BackupRecord[]>('list_backups', { token: user.token }),
+                invoke<BackupSchedule>('get_backup_schedule', { token: user.token }),
+            ]);
+            setBackups(list);
+            setSchedule(current);
+        } catch (e) {
+            toast({ title: 'Could not load backups', description: errorMessage(e), variant: 'destructive' });
+        }
+    }, [user, toast]);
+
+    useEffect(() => { load(); }, [load, refreshKey]);
+
+    // Sensitive changes ask for the account password once, then retry
+    const withReauth = async <T,>(action: () => Promise<T>): Promise<T> => {
+        try {
+            return await action();
+        } catch (e) {
+            if (!isReauthRequired(e)) throw e;
+            await confirmReauth(accountPassword);
+            return await action();
+        }
+    };
+
+    const handleSaveSchedule = async () => {
+        if (!user || !schedule) return;
+        try {
+            const saved = await withReauth(() =>
+                invoke<BackupSchedule>('set_backup_schedule', { token: user.token, schedule }));
+            setSchedule(saved);
+            setAccountPassword('');
+            toast({ title: 'Backup Schedule Saved' });
+        } catch (e) {
+            toast({ title: 'Could not save schedule', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const handleInspect = async (backup: BackupRecord) => {
+        if (!user) return;
+        try {
+            const inspection = await invoke<BackupInspection>('inspect_backup', { token: user.token, backupId: backup.id });
+            setInspections((prev) => ({ ...prev, [backup.id]: inspection }));
+        } catch (e) {
+            toast({ title: 'Inspection Failed', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const handleDelete = async (backup: BackupRecord) => {
+        if (!user || !window.confirm(`Delete ${backup.file_name}? The file cannot be recovered.`)) return;
+        try {
+            await withReauth(() => invoke('delete_backup', { token: user.token, backupId: backup.id }));
+            setAccountPassword('');
+            await load();
+        } catch (e) {
+            toast({ title: 'Delete Failed', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const setNumber = (field: keyof BackupSchedule) => (e: React.ChangeEvent<HTMLInputElement>) =>
+        setSchedule((prev) => prev && {
+            ...prev,
+            [field]: e.target.value === '' && field === 'interval_hours' ? null : Number(e.target.value),
+        });
+
+    const visible = showDeleted ? backups : backups.filter((b) => !b.deleted_at);
+
+    return (
+        <div className="space-y-4">
+            {schedule && (
+                <div className="space-y-2">
+                    <Label>Automatic backups</Label>
+                    <p className="text-sm text-muted-foreground">
+                        A backup is taken at every start-up and, while the app is open, every few hours. Older automatic
+                        backups are thinned out to the newest one per day, week and month. Manual backups are kept until deleted.
+                    </p>
+                    <div className="grid grid-cols-2 md:grid-cols-4 gap-2">
+                        <div>
+                            <Label htmlFor="interval-hours" className="text-xs">Every (hours, empty = off)</Label>
+                            <Input id="interval-hours" type="number" min={1} value={schedule.interval_hours ?? ''} onChange={setNumber('interval_hours')} />
+                        </div>
+                        <div>
+                            <Label htmlFor="keep-daily" className="text-xs">Daily backups kept</Label>
+                            <Input id="keep-daily" type="number" min={0} value={schedule.keep_daily} onChange={setNumber('keep_daily')} />
+                        </div>
+                        <div>
+                            <Label htmlFor="keep-weekly" className="text-xs">Weekly backups kept</Label>
+                            <Input id="keep-weekly" type="number" min={0} value={schedule.keep_weekly} onChange={setNumber('keep_weekly')} />
+                        </div>
+                        <div>
+                            <Label htmlFor="keep-monthly" className="text-xs">Monthly backups kept</Label>
+                            <Input id="keep-monthly" type="number" min={0} value={schedule.keep_monthly} onChange={setNumber('keep_monthly')} />
+                        </div>
+                    </div>
+                    <div className="flex items-center gap-2">
+                        <Input
+                            type="password"
+                            placeholder="Your account password (to confirm changes)"
+                            value={accountPassword}
+                            onChange={(e) => setAccountPassword(e.target.value)}
+                        />
+                        <Button variant="secondary" onClick={handleSaveSchedule}>Save Schedule</Button>
+                    </div>
+                </div>
+            )}
+
+            <div className="flex items-center justify-between">
+                <Label>Backup history</Label>
+                <label className="flex items-center gap-2 text-sm text-muted-foreground">
+                    <input type="checkbox" checked={showDeleted} onChange={(e) => setShowDeleted(e.target.checked)} />
+                    Show deleted
+                </label>
+            </div>
+            {visible.length === 0 ? (
+                <div className="text-sm text-muted-foreground">No backups yet.</div>
+            ) : (
+                <Table>
+                    <TableHeader>
+                        <TableRow>
+                            <TableHead>Created</TableHead>
+                            <TableHead>Kind</TableHead>
+                            <TableHead>File</TableHead>
+                            <TableHead>Size</TableHead>
+                            <TableHead>Status</TableHead>
+                            <TableHead />
+                        </TableRow>
+                    </TableHeader>
+                    <TableBody>
+                        {visible.map((backup) => {
+                            const inspection = inspections[backup.id];
+                            return (
+                                <TableRow key={backup.id}>
+                                    <TableCell className="font-mono text-xs">{backup.created_at}</TableCell>
+                                    <TableCell>
+                                        <Badge variant="outline">{backup.kind}</Badge>
+                                        {backup.encrypted && <Badge variant="secondary" className="ml-1">Encrypted</Badge>}
+                                    </TableCell>
+                                    <TableCell className="text-xs break-all max-w-xs">{backup.file_path}</TableCell>
+                                    <TableCell className="text-xs">{formatSize(backup.size_bytes)}</TableCell>
+                                    <TableCell className="text-xs">
+                                        {backup.deleted_at
+                                            ? `Deleted ${backup.deleted_at}`
+                                            : inspection
+                                                ? (inspection.problem
+                                                    ? <span className="text-red-600">{inspection.problem}</span>
+                                                    : <span className="text-green-700">Verified</span>)
+                                                : null}
+                                    </TableCell>
+                                    <TableCell className="space-x-2 whitespace-nowrap">
+                                        {!backup.deleted_at && (
+                                            <>
+                                                <Button variant="outline" size="sm" onClick={() => handleInspect(backup)}>Verify</Button>
+                                                <Button variant="destructive" size="sm" onClick={() => handleDelete(backup)}>Delete</Button>
+                                            </>
+                                        )}
+                                    </TableCell>
+                                </TableRow>
+                            );
+                        })}
+                    </TableBody>
+                </Table>
+            )}
+        </div>
+    );
+}
@@ -0,0 +1,147 @@
+import { useCallback, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Badge } from '@/components/ui/badge';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import { useAuth, isReauthRequired } from '@/contexts/AuthContext';
+import { useToast } from '@/components/ui/use-toast';
+import { errorMessage } from '@/lib/errors';
+
+export type EncryptionMode = 'NONE' | 'PASSPHRASE' | 'KEY_FILE';
+
+interface EncryptionStatus {
+    available: boolean;
+    cipher_version: string | null;
+    encrypted: boolean;
+    mode: EncryptionMode;
+    key_file: string | null;
+}
+
+// Encrypts the database file in place, or changes its key
+export function DatabaseEncryption() {
+    const { user, confirmReauth } = useAuth();
+    const { toast } = useToast();
+    const [status, setStatus] = useState<EncryptionStatus | null>(null);
+    const [mode, setMode] = useState<'PASSPHRASE' | 'KEY_FILE'>('PASSPHRASE');
+    const [passphrase, setPassphrase] = useState('');
+    const [confirmation, setConfirmation] = useState('');
+    const [keyFile, setKeyFile] = useState('');
+    const [accountPassword, setAccountPassword] = useState('');
+    const [busy, setBusy] = useState(false);
+
+    const load = useCallback(async () => {
+        if (!user) return;
+        try {
+            setStatus(await invoke<EncryptionStatus>('get_database_encryption', { token: user.token }));
+        } catch (e) {
+            toast({ title: 'Could not load encryption settings', description: errorMessage(e), variant: 'destructive' });
+        }
+    }, [user, toast]);
+
+    useEffect(() => { load(); }, [load]);
+
+    const handleApply = async () => {
+        if (!user || !status) return;
+        if (mode === 'PASSPHRASE' && passphrase !== confirmation) {
+            toast({ title: 'Passphrases do not match', variant: 'destructive' });
+            return;
+        }
+        const command = status.encrypted ? 'rekey_database' : 'encrypt_database';
+        const args = {
+            token: user.token,
+            passphrase: mode === 'PASSPHRASE' ? passphrase : null,
+            keyFile: mode === 'KEY_FILE' ? keyFile : null,
+        };
+
+        setBusy(true);
+        try {
+            let updated: EncryptionStatus;
+            try {
+                updated = await invoke<EncryptionStatus>(command, args);
+            } catch (e) {
+                if (!isReauthRequired(e)) throw e;
+                await confirmReauth(accountPassword);
+                updated = await invoke<EncryptionStatus>(command, args);
+            }
+            setStatus(updated);
+            setPassphrase('');
+            setConfirmation('');
+            setAccountPassword('');
+            toast({
+                title: status.encrypted ? 'Database Key Changed' : 'Database Encrypted',
+                description: mode === 'PASSPHRASE'
+                    ? 'The passphrase will be asked for at every start-up. It cannot be recovered if it is lost.'
+                    : 'Keep a copy of the key file somewhere safe. The database cannot be opened without it.',
+            });
+        } catch (e) {
+            toast({ title: 'Encryption Failed', description: errorMessage(e), variant: 'destructive' });
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    if (!status) return null;
+
+    if (!status.available) {
+        return <div className="text-sm text-muted-foreground">This build of the application does not support database encryption.</div>;
+    }
+
+    return (
+        <div className="space-y-4">
+            <div className="flex items-center gap-2 text-sm">
+                {status.encrypted
+                    ? <Badge variant="secondary">Encrypted</Badge>
+                    : <Badge variant="outline">Not encrypted</Badge>}
+                {status.encrypted && status.mode === 'KEY_FILE' && <span className="text-xs break-all">Key file: {status.key_file}</span>}
+                {status.encrypted && status.mode === 'PASSPHRASE' && <span className="text-xs">Unlocked with a passphrase at start-up</span>}
+            </div>
+            <p className="text-sm text-muted-foreground">
+                {status.encrypted
+                    ? 'Changing the key re-encrypts the database. Backups taken under the old key can only be restored while that key is in use.'
+                    : 'Encrypting protects the database file if the computer is lost or shared. Backups taken before encrypting stay unencrypted; delete them once a new backup has been taken.'}
+            </p>
+
+            <div className="flex gap-4 text-sm">
+                <label className="flex items-center gap-2">
+                    <input type="radio" checked={mode === 'PASSPHRASE'} onChange={() => setMode('PASSPHRASE')} />
+                    Passphrase
+                </label>
+                <label className="flex items-center gap-2">
+                    <input type="radio" checked={mode === 'KEY_FILE'} onChange={() => setMode('KEY_FILE')} />
+                    Key file
+                </label>
+            </div>
+
+            {mode === 'PASSPHRASE' ? (
+                <div className="grid gap-2 md:grid-cols-2">
+                    <div>
+                        <Label htmlFor="db-passphrase" className="text-xs">Passphrase (at least 12 characters)</Label>
+                        <Input id="db-passphrase" type="password" value={passphrase} onChange={(e) => setPassphrase(e.target.value)} />
+                    </div>
+                    <div>
+                        <Label htmlFor="db-passphrase-confirm" className="text-xs">Confirm passphrase</Label>
+                        <Input id="db-passphrase-confirm" type="password" value={confirmation} onChange={(e) => setConfirmation(e.target.value)} />
+                    </div>
+                </div>
+            ) : (
+                <div>
+                    <Label htmlFor="db-key-file" className="text-xs">Key file path (created if it does not exist, e.g. on a USB drive)</Label>
+                    <Input id="db-key-file" placeholder="E:\resonance.key" value={keyFile} onChange={(e) => setKeyFile(e.target.value)} />
+                </div>
+            )}
+
+            <div className="flex items-center gap-2">
+                <Input
+                    type="password"
+                    placeholder="Your account password (to confirm changes)"
+                    value={accountPassword}
+                    onChange={(e) => setAccountPassword(e.target.value)}
+                />
+                <Button variant="secondary" disabled={busy} onClick={handleApply}>
+                    {busy ? 'Working...' : status.encrypted ? 'Change Key' : 'Encrypt Database'}
+                </Button>
+            </div>
+        </div>
+    );
+}
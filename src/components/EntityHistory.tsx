@@ -0,0 +1,82 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Badge } from '@/components/ui/badge';
+import { useAuth } from '@/contexts/AuthContext';
+import { errorMessage } from '@/lib/errors';
+
+export interface FieldChange {
+    field: string;
+    before: unknown;
+    after: unknown;
+}
+
+interface HistoryEntry {
+    id: number;
+    actor_user_id: string;
+    actor_email: string | null;
+    action: string;
+    created_at: string;
+    changes: FieldChange[];
+    context: Record<string, unknown>;
+}
+
+interface EntityHistoryProps {
+    entityType: string;
+    entityId: string;
+}
+
+const formatValue = (value: unknown) =>
+    value === null || value === undefined ? '(empty)' : typeof value === 'string' ? value : JSON.stringify(value);
+
+export function ChangeList({ changes }: { changes: FieldChange[] }) {
+    return (
+        <ul className="space-y-1 text-xs">
+            {changes.map((c) => (
+                <li key={c.field}>
+                    <span className="font-semibold">{c.field}</span>:{' '}
+                    <span className="text-red-600 line-through">{formatValue(c.before)}</span>{' → '}
+                    <span className="text-green-700">{formatValue(c.after)}</span>
+                </li>
+            ))}
+        </ul>
+    );
+}
+
+export function EntityHistory({ entityType, entityId }: EntityHistoryProps) {
+    const { user } = useAuth();
+    const [entries, setEntries] = useState<HistoryEntry[]>([]);
+    const [loading, setLoading] = useState(true);
+    const [error, setError] = useState('');
+
+    useEffect(() => {
+        if (!user) return;
+        setLoading(true);
+        invoke<HistoryEntry[]>('get_entity_history', { token: user.token, entityType, entityId })
+            .then((data) => { setEntries(data); setError(''); })
+            .catch((e) => setError(errorMessage(e)))
+            .finally(() => setLoading(false));
+    }, [user, entityType, entityId]);
+
+    if (loading) return <div className="text-sm text-muted-foreground">Loading history...</div>;
+    if (error) return <div className="text-sm text-red-500">{error}</div>;
+    if (entries.length === 0) return <div className="text-sm text-muted-foreground">No recorded changes.</div>;
+
+    return (
+        <ol className="space-y-4">
+            {entries.map((entry) => (
+                <li key={entry.id} className="border-l-2 pl-4">
+                    <div className="flex items-center gap-2 text-sm">
+                        <Badge variant="outline">{entry.action}</Badge>
+                        <span className="text-muted-foreground">
+                            {entry.created_at} by {entry.actor_email ?? entry.actor_user_id}
+                        </span>
+                    </div>
+                    {entry.changes.length > 0 && <ChangeList changes={entry.changes} />}
+                    {Object.keys(entry.context).length > 0 && (
+                        <div className="text-xs text-muted-foreground font-mono">{JSON.stringify(entry.context)}</div>
+                    )}
+                </li>
+            ))}
+        </ol>
+    );
+}
@@ -0,0 +1,88 @@
+import { useCallback, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table';
+import { Button } from '@/components/ui/button';
+import { useAuth } from '@/contexts/AuthContext';
+import { useToast } from '@/components/ui/use-toast';
+import { errorMessage } from '@/lib/errors';
+
+interface ForeignKeyViolation {
+    id: number;
+    table_name: string;
+    row_id: number | null;
+    parent_table: string;
+    constraint_index: number;
+    detected_at: string;
+    resolved_at: string | null;
+}
+
+// Rows that point at a record that no longer exists. They were written before
+// foreign keys were enforced and are listed until they are fixed.
+export function ForeignKeyViolations() {
+    const { user } = useAuth();
+    const { toast } = useToast();
+    const [violations, setViolations] = useState<ForeignKeyViolation[] | null>(null);
+    const [checking, setChecking] = useState(false);
+
+    const load = useCallback(async () => {
+        if (!user) return;
+        try {
+            setViolations(await invoke<ForeignKeyViolation[]>('get_foreign_key_violations', { token: user.token }));
+        } catch (e) {
+            toast({ title: 'Could not load integrity report', description: errorMessage(e), variant: 'destructive' });
+        }
+    }, [user, toast]);
+
+    useEffect(() => { load(); }, [load]);
+
+    const handleCheck = async () => {
+        if (!user) return;
+        setChecking(true);
+        try {
+            setViolations(await invoke<ForeignKeyViolation[]>('check_foreign_keys', { token: user.token }));
+        } catch (e) {
+            toast({ title: 'Check Failed', description: errorMessage(e), variant: 'destructive' });
+        } finally {
+            setChecking(false);
+        }
+    };
+
+    if (!violations) return null;
+
+    return (
+        <div className="space-y-4">
+            <div className="flex items-center justify-between">
+                <p className="text-sm text-muted-foreground">
+                    {violations.length === 0
+                        ? 'No broken references were found.'
+                        : `${violations.length} record(s) refer to a record that does not exist. They can still be read, but changes to the broken reference will be rejected.`}
+                </p>
+                <Button variant="outline" size="sm" disabled={checking} onClick={handleCheck}>
+                    {checking ? 'Checking...' : 'Check Again'}
+                </Button>
+            </div>
+            {violations.length > 0 && (
+                <Table>
+                    <TableHeader>
+                        <TableRow>
+                            <TableHead>Table</TableHead>
+                            <TableHead>Row</TableHead>
+                            <TableHead>Missing record in</TableHead>
+                            <TableHead>Found</TableHead>
+                        </TableRow>
+                    </TableHeader>
+                    <TableBody>
+                        {violations.map((v) => (
+                            <TableRow key={v.id}>
+                                <TableCell className="font-mono text-xs">{v.table_name}</TableCell>
+                                <TableCell className="font-mono text-xs">{v.row_id ?? '-'}</TableCell>
+                                <TableCell className="font-mono text-xs">{v.parent_table}</TableCell>
+                                <TableCell className="font-mono text-xs">{v.detected_at}</TableCell>
+                            </TableRow>
+                        ))}
+                    </TableBody>
+                </Table>
+            )}
+        </div>
+    );
+}
@@ -0,0 +1,71 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { useAuth } from '@/contexts/AuthContext';
+
+interface TableSalvage {
+    name: string;
+    rows_copied: number;
+    rows_failed: number;
+    error: string | null;
+}
+
+interface RecoveryReport {
+    recovered_at: string;
+    problems: string[];
+    source: 'SALVAGE' | 'BACKUP' | 'PARTIAL_SALVAGE';
+    backup_used: string | null;
+    corrupt_copy: string;
+    salvage_copy: string | null;
+    tables: TableSalvage[];
+    schema_errors: string[];
+}
+
+const SOURCE_TEXT: Record<RecoveryReport['source'], string> = {
+    SALVAGE: 'All data was copied out of the damaged file.',
+    BACKUP: 'The damaged file could not be fully read, so the most recent verified backup was restored. Changes made after that backup are missing.',
+    PARTIAL_SALVAGE: 'Some records could not be read and no usable backup was found. The data below may be incomplete.',
+};
+
+// Shown to administrators when the database failed its integrity check at start-up
+export function RecoveryNotice() {
+    const { user, hasPermission } = useAuth();
+    const [report, setReport] = useState<RecoveryReport | null>(null);
+    const [dismissed, setDismissed] = useState(false);
+
+    const canView = hasPermission('system.backup');
+
+    useEffect(() => {
+        if (!user || !canView) return;
+        invoke<RecoveryReport | null>('get_recovery_report', { token: user.token })
+            .then(setReport)
+            .catch((e) => console.error('Failed to load recovery report', e));
+    }, [user, canView]);
+
+    if (!report || dismissed) return null;
+
+    const damaged = report.tables.filter((t) => t.rows_failed > 0 || t.error);
+
+    return (
+        <div className="m-4 rounded-md border border-amber-300 bg-amber-50 p-4 text-sm space-y-2">
+            <div className="flex items-start justify-between gap-4">
+                <p className="font-semibold text-amber-900">The database was damaged and has been recovered.</p>
+                <button className="text-xs text-amber-900 underline" onClick={() => setDismissed(true)}>Dismiss</button>
+            </div>
+            <p>{SOURCE_TEXT[report.source]}</p>
+            {report.backup_used && <p className="break-all">Backup used: {report.backup_used}</p>}
+            {damaged.length > 0 && (
+                <ul className="list-disc pl-5 text-xs">
+                    {damaged.map((t) => (
+                        <li key={t.name}>
+                            {t.name}: {t.rows_copied} rows recovered{t.rows_failed > 0 && `, ${t.rows_failed} unreadable`}
+                        </li>
+                    ))}
+                </ul>
+            )}
+            <p className="text-xs text-muted-foreground break-all">
+                The damaged file was kept at {report.corrupt_copy}
+                {report.salvage_copy && ` and the salvaged data at ${report.salvage_copy}`}.
+            </p>
+        </div>
+    );
+}
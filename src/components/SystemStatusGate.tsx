@@ -0,0 +1,146 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { errorMessage } from '@/lib/errors';
+import type { EncryptionMode } from '@/components/DatabaseEncryption';
+import { WorkspacePicker } from '@/components/WorkspacePicker';
+
+// Mirrors SystemStatus in src-tauri/src/system_status.rs
+type SystemStatus =
+    | { state: 'INITIALIZING' | 'RECOVERING' | 'MAINTENANCE' | 'READY' }
+    | { state: 'LOCKED'; mode: EncryptionMode }
+    | { state: 'FAILED'; reason: string }
+    | { state: 'WORKSPACE_UNAVAILABLE'; workspace: string; reason: string };
+
+// Asks for the database key before anyone can sign in
+function UnlockDatabase({ mode }: { mode: EncryptionMode }) {
+    const [useKeyFile, setUseKeyFile] = useState(mode === 'KEY_FILE');
+    const [secret, setSecret] = useState('');
+    const [error, setError] = useState<string | null>(null);
+    const [busy, setBusy] = useState(false);
+
+    const handleUnlock = async (e: React.FormEvent) => {
+        e.preventDefault();
+        setBusy(true);
+        setError(null);
+        try {
+            // Start-up continues in the background and reports through the status event
+            await invoke('unlock_database', useKeyFile ? { keyFile: secret } : { passphrase: secret });
+        } catch (err) {
+            setError(errorMessage(err));
+            setBusy(false);
+        }
+    };
+
+    return (
+        <form className="space-y-3 text-left" onSubmit={handleUnlock}>
+            <h1 className="text-xl font-bold text-center">The database is encrypted</h1>
+            <p className="text-sm text-muted-foreground text-center">
+                {useKeyFile
+                    ? 'The key file was not found. Connect the drive that holds it and enter its path.'
+                    : 'Enter the database passphrase set by your administrator.'}
+            </p>
+            <Input
+                type={useKeyFile ? 'text' : 'password'}
+                placeholder={useKeyFile ? 'Key file path' : 'Passphrase'}
+                value={secret}
+                onChange={(e) => setSecret(e.target.value)}
+                autoFocus
+            />
+            {error && <p className="text-sm text-red-600">{error}</p>}
+            <div className="flex items-center justify-between">
+                <button
+                    type="button"
+                    className="text-xs underline text-muted-foreground"
+                    onClick={() => { setUseKeyFile(!useKeyFile); setSecret(''); }}
+                >
+                    {useKeyFile ? 'Use a passphrase instead' : 'Use a key file instead'}
+                </button>
+                <Button type="submit" disabled={busy || !secret}>{busy ? 'Unlocking...' : 'Unlock'}</Button>
+            </div>
+        </form>
+    );
+}
+
+// Holds back the app until the database is open; every other command is rejected
+// with DATABASE_NOT_READY before then
+export function SystemStatusGate({ children }: { children: React.ReactNode }) {
+    const [status, setStatus] = useState<SystemStatus | null>(null);
+
+    useEffect(() => {
+        let received = false;
+        let unlisten: (() => void) | undefined;
+        let cancelled = false;
+
+        listen<SystemStatus>('system-status', (event) => {
+            received = true;
+            setStatus(event.payload);
+        })
+            .then((stop) => {
+                if (cancelled) stop();
+                else unlisten = stop;
+                return invoke<SystemStatus>('get_system_status');
+            })
+            // An event that arrived meanwhile is newer than this answer
+            .then((current) => { if (!received) setStatus(current); })
+            .catch((e) => setStatus({ state: 'FAILED', reason: errorMessage(e) }));
+
+        return () => {
+            cancelled = true;
+            unlisten?.();
+        };
+    }, []);
+
+    if (status?.state === 'READY') return <>{children}</>;
+
+    // Kept mounted so the page that started the maintenance gets its answer
+    if (status?.state === 'MAINTENANCE') {
+        return (
+            <>
+                {children}
+                <div className="fixed inset-0 z-50 flex items-center justify-center bg-slate-50/90">
+                    <div className="max-w-md space-y-2 text-center">
+                        <h1 className="text-xl font-bold">Database maintenance in progress...</h1>
+                        <p className="text-sm text-muted-foreground">The database is being re-encrypted. Please do not close the application.</p>
+                    </div>
+                </div>
+            </>
+        );
+    }
+
+    return (
+        <div className="flex h-screen items-center justify-center bg-slate-50">
+            <div className="max-w-md space-y-2 text-center">
+                {status?.state === 'FAILED' ? (
+                    <>
+                        <h1 className="text-xl font-bold text-red-600">The database could not be opened</h1>
+                        <p className="text-sm break-words">{status.reason}</p>
+                        <p className="text-sm text-muted-foreground">Restart the application to try again, or open another workspace. If the problem persists, contact your administrator.</p>
+                        <WorkspacePicker />
+                    </>
+                ) : status?.state === 'WORKSPACE_UNAVAILABLE' ? (
+                    <>
+                        <h1 className="text-xl font-bold">Workspace "{status.workspace}" is not available</h1>
+                        <p className="text-sm break-words">{status.reason}</p>
+                        <p className="text-sm text-muted-foreground">Connect the drive that holds it and open it again, or choose another workspace.</p>
+                        <WorkspacePicker />
+                    </>
+                ) : status?.state === 'LOCKED' ? (
+                    <>
+                        <UnlockDatabase mode={status.mode} />
+                        <WorkspacePicker />
+                    </>
+                ) : status?.state === 'RECOVERING' ? (
+                    <>
+                        <h1 className="text-xl font-bold">Recovering the database...</h1>
+                        <p className="text-sm text-muted-foreground">The database failed its integrity check. This can take a few minutes; please do not close the application.</p>
+                    </>
+                ) : (
+                    <h1 className="text-xl font-bold">Starting up...</h1>
+                )}
+            </div>
+        </div>
+    );
+}
@@ -0,0 +1,121 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
+import { errorMessage } from '@/lib/errors';
+
+// Mirrors Workspace and WorkspaceList in src-tauri/src/workspace.rs
+export interface Workspace {
+    id: string;
+    name: string;
+    path: string;
+    created_at: string;
+    archived_at: string | null;
+}
+
+export interface WorkspaceList {
+    active: string;
+    data_root: string;
+    workspaces: Workspace[];
+}
+
+// Names the workspace the login screen signs in to. Switching needs an admin
+// session, from the settings page.
+export function CurrentWorkspaceName() {
+    const [name, setName] = useState('');
+
+    useEffect(() => {
+        invoke<WorkspaceList>('list_workspaces')
+            .then((current) => setName(current.workspaces.find((w) => w.id === current.active)?.name ?? ''))
+            .catch(() => setName(''));
+    }, []);
+
+    if (!name) return null;
+    return <p className="text-xs text-muted-foreground">Workspace: {name}</p>;
+}
+
+// Chooses which school's database to open while none is open, e.g. when the last
+// one's drive is missing. Opening another workspace restarts the database; the
+// start-up screen is shown meanwhile.
+export function WorkspacePicker() {
+    const [list, setList] = useState<WorkspaceList | null>(null);
+    const [selected, setSelected] = useState('');
+    const [newName, setNewName] = useState('');
+    const [adminEmail, setAdminEmail] = useState('');
+    const [adminPassword, setAdminPassword] = useState('');
+    const [creating, setCreating] = useState(false);
+    const [error, setError] = useState('');
+
+    useEffect(() => {
+        invoke<WorkspaceList>('list_workspaces')
+            .then((current) => {
+                setList(current);
+                setSelected(current.active);
+            })
+            .catch((e) => setError(errorMessage(e)));
+    }, []);
+
+    const open = async (workspaceId: string) => {
+        setError('');
+        try {
+            await invoke('select_workspace', { workspaceId });
+        } catch (e) {
+            setError(errorMessage(e));
+        }
+    };
+
+    const handleCreate = async (e: React.FormEvent) => {
+        e.preventDefault();
+        setError('');
+        try {
+            const workspace = await invoke<Workspace>('create_workspace', { name: newName, adminEmail, adminPassword });
+            setNewName('');
+            setAdminEmail('');
+            setAdminPassword('');
+            setCreating(false);
+            await open(workspace.id);
+        } catch (err) {
+            setError(errorMessage(err));
+        }
+    };
+
+    if (!list) return error ? <p className="text-red-500 text-sm">{error}</p> : null;
+
+    const available = list.workspaces.filter((w) => !w.archived_at);
+
+    return (
+        <div className="space-y-2 text-left">
+            <Label>Workspace</Label>
+            <div className="flex gap-2">
+                <Select value={selected} onValueChange={setSelected}>
+                    <SelectTrigger>
+                        <SelectValue placeholder="Choose a workspace" />
+                    </SelectTrigger>
+                    <SelectContent>
+                        {available.map((w) => (
+                            <SelectItem key={w.id} value={w.id}>{w.name}</SelectItem>
+                        ))}
+                    </SelectContent>
+                </Select>
+                <Button type="button" variant="outline" disabled={!selected} onClick={() => open(selected)}>
+                    Open
+                </Button>
+            </div>
+            {creating ? (
+                <form className="space-y-2" onSubmit={handleCreate}>
+                    <Input placeholder="New workspace name" value={newName} onChange={(e) => setNewName(e.target.value)} autoFocus />
+                    <Input type="email" placeholder="Admin email for the new workspace" value={adminEmail} onChange={(e) => setAdminEmail(e.target.value)} />
+                    <Input type="password" placeholder="Admin password" value={adminPassword} onChange={(e) => setAdminPassword(e.target.value)} />
+                    <Button type="submit" variant="secondary" disabled={!newName.trim() || !adminEmail.trim() || !adminPassword}>Create</Button>
+                </form>
+            ) : (
+                <button type="button" className="text-xs underline text-muted-foreground" onClick={() => setCreating(true)}>
+                    Create a new workspace
+                </button>
+            )}
+            {error && <p className="text-red-500 text-sm">{error}</p>}
+        </div>
+    );
+}
@@ -0,0 +1,170 @@
+import { useCallback, useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table';
+import { Badge } from '@/components/ui/badge';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+import { useAuth, isReauthRequired } from '@/contexts/AuthContext';
+import { useToast } from '@/components/ui/use-toast';
+import { errorMessage } from '@/lib/errors';
+import type { Workspace, WorkspaceList } from '@/components/WorkspacePicker';
+
+// Lists every workspace on this computer, archives unused ones and sets where new
+// ones are created
+export function WorkspaceSettings() {
+    const { user, confirmReauth, logout } = useAuth();
+    const { toast } = useToast();
+    const [list, setList] = useState<WorkspaceList | null>(null);
+    const [dataRoot, setDataRoot] = useState('');
+    const [newName, setNewName] = useState('');
+    const [adminEmail, setAdminEmail] = useState('');
+    const [adminPassword, setAdminPassword] = useState('');
+    const [accountPassword, setAccountPassword] = useState('');
+
+    const load = useCallback(async () => {
+        try {
+            const current = await invoke<WorkspaceList>('list_workspaces');
+            setList(current);
+            setDataRoot(current.data_root);
+        } catch (e) {
+            toast({ title: 'Could not load workspaces', description: errorMessage(e), variant: 'destructive' });
+        }
+    }, [toast]);
+
+    useEffect(() => { load(); }, [load]);
+
+    // Sensitive changes ask for the account password once, then retry
+    const withReauth = async <T,>(action: () => Promise<T>): Promise<T> => {
+        try {
+            return await action();
+        } catch (e) {
+            if (!isReauthRequired(e)) throw e;
+            await confirmReauth(accountPassword);
+            return await action();
+        }
+    };
+
+    const handleArchive = async (workspace: Workspace) => {
+        if (!user) return;
+        const command = workspace.archived_at ? 'unarchive_workspace' : 'archive_workspace';
+        try {
+            setList(await withReauth(() => invoke<WorkspaceList>(command, { token: user.token, workspaceId: workspace.id })));
+            setAccountPassword('');
+        } catch (e) {
+            toast({ title: 'Could not update workspace', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const handleDataRoot = async (reset: boolean) => {
+        if (!user) return;
+        try {
+            const updated = await withReauth(() =>
+                invoke<WorkspaceList>('set_data_root', { token: user.token, dataRoot: reset ? null : dataRoot }));
+            setList(updated);
+            setDataRoot(updated.data_root);
+            setAccountPassword('');
+            toast({ title: 'Data Folder Saved', description: 'New workspaces will be created there.' });
+        } catch (e) {
+            toast({ title: 'Could not set data folder', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const handleCreate = async () => {
+        if (!user) return;
+        try {
+            await withReauth(() =>
+                invoke<Workspace>('create_workspace', { token: user.token, name: newName, adminEmail, adminPassword }));
+            setNewName('');
+            setAdminEmail('');
+            setAdminPassword('');
+            setAccountPassword('');
+            await load();
+        } catch (e) {
+            toast({ title: 'Could not create workspace', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    // Everyone is signed out, since accounts belong to each workspace's database
+    const handleOpen = async (workspace: Workspace) => {
+        if (!user || !window.confirm(`Open ${workspace.name}? You will be signed out.`)) return;
+        try {
+            await withReauth(() => invoke('select_workspace', { token: user.token, workspaceId: workspace.id }));
+            logout();
+        } catch (e) {
+            toast({ title: 'Could not open workspace', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    if (!list) return null;
+
+    return (
+        <div className="space-y-4">
+            <Table>
+                <TableHeader>
+                    <TableRow>
+                        <TableHead>Name</TableHead>
+                        <TableHead>Folder</TableHead>
+                        <TableHead>Created</TableHead>
+                        <TableHead />
+                    </TableRow>
+                </TableHeader>
+                <TableBody>
+                    {list.workspaces.map((workspace) => (
+                        <TableRow key={workspace.id}>
+                            <TableCell>
+                                {workspace.name}
+                                {workspace.id === list.active && <Badge variant="secondary" className="ml-2">Open</Badge>}
+                                {workspace.archived_at && <Badge variant="outline" className="ml-2">Archived</Badge>}
+                            </TableCell>
+                            <TableCell className="text-xs break-all max-w-xs">{workspace.path}</TableCell>
+                            <TableCell className="font-mono text-xs">{workspace.created_at}</TableCell>
+                            <TableCell className="space-x-2 whitespace-nowrap">
+                                {workspace.id !== list.active && !workspace.archived_at && (
+                                    <Button variant="outline" size="sm" onClick={() => handleOpen(workspace)}>Open</Button>
+                                )}
+                                {workspace.id !== list.active && (
+                                    <Button variant="outline" size="sm" onClick={() => handleArchive(workspace)}>
+                                        {workspace.archived_at ? 'Unarchive' : 'Archive'}
+                                    </Button>
+                                )}
+                            </TableCell>
+                        </TableRow>
+                    ))}
+                </TableBody>
+            </Table>
+
+            <div className="flex items-center gap-2">
+                <Input placeholder="New workspace name" value={newName} onChange={(e) => setNewName(e.target.value)} />
+                <Input type="email" placeholder="Its admin email" value={adminEmail} onChange={(e) => setAdminEmail(e.target.value)} />
+                <Input type="password" placeholder="Its admin password" value={adminPassword} onChange={(e) => setAdminPassword(e.target.value)} />
+                <Button
+                    variant="secondary"
+                    disabled={!newName.trim() || !adminEmail.trim() || !adminPassword}
+                    onClick={handleCreate}
+                >
+                    Create Workspace
+                </Button>
+            </div>
+
+            <div className="space-y-2">
+                <Label htmlFor="data-root">Folder for new workspaces</Label>
+                <p className="text-sm text-muted-foreground">
+                    For example a folder on an external drive. Existing workspaces stay where they are.
+                </p>
+                <div className="flex items-center gap-2">
+                    <Input id="data-root" value={dataRoot} onChange={(e) => setDataRoot(e.target.value)} />
+                    <Button variant="secondary" onClick={() => handleDataRoot(false)}>Save</Button>
+                    <Button variant="ghost" onClick={() => handleDataRoot(true)}>Reset</Button>
+                </div>
+            </div>
+
+            <Input
+                type="password"
+                placeholder="Your account password (to confirm changes)"
+                value={accountPassword}
+                onChange={(e) => setAccountPassword(e.target.value)}
+            />
+        </div>
+    );
+}
@@ -1,5 +1,6 @@
 import React, { createContext, useContext, useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { hasErrorCode } from '@/lib/errors';
 
 export const Role = {
     ADMIN: 'ADMIN',
@@ -9,16 +10,28 @@ export const Role = {
 
 export type RoleType = typeof Role[keyof typeof Role];
 
+// Destructive commands reject with this prefix until confirmReauth succeeds; retry afterwards
+export const isReauthRequired = (e: unknown) => hasErrorCode(e, 'REAUTH_REQUIRED');
+
 interface User {
     id: string;
     email: string;
-    role: RoleType;
+    // Built-in roles or an admin-defined custom role
+    role: RoleType | string;
+    token: string;
+    expires_at: string;
+    must_change_password: boolean;
+    two_factor_setup_required: boolean;
 }
 
 interface AuthContextType {
     user: User | null;
-    login: (email: string, pass: string) => Promise<void>;
+    login: (email: string, pass: string, otpCode?: string) => Promise<void>;
     logout: () => void;
+    changePassword: (current: string, next: string) => Promise<void>;
+    completeTwoFactorSetup: () => void;
+    confirmReauth: (password: string, otpCode?: string) => Promise<void>;
+    hasPermission: (permission: string) => boolean;
     isLoading: boolean;
 }
 
@@ -26,38 +39,98 @@ const AuthContext = createContext<AuthContextType | undefined>(undefined);
 
 export function AuthProvider({ children }: { children: React.ReactNode }) {
     const [user, setUser] = useState<User | null>(null);
+    const [permissions, setPermissions] = useState<string[]>([]);
     const [isLoading, setIsLoading] = useState(true);
 
     useEffect(() => {
-        const storedUser = localStorage.getItem('user');
-        if (storedUser) {
-            try {
-                setUser(JSON.parse(storedUser));
-            } catch (e) {
-                console.error("Failed to parse user", e);
+        const restoreSession = async () => {
+            const storedUser = localStorage.getItem('user');
+            if (storedUser) {
+                try {
+                    const parsed: User = JSON.parse(storedUser);
+                    // Server-side check: the token may have expired or been revoked
+                    const current = await invoke<{ must_change_password: boolean; two_factor_setup_required: boolean }>('get_current_user', { token: parsed.token });
+                    setUser({
+                        ...parsed,
+                        must_change_password: current.must_change_password,
+                        two_factor_setup_required: current.two_factor_setup_required,
+                    });
+                } catch (e) {
+                    console.error("Failed to restore session", e);
+                    localStorage.removeItem('user');
+                }
             }
-        }
-        setIsLoading(false);
+            setIsLoading(false);
+        };
+
+        restoreSession();
     }, []);
 
-    const login = async (email: string, pass: string) => {
+    // Permissions only drive what the UI offers; the backend enforces them independently
+    useEffect(() => {
+        if (!user || user.must_change_password || user.two_factor_setup_required) {
+            setPermissions([]);
+            return;
+        }
+        invoke<string[]>('get_my_permissions', { token: user.token })
+            .then(setPermissions)
+            .catch((e) => {
+                console.error("Failed to load permissions", e);
+                setPermissions([]);
+            });
+    }, [user?.token, user?.must_change_password, user?.two_factor_setup_required]);
+
+    const hasPermission = (permission: string) => permissions.includes(permission);
+
+    const login = async (email: string, pass: string, otpCode?: string) => {
         try {
-            const response = await invoke<User>('login', { email, passwordPlain: pass });
+            const response = await invoke<User>('login', { email, passwordPlain: pass, otpCode });
             setUser(response);
             localStorage.setItem('user', JSON.stringify(response));
         } catch (e) {
             console.error("Login failed", e);
-            throw new Error(String(e));
+            throw e;
         }
     };
 
+    const changePassword = async (current: string, next: string) => {
+        if (!user) return;
+        await invoke('change_password', {
+            token: user.token,
+            currentPasswordPlain: current,
+            newPasswordPlain: next,
+        });
+        const updated = { ...user, must_change_password: false };
+        setUser(updated);
+        localStorage.setItem('user', JSON.stringify(updated));
+    };
+
+    const completeTwoFactorSetup = () => {
+        if (!user) return;
+        const updated = { ...user, two_factor_setup_required: false };
+        setUser(updated);
+        localStorage.setItem('user', JSON.stringify(updated));
+    };
+
+    const confirmReauth = async (password: string, otpCode?: string) => {
+        if (!user) return;
+        await invoke('confirm_reauth', {
+            token: user.token,
+            passwordPlain: password || undefined,
+            otpCode,
+        });
+    };
+
     const logout = () => {
+        if (user) {
+            invoke('logout', { token: user.token }).catch((e) => console.error("Logout failed", e));
+        }
         setUser(null);
         localStorage.removeItem('user');
     };
 
     return (
-        <AuthContext.Provider value={{ user, login, logout, isLoading }}>
+        <AuthContext.Provider value={{ user, login, logout, changePassword, completeTwoFactorSetup, confirmReauth, hasPermission, isLoading }}>
             {children}
         </AuthContext.Provider>
     );
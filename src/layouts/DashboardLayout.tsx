@@ -1,7 +1,8 @@
-import { useAuth, Role, RoleType } from '../contexts/AuthContext';
+import { useAuth } from '../contexts/AuthContext';
 import { Outlet, Link, useNavigate, useLocation } from 'react-router-dom';
 import { cn } from '@/lib/utils';
 import { Button } from '@/components/ui/button';
+import { RecoveryNotice } from '@/components/RecoveryNotice';
 import { 
     LayoutDashboard, 
     Users, 
@@ -14,7 +15,7 @@ import {
 } from 'lucide-react';
 
 export function DashboardLayout() {
-    const { user, logout } = useAuth();
+    const { user, logout, hasPermission } = useAuth();
     const navigate = useNavigate();
     const location = useLocation();
 
@@ -25,48 +26,48 @@ export function DashboardLayout() {
 
     if (!user) return null;
 
-    const navItems: { label: string; path: string; icon: any; roles: RoleType[] }[] = [
+    // Items without a permission are available to every signed-in user
+    const navItems: { label: string; path: string; icon: any; permission?: string }[] = [
         { 
             label: 'Dashboard', 
             path: '/dashboard', 
             icon: LayoutDashboard,
-            roles: [Role.ADMIN, Role.TEACHER, Role.MANAGEMENT_FINANCE] 
+            permission: 'dashboard.view' 
         },
         { 
             label: 'Students', 
             path: '/students', 
             icon: Users,
-            roles: [Role.ADMIN, Role.TEACHER]
+            permission: 'students.read_sensitive'
         },
         { 
             label: 'Classes', 
             path: '/classes', 
             icon: BookOpen,
-            roles: [Role.ADMIN, Role.TEACHER] 
+            permission: 'attendance.read' 
         },
         { 
             label: 'Finance', 
             path: '/finance', 
             icon: CreditCard,
-            roles: [Role.ADMIN, Role.MANAGEMENT_FINANCE] 
+            permission: 'finance.invoices.read' 
         },
         { 
             label: 'Trajectory', 
             path: '/trajectory', 
             icon: Activity,
-            roles: [Role.ADMIN, Role.TEACHER] 
+            permission: 'trajectory.read' 
         },
         { 
             label: 'Audit Log', 
             path: '/audit', 
             icon: Shield,
-            roles: [Role.ADMIN] 
+            permission: 'system.audit' 
         },
         { 
             label: 'Settings', 
             path: '/settings', 
-            icon: Settings,
-            roles: [Role.ADMIN, Role.TEACHER, Role.MANAGEMENT_FINANCE] 
+            icon: Settings 
         },
     ];
 
@@ -84,7 +85,7 @@ export function DashboardLayout() {
                 <div className="flex-1 overflow-y-auto py-4">
                     <nav className="space-y-1 px-2">
                         {navItems.map((item) => {
-                            if (!item.roles.includes(user.role)) return null;
+                            if (item.permission && !hasPermission(item.permission)) return null;
                             
                             const isActive = location.pathname.startsWith(item.path);
                             return (
@@ -125,6 +126,7 @@ export function DashboardLayout() {
 
             {/* Main Content */}
             <div className="flex-1 overflow-auto">
+                <RecoveryNotice />
                 <Outlet />
             </div>
         </div>
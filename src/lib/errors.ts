@@ -0,0 +1,19 @@
+// Shape of every command rejection; see src-tauri/src/error.rs for the codes
+export interface AppError {
+    code: string;
+    message: string;
+    fields?: { field: string; message: string }[];
+}
+
+export function isAppError(e: unknown): e is AppError {
+    return typeof e === 'object' && e !== null && 'code' in e && 'message' in e;
+}
+
+export function hasErrorCode(e: unknown, code: string): boolean {
+    return isAppError(e) && e.code === code;
+}
+
+export function errorMessage(e: unknown): string {
+    if (isAppError(e) || e instanceof Error) return e.message;
+    return String(e);
+}
@@ -2,12 +2,17 @@ import { useState, useEffect } from 'react';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from '@/components/ui/table';
 import { Badge } from '@/components/ui/badge';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
 import { invoke } from '@tauri-apps/api/core';
-import { useAuth, Role } from '@/contexts/AuthContext';
+import { useAuth } from '@/contexts/AuthContext';
+import { errorMessage } from '@/lib/errors';
+import { ChangeList, FieldChange } from '@/components/EntityHistory';
 
 interface AuditLog {
     id: number;
     actor_user_id: string;
+    actor_email: string | null;
     action: string;
     entity_type: string;
     entity_id: string;
@@ -15,37 +20,185 @@ interface AuditLog {
     created_at: string;
 }
 
+interface AuditLogResult {
+    entries: AuditLog[];
+    total: number;
+    next_cursor: number | null;
+}
+
+interface AuditChainReport {
+    valid: boolean;
+    entries_checked: number;
+    first_broken_link: { entry_id: number; reason: string } | null;
+    checkpoints_checked: number;
+    invalid_checkpoints: number[];
+    last_checkpoint_at: string | null;
+    checkpoint_key_path: string | null;
+    archives_checked: number;
+    invalid_archives: number[];
+    missing_archives: number[];
+}
+
+interface AuditLogFilter {
+    actor_user_id?: string;
+    action?: string;
+    entity_type?: string;
+    entity_id?: string;
+    from?: string;
+    to?: string;
+}
+
+// Structured entries carry { changes, context }; older ones hold free text
+const parseChanges = (metadata: string | null): FieldChange[] => {
+    try {
+        const parsed = metadata ? JSON.parse(metadata) : null;
+        return Array.isArray(parsed?.changes) ? parsed.changes : [];
+    } catch {
+        return [];
+    }
+};
+
 export function AuditLogPage() {
-    const { user } = useAuth();
+    const { user, hasPermission } = useAuth();
     const [logs, setLogs] = useState<AuditLog[]>([]);
+    const [total, setTotal] = useState(0);
+    const [nextCursor, setNextCursor] = useState<number | null>(null);
+    const [filter, setFilter] = useState<AuditLogFilter>({});
     const [loading, setLoading] = useState(true);
+    const [error, setError] = useState('');
+    const [chainReport, setChainReport] = useState<AuditChainReport | null>(null);
+    const [verifying, setVerifying] = useState(false);
+    const [exportPath, setExportPath] = useState('');
+    const [exportStatus, setExportStatus] = useState('');
+
+    const canView = hasPermission('system.audit');
+
+    // Empty inputs mean "no filter"; dates are sent as the start of the day
+    const activeFilter = () => Object.fromEntries(
+        Object.entries(filter)
+            .filter(([, v]) => v)
+            .map(([k, v]) => [k, k === 'from' || k === 'to' ? `${v}T00:00:00` : v])
+    );
+
+    const fetchPage = async (cursor: number | null) => {
+        if (!user) return;
+        setLoading(true);
+        try {
+            const page = await invoke<AuditLogResult>('query_audit_logs', {
+                token: user.token,
+                filter: activeFilter(),
+                cursor,
+            });
+            setLogs(cursor === null ? page.entries : [...logs, ...page.entries]);
+            setTotal(page.total);
+            setNextCursor(page.next_cursor);
+            setError('');
+        } catch (e) {
+            setError(errorMessage(e));
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const exportLogs = async (format: 'csv' | 'jsonl') => {
+        if (!user || !exportPath) return;
+        try {
+            const count = await invoke<number>('export_audit_logs', {
+                token: user.token,
+                filter: activeFilter(),
+                format,
+                exportPath,
+            });
+            setExportStatus(`Exported ${count} entries to ${exportPath}`);
+        } catch (e) {
+            setExportStatus(errorMessage(e));
+        }
+    };
+
+    const verifyChain = async () => {
+        if (!user) return;
+        setVerifying(true);
+        try {
+            setChainReport(await invoke<AuditChainReport>('verify_audit_chain', { token: user.token }));
+        } catch (e) {
+            setError(errorMessage(e));
+        } finally {
+            setVerifying(false);
+        }
+    };
 
     useEffect(() => {
-        if (user?.role !== Role.ADMIN) return;
-
-        // In real app, call backend
-        // invoke('get_audit_logs', { userId: user.id }).then(...)
-        
-        // Mock data
-        setLogs([
-            { id: 1, actor_user_id: 'admin', action: 'CREATE', entity_type: 'USER', entity_id: 'user_teacher_01', metadata_json: null, created_at: '2023-10-25 10:00:00' },
-            { id: 2, actor_user_id: 'teacher_01', action: 'UPDATE', entity_type: 'STUDENT', entity_id: 'student_05', metadata_json: '{"field": "grade"}', created_at: '2023-10-25 11:30:00' },
-        ]);
-        setLoading(false);
-    }, [user]);
-
-    if (user?.role !== Role.ADMIN) {
+        if (canView) fetchPage(null);
+    }, [user, canView]);
+
+    if (!canView) {
         return <div className="p-8 text-red-500">Access Denied</div>;
     }
 
+    const updateFilter = (key: keyof AuditLogFilter) => (e: React.ChangeEvent<HTMLInputElement>) =>
+        setFilter({ ...filter, [key]: e.target.value });
+
     return (
         <div className="p-6 space-y-6">
-            <h1 className="text-3xl font-bold">Audit Logs</h1>
+            <div className="flex items-center justify-between">
+                <h1 className="text-3xl font-bold">Audit Logs</h1>
+                <Button variant="outline" disabled={verifying} onClick={verifyChain}>
+                    {verifying ? 'Verifying...' : 'Verify Integrity'}
+                </Button>
+            </div>
+            {chainReport && (
+                <div className={`rounded-md border p-3 text-sm ${chainReport.valid ? 'border-green-500 text-green-700' : 'border-red-500 text-red-600'}`}>
+                    {chainReport.valid
+                        ? `Audit trail intact: ${chainReport.entries_checked} entries and ${chainReport.checkpoints_checked} checkpoints verified.`
+                        : chainReport.first_broken_link
+                            ? `Audit trail broken at entry #${chainReport.first_broken_link.entry_id}: ${chainReport.first_broken_link.reason}`
+                            : chainReport.invalid_archives.length > 0
+                                ? `Archive(s) ${chainReport.invalid_archives.join(', ')} failed signature or checksum verification.`
+                                : chainReport.missing_archives.length > 0
+                                    ? `Archive file(s) missing for archive(s) ${chainReport.missing_archives.join(', ')}.`
+                                    : `Checkpoint verification failed for checkpoint(s) ${chainReport.invalid_checkpoints.join(', ')}.`}
+                    {chainReport.last_checkpoint_at && (
+                        <div className="text-xs text-muted-foreground">Last signed checkpoint: {chainReport.last_checkpoint_at}</div>
+                    )}
+                    {chainReport.checkpoint_key_path && (
+                        <div className="text-xs text-muted-foreground break-all">
+                            Checkpoints only prove the log was not rewritten by someone who cannot read the signing key at {chainReport.checkpoint_key_path}.
+                        </div>
+                    )}
+                </div>
+            )}
             <Card>
                 <CardHeader>
                     <CardTitle>System Activity</CardTitle>
                 </CardHeader>
-                <CardContent>
+                <CardContent className="space-y-4">
+                    <form
+                        className="grid grid-cols-2 md:grid-cols-6 gap-2"
+                        onSubmit={(e) => { e.preventDefault(); fetchPage(null); }}
+                    >
+                        <Input placeholder="Actor user id" value={filter.actor_user_id ?? ''} onChange={updateFilter('actor_user_id')} />
+                        <Input placeholder="Action" value={filter.action ?? ''} onChange={updateFilter('action')} />
+                        <Input placeholder="Entity type" value={filter.entity_type ?? ''} onChange={updateFilter('entity_type')} />
+                        <Input placeholder="Entity id" value={filter.entity_id ?? ''} onChange={updateFilter('entity_id')} />
+                        <Input type="date" value={filter.from ?? ''} onChange={updateFilter('from')} />
+                        <Input type="date" value={filter.to ?? ''} onChange={updateFilter('to')} />
+                        <Button type="submit" disabled={loading}>Apply Filters</Button>
+                    </form>
+
+                    <div className="flex gap-2">
+                        <Input
+                            placeholder="Export file path"
+                            value={exportPath}
+                            onChange={(e) => setExportPath(e.target.value)}
+                        />
+                        <Button variant="outline" disabled={!exportPath} onClick={() => exportLogs('csv')}>Export CSV</Button>
+                        <Button variant="outline" disabled={!exportPath} onClick={() => exportLogs('jsonl')}>Export JSONL</Button>
+                    </div>
+                    {exportStatus && <div className="text-sm text-muted-foreground">{exportStatus}</div>}
+
+                    {error && <div className="text-sm text-red-500">{error}</div>}
+                    <div className="text-sm text-muted-foreground">{total} matching entries</div>
+
                     <Table>
                         <TableHeader>
                             <TableRow>
@@ -60,7 +213,7 @@ export function AuditLogPage() {
                             {logs.map((log) => (
                                 <TableRow key={log.id}>
                                     <TableCell className="font-mono text-xs">{log.created_at}</TableCell>
-                                    <TableCell>{log.actor_user_id}</TableCell>
+                                    <TableCell>{log.actor_email ?? log.actor_user_id}</TableCell>
                                     <TableCell>
                                         <Badge variant="outline">{log.action}</Badge>
                                     </TableCell>
@@ -69,12 +222,20 @@ export function AuditLogPage() {
                                         {log.entity_id}
                                     </TableCell>
                                     <TableCell className="text-xs text-muted-foreground font-mono max-w-xs truncate">
-                                        {log.metadata_json || '-'}
+                                        {parseChanges(log.metadata_json).length > 0
+                                            ? <ChangeList changes={parseChanges(log.metadata_json)} />
+                                            : log.metadata_json || '-'}
                                     </TableCell>
                                 </TableRow>
                             ))}
                         </TableBody>
                     </Table>
+
+                    {nextCursor !== null && (
+                        <Button variant="outline" disabled={loading} onClick={() => fetchPage(nextCursor)}>
+                            Load More
+                        </Button>
+                    )}
                 </CardContent>
             </Card>
         </div>
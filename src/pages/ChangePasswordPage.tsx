@@ -0,0 +1,85 @@
+import React, { useState } from 'react';
+import { useAuth } from '../contexts/AuthContext';
+import { Navigate, useNavigate } from 'react-router-dom';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Card, CardHeader, CardTitle, CardContent, CardDescription, CardFooter } from '@/components/ui/card';
+import { Label } from '@/components/ui/label';
+import { errorMessage } from '@/lib/errors';
+
+export function ChangePasswordPage() {
+    const { user, changePassword } = useAuth();
+    const navigate = useNavigate();
+    const [current, setCurrent] = useState('');
+    const [next, setNext] = useState('');
+    const [confirm, setConfirm] = useState('');
+    const [error, setError] = useState('');
+
+    if (!user) return <Navigate to="/login" replace />;
+
+    const handleSubmit = async (e: React.FormEvent) => {
+        e.preventDefault();
+        if (next !== confirm) {
+            setError('New passwords do not match');
+            return;
+        }
+        try {
+            await changePassword(current, next);
+            navigate('/dashboard');
+        } catch (err) {
+            setError(errorMessage(err));
+        }
+    };
+
+    return (
+        <div className="flex items-center justify-center h-screen bg-slate-100">
+            <Card className="w-[350px]">
+                <CardHeader>
+                    <CardTitle>Change Password</CardTitle>
+                    <CardDescription>
+                        {user.must_change_password
+                            ? 'You must choose a new password before continuing'
+                            : 'Update your account password'}
+                    </CardDescription>
+                </CardHeader>
+                <form onSubmit={handleSubmit}>
+                    <CardContent>
+                        <div className="grid w-full items-center gap-4">
+                            <div className="flex flex-col space-y-1.5">
+                                <Label htmlFor="current">Current Password</Label>
+                                <Input
+                                    id="current"
+                                    type="password"
+                                    value={current}
+                                    onChange={(e: React.ChangeEvent<HTMLInputElement>) => setCurrent(e.target.value)}
+                                />
+                            </div>
+                            <div className="flex flex-col space-y-1.5">
+                                <Label htmlFor="next">New Password</Label>
+                                <Input
+                                    id="next"
+                                    type="password"
+                                    value={next}
+                                    onChange={(e: React.ChangeEvent<HTMLInputElement>) => setNext(e.target.value)}
+                                />
+                            </div>
+                            <div className="flex flex-col space-y-1.5">
+                                <Label htmlFor="confirm">Confirm New Password</Label>
+                                <Input
+                                    id="confirm"
+                                    type="password"
+                                    value={confirm}
+                                    onChange={(e: React.ChangeEvent<HTMLInputElement>) => setConfirm(e.target.value)}
+                                />
+                            </div>
+                            {error && <p className="text-red-500 text-sm">{error}</p>}
+                        </div>
+                    </CardContent>
+                    <CardFooter className="flex justify-between">
+                        <Button type="submit" className="w-full">Change Password</Button>
+                    </CardFooter>
+                </form>
+            </Card>
+        </div>
+    );
+}
@@ -26,7 +26,7 @@ export function FinanceDashboard() {
         
         const fetchInvoices = async () => {
             try {
-                const data = await invoke<Invoice[]>('get_invoices', { userId: user.id });
+                const data = await invoke<Invoice[]>('get_invoices', { token: user.token });
                 setInvoices(data);
             } catch (e) {
                 console.error("Failed to fetch invoices", e);
@@ -5,21 +5,30 @@ import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { Card, CardHeader, CardTitle, CardContent, CardDescription, CardFooter } from '@/components/ui/card';
 import { Label } from '@/components/ui/label';
+import { errorMessage, hasErrorCode } from '@/lib/errors';
+import { CurrentWorkspaceName } from '@/components/WorkspacePicker';
 
 export function LoginPage() {
     const { login } = useAuth();
     const navigate = useNavigate();
     const [email, setEmail] = useState('');
     const [password, setPassword] = useState('');
+    const [otpCode, setOtpCode] = useState('');
+    const [needsOtp, setNeedsOtp] = useState(false);
     const [error, setError] = useState('');
 
     const handleLogin = async (e: React.FormEvent) => {
         e.preventDefault();
         try {
-            await login(email, password);
+            await login(email, password, needsOtp ? otpCode : undefined);
             navigate('/dashboard');
         } catch (err) {
-            setError('Login failed');
+            if (hasErrorCode(err, 'TWO_FACTOR_REQUIRED')) {
+                setNeedsOtp(true);
+                setError('');
+            } else {
+                setError(errorMessage(err));
+            }
         }
     };
 
@@ -52,6 +61,17 @@ export function LoginPage() {
                                     onChange={(e: React.ChangeEvent<HTMLInputElement>) => setPassword(e.target.value)}
                                 />
                             </div>
+                            {needsOtp && (
+                                <div className="flex flex-col space-y-1.5">
+                                    <Label htmlFor="otp">Authenticator Code</Label>
+                                    <Input 
+                                        id="otp" 
+                                        placeholder="6-digit code or recovery code" 
+                                        value={otpCode}
+                                        onChange={(e: React.ChangeEvent<HTMLInputElement>) => setOtpCode(e.target.value)}
+                                    />
+                                </div>
+                            )}
                             {error && <p className="text-red-500 text-sm">{error}</p>}
                         </div>
                     </CardContent>
@@ -59,8 +79,11 @@ export function LoginPage() {
                         <Button type="submit" className="w-full">Login</Button>
                     </CardFooter>
                 </form>
+                <div className="px-6 pb-4">
+                    <CurrentWorkspaceName />
+                </div>
                 <div className="px-6 pb-4 text-xs text-muted-foreground">
-                    <p>Demo Credentials:</p>
+                    <p>Demo Credentials (password change required on first login):</p>
                     <ul className="list-disc pl-4 mt-1">
                         <li>admin@local / password</li>
                         <li>teacher@local / password</li>
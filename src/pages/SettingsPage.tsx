@@ -5,22 +5,75 @@ import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { invoke } from '@tauri-apps/api/core';
-import { useAuth, Role } from '@/contexts/AuthContext';
+import { useAuth, Role, isReauthRequired } from '@/contexts/AuthContext';
 import { useToast } from '@/components/ui/use-toast';
 import { Download, Upload, Database, Save, RotateCcw } from 'lucide-react';
+import { errorMessage } from '@/lib/errors';
+import { BackupCatalog, BackupRecord } from '@/components/BackupCatalog';
+import { DatabaseEncryption } from '@/components/DatabaseEncryption';
+import { ForeignKeyViolations } from '@/components/ForeignKeyViolations';
+import { WorkspaceSettings } from '@/components/WorkspaceSettings';
+
+interface RestoreReport {
+    source: string;
+    schema_version: number | null;
+    migrations_applied: number;
+    safety_backup: string;
+}
 
 export function SettingsPage() {
-    const { user } = useAuth();
+    const { user, confirmReauth, logout } = useAuth();
     const { toast } = useToast();
     const [backupPath, setBackupPath] = useState('');
+    // Leave empty for a plain database copy
+    const [backupPassphrase, setBackupPassphrase] = useState('');
+    const [restorePath, setRestorePath] = useState('');
+    const [restorePassphrase, setRestorePassphrase] = useState('');
+    const [accountPassword, setAccountPassword] = useState('');
 
     const handleBackup = async () => {
         try {
-            const path = await invoke<string>('backup_db');
-            setBackupPath(path);
-            toast({ title: 'Backup Successful', description: `Saved to ${path}` });
+            const backup = await invoke<BackupRecord>('backup_db', {
+                token: user?.token,
+                passphrase: backupPassphrase || undefined,
+            });
+            setBackupPath(backup.file_path);
+            setBackupPassphrase('');
+            toast({
+                title: backup.encrypted ? 'Encrypted Backup Successful' : 'Backup Successful',
+                description: `Verified and saved to ${backup.file_path}`,
+            });
+        } catch (e) {
+            toast({ title: 'Backup Failed', description: errorMessage(e), variant: 'destructive' });
+        }
+    };
+
+    const handleRestore = async () => {
+        if (!user || !restorePath) return;
+        const restore = () => restorePath.endsWith('.rbak')
+            ? invoke<RestoreReport>('restore_encrypted_backup', { token: user.token, archivePath: restorePath, passphrase: restorePassphrase })
+            : invoke<RestoreReport>('restore_db', { token: user.token, backupPathStr: restorePath });
+
+        try {
+            let report: RestoreReport;
+            try {
+                report = await restore();
+            } catch (e) {
+                if (!isReauthRequired(e)) throw e;
+                await confirmReauth(accountPassword);
+                report = await restore();
+            }
+            setRestorePassphrase('');
+            setAccountPassword('');
+            const upgraded = report.migrations_applied > 0 ? ` and upgraded (${report.migrations_applied} migrations)` : '';
+            toast({
+                title: 'Restore Complete',
+                description: `Database restored${upgraded}. The previous data was saved to ${report.safety_backup}. Please sign in again.`,
+            });
+            // The restore revokes every session, this one included
+            logout();
         } catch (e) {
-            toast({ title: 'Backup Failed', description: String(e), variant: 'destructive' });
+            toast({ title: 'Restore Failed', description: errorMessage(e), variant: 'destructive' });
         }
     };
 
@@ -60,19 +113,95 @@ export function SettingsPage() {
                                 <CardDescription>Manage your local database file.</CardDescription>
                             </CardHeader>
                             <CardContent className="space-y-4">
-                                <div className="flex items-center gap-4">
-                                    <Button onClick={handleBackup}>
-                                        <Save className="mr-2 h-4 w-4" /> Create Backup
-                                    </Button>
-                                    <Button variant="outline">
-                                        <RotateCcw className="mr-2 h-4 w-4" /> Restore from Backup
-                                    </Button>
+                                <div className="space-y-2">
+                                    <Label htmlFor="backup-passphrase">Encryption passphrase (optional)</Label>
+                                    <p className="text-sm text-muted-foreground">
+                                        Encrypted backups also include student and staff photos. Use them for any copy that leaves this computer; the passphrase cannot be recovered.
+                                    </p>
+                                    <div className="flex items-center gap-4">
+                                        <Input
+                                            id="backup-passphrase"
+                                            type="password"
+                                            placeholder="At least 12 characters"
+                                            value={backupPassphrase}
+                                            onChange={(e) => setBackupPassphrase(e.target.value)}
+                                        />
+                                        <Button onClick={handleBackup}>
+                                            <Save className="mr-2 h-4 w-4" /> {backupPassphrase ? 'Create Encrypted Backup' : 'Create Backup'}
+                                        </Button>
+                                    </div>
                                 </div>
                                 {backupPath && (
                                     <div className="p-3 bg-slate-100 rounded text-sm break-all">
                                         Last backup: {backupPath}
                                     </div>
                                 )}
+                                <div className="space-y-2 border-t pt-4">
+                                    <Label htmlFor="restore-path">Restore from backup file</Label>
+                                    <Input
+                                        id="restore-path"
+                                        placeholder="Path to a .db or .rbak backup"
+                                        value={restorePath}
+                                        onChange={(e) => setRestorePath(e.target.value)}
+                                    />
+                                    {restorePath.endsWith('.rbak') && (
+                                        <Input
+                                            type="password"
+                                            placeholder="Backup passphrase"
+                                            value={restorePassphrase}
+                                            onChange={(e) => setRestorePassphrase(e.target.value)}
+                                        />
+                                    )}
+                                    <Input
+                                        type="password"
+                                        placeholder="Your account password (to confirm)"
+                                        value={accountPassword}
+                                        onChange={(e) => setAccountPassword(e.target.value)}
+                                    />
+                                    <Button variant="outline" onClick={handleRestore} disabled={!restorePath}>
+                                        <RotateCcw className="mr-2 h-4 w-4" /> Restore from Backup
+                                    </Button>
+                                </div>
+                            </CardContent>
+                        </Card>
+
+                        <Card>
+                            <CardHeader>
+                                <CardTitle>Backup History & Schedule</CardTitle>
+                                <CardDescription>Verify, delete and schedule backups.</CardDescription>
+                            </CardHeader>
+                            <CardContent>
+                                <BackupCatalog refreshKey={backupPath} />
+                            </CardContent>
+                        </Card>
+
+                        <Card>
+                            <CardHeader>
+                                <CardTitle>Database Encryption</CardTitle>
+                                <CardDescription>Encrypt the database file with a passphrase or a key file.</CardDescription>
+                            </CardHeader>
+                            <CardContent>
+                                <DatabaseEncryption />
+                            </CardContent>
+                        </Card>
+
+                        <Card>
+                            <CardHeader>
+                                <CardTitle>Data Integrity</CardTitle>
+                                <CardDescription>Records with broken references to other records.</CardDescription>
+                            </CardHeader>
+                            <CardContent>
+                                <ForeignKeyViolations />
+                            </CardContent>
+                        </Card>
+
+                        <Card>
+                            <CardHeader>
+                                <CardTitle>Workspaces</CardTitle>
+                                <CardDescription>Separate databases, e.g. one per school, and where they are stored.</CardDescription>
+                            </CardHeader>
+                            <CardContent>
+                                <WorkspaceSettings />
                             </CardContent>
                         </Card>
 
@@ -5,11 +5,15 @@ import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card';
 import { PhotoUpload } from '@/components/PhotoUpload';
 import { Badge } from '@/components/ui/badge';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { EntityHistory } from '@/components/EntityHistory';
 import { AttendanceTab } from '@/components/student/AttendanceTab';
 import { AssessmentsTab } from '@/components/student/AssessmentsTab';
 import { NotesTab } from '@/components/student/NotesTab';
 import { InterventionsTab } from '@/components/student/InterventionsTab';
 import { invoke } from '@tauri-apps/api/core';
+import { errorMessage } from '@/lib/errors';
 
 interface StudentProfile {
     id: string;
@@ -27,12 +31,23 @@ interface StudentProfile {
     emergency_contact?: string;
 }
 
+type StudentContact = Pick<StudentProfile, 'address' | 'guardian_name' | 'guardian_contact' | 'emergency_contact'>;
+
+const CONTACT_FIELDS: { key: keyof StudentContact; label: string }[] = [
+    { key: 'address', label: 'Address' },
+    { key: 'guardian_name', label: 'Guardian' },
+    { key: 'guardian_contact', label: 'Guardian Contact' },
+    { key: 'emergency_contact', label: 'Emergency Contact' },
+];
+
 export function StudentProfilePage() {
     const { id } = useParams<{ id: string }>();
-    const { user } = useAuth();
+    const { user, hasPermission } = useAuth();
     const [student, setStudent] = useState<StudentProfile | null>(null);
     const [loading, setLoading] = useState(true);
     const [error, setError] = useState('');
+    const [contact, setContact] = useState<StudentContact | null>(null);
+    const [saveError, setSaveError] = useState('');
 
     useEffect(() => {
         if (!user || !id) return;
@@ -41,13 +56,13 @@ export function StudentProfilePage() {
             setLoading(true);
             try {
                 const data = await invoke<StudentProfile>('get_student_details', { 
-                    userId: user.id, 
+                    token: user.token, 
                     studentId: id 
                 });
                 setStudent(data);
             } catch (e) {
                 console.error("Failed to fetch student", e);
-                setError(String(e));
+                setError(errorMessage(e));
             } finally {
                 setLoading(false);
             }
@@ -62,6 +77,24 @@ export function StudentProfilePage() {
 
     const canSeeSensitive = user?.role === Role.ADMIN || user?.role === Role.TEACHER;
     const isFinance = user?.role === Role.MANAGEMENT_FINANCE;
+    const canEditSensitive = hasPermission('students.write_sensitive');
+    const canViewHistory = hasPermission('system.audit');
+
+    const saveContact = async () => {
+        if (!user || !contact) return;
+        try {
+            const saved = await invoke<StudentContact>('update_student_contact', {
+                token: user.token,
+                studentId: student.id,
+                contact,
+            });
+            setStudent({ ...student, ...saved });
+            setContact(null);
+            setSaveError('');
+        } catch (e) {
+            setSaveError(errorMessage(e));
+        }
+    };
 
     return (
         <div className="p-6 space-y-6">
@@ -94,6 +127,7 @@ export function StudentProfilePage() {
                     {!isFinance && <TabsTrigger value="assessments">Assessments</TabsTrigger>}
                     {!isFinance && <TabsTrigger value="notes">Notes</TabsTrigger>}
                     {!isFinance && <TabsTrigger value="interventions">Interventions</TabsTrigger>}
+                    {canViewHistory && <TabsTrigger value="history">History</TabsTrigger>}
                 </TabsList>
 
                 <TabsContent value="overview">
@@ -132,7 +166,39 @@ export function StudentProfilePage() {
 
                             {canSeeSensitive ? (
                                 <div className="border-t pt-4 mt-4">
-                                    <h3 className="font-semibold mb-3 text-red-600">Sensitive Information</h3>
+                                    <div className="flex items-center justify-between mb-3">
+                                        <h3 className="font-semibold text-red-600">Sensitive Information</h3>
+                                        {canEditSensitive && !contact && (
+                                            <Button variant="outline" size="sm" onClick={() => setContact({
+                                                address: student.address,
+                                                guardian_name: student.guardian_name,
+                                                guardian_contact: student.guardian_contact,
+                                                emergency_contact: student.emergency_contact,
+                                            })}>
+                                                Edit
+                                            </Button>
+                                        )}
+                                    </div>
+                                    {contact ? (
+                                        <div className="space-y-3">
+                                            <div className="grid grid-cols-2 gap-4">
+                                                {CONTACT_FIELDS.map(({ key, label }) => (
+                                                    <div key={key}>
+                                                        <p className="text-sm font-medium text-muted-foreground">{label}</p>
+                                                        <Input
+                                                            value={contact[key] ?? ''}
+                                                            onChange={(e) => setContact({ ...contact, [key]: e.target.value })}
+                                                        />
+                                                    </div>
+                                                ))}
+                                            </div>
+                                            {saveError && <div className="text-sm text-red-500">{saveError}</div>}
+                                            <div className="flex gap-2">
+                                                <Button size="sm" onClick={saveContact}>Save</Button>
+                                                <Button size="sm" variant="outline" onClick={() => { setContact(null); setSaveError(''); }}>Cancel</Button>
+                                            </div>
+                                        </div>
+                                    ) : (
                                     <div className="grid grid-cols-2 gap-4">
                                         <div>
                                             <p className="text-sm font-medium text-muted-foreground">Address</p>
@@ -147,6 +213,7 @@ export function StudentProfilePage() {
                                             <p>{student.emergency_contact}</p>
                                         </div>
                                     </div>
+                                    )}
                                 </div>
                             ) : (
                                 <div className="border-t pt-4 mt-4">
@@ -163,6 +230,14 @@ export function StudentProfilePage() {
                 {!isFinance && <TabsContent value="assessments"><AssessmentsTab studentId={student.id} /></TabsContent>}
                 {!isFinance && <TabsContent value="notes"><NotesTab studentId={student.id} /></TabsContent>}
                 {!isFinance && <TabsContent value="interventions"><InterventionsTab studentId={student.id} /></TabsContent>}
+                {canViewHistory && (
+                    <TabsContent value="history">
+                        <Card>
+                            <CardHeader><CardTitle>Access &amp; Change History</CardTitle></CardHeader>
+                            <CardContent><EntityHistory entityType="STUDENT" entityId={student.id} /></CardContent>
+                        </Card>
+                    </TabsContent>
+                )}
             </Tabs>
         </div>
     );
@@ -44,7 +44,7 @@ export function TrajectoryView() {
             // Hardcoded student_01 for demo view as this page is currently global
             // Ideally it should be per-student or aggregate
             const data = await invoke<TrajectoryResult>('compute_trajectory', { 
-                userId: user.id, 
+                token: user.token, 
                 studentId: 'student_01' 
             });
             setResult(data);
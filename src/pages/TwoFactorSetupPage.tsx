@@ -0,0 +1,99 @@
+import React, { useEffect, useState } from 'react';
+import { useAuth } from '../contexts/AuthContext';
+import { Navigate, useNavigate } from 'react-router-dom';
+import { invoke } from '@tauri-apps/api/core';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Card, CardHeader, CardTitle, CardContent, CardDescription, CardFooter } from '@/components/ui/card';
+import { Label } from '@/components/ui/label';
+import { errorMessage } from '@/lib/errors';
+
+interface TotpEnrollment {
+    secret: string;
+    otpauth_uri: string;
+}
+
+export function TwoFactorSetupPage() {
+    const { user, completeTwoFactorSetup } = useAuth();
+    const navigate = useNavigate();
+    const [enrollment, setEnrollment] = useState<TotpEnrollment | null>(null);
+    const [code, setCode] = useState('');
+    const [recoveryCodes, setRecoveryCodes] = useState<string[]>([]);
+    const [error, setError] = useState('');
+
+    useEffect(() => {
+        if (!user) return;
+        invoke<TotpEnrollment>('begin_totp_enrollment', { token: user.token })
+            .then(setEnrollment)
+            .catch((e) => setError(errorMessage(e)));
+    }, [user?.token]);
+
+    if (!user) return <Navigate to="/login" replace />;
+
+    const handleConfirm = async (e: React.FormEvent) => {
+        e.preventDefault();
+        try {
+            const codes = await invoke<string[]>('confirm_totp_enrollment', { token: user.token, code });
+            setRecoveryCodes(codes);
+            setError('');
+        } catch (err) {
+            setError(errorMessage(err));
+        }
+    };
+
+    const handleDone = () => {
+        completeTwoFactorSetup();
+        navigate('/dashboard');
+    };
+
+    return (
+        <div className="flex items-center justify-center h-screen bg-slate-100">
+            <Card className="w-[420px]">
+                <CardHeader>
+                    <CardTitle>Two-Factor Authentication</CardTitle>
+                    <CardDescription>Your role requires an authenticator app</CardDescription>
+                </CardHeader>
+                {recoveryCodes.length > 0 ? (
+                    <>
+                        <CardContent>
+                            <p className="text-sm mb-2">Store these recovery codes somewhere safe. Each can be used once and they will not be shown again.</p>
+                            <ul className="grid grid-cols-2 gap-1 font-mono text-sm">
+                                {recoveryCodes.map((c) => <li key={c}>{c}</li>)}
+                            </ul>
+                        </CardContent>
+                        <CardFooter>
+                            <Button className="w-full" onClick={handleDone}>Continue</Button>
+                        </CardFooter>
+                    </>
+                ) : (
+                    <form onSubmit={handleConfirm}>
+                        <CardContent>
+                            <div className="grid w-full items-center gap-4">
+                                {enrollment && (
+                                    <div className="text-sm space-y-1">
+                                        <p>Add this key to your authenticator app:</p>
+                                        <p className="font-mono break-all">{enrollment.secret}</p>
+                                        <p className="text-xs text-muted-foreground break-all">{enrollment.otpauth_uri}</p>
+                                    </div>
+                                )}
+                                <div className="flex flex-col space-y-1.5">
+                                    <Label htmlFor="code">Verification Code</Label>
+                                    <Input
+                                        id="code"
+                                        placeholder="6-digit code"
+                                        value={code}
+                                        onChange={(e: React.ChangeEvent<HTMLInputElement>) => setCode(e.target.value)}
+                                    />
+                                </div>
+                                {error && <p className="text-red-500 text-sm">{error}</p>}
+                            </div>
+                        </CardContent>
+                        <CardFooter>
+                            <Button type="submit" className="w-full">Verify</Button>
+                        </CardFooter>
+                    </form>
+                )}
+            </Card>
+        </div>
+    );
+}